@@ -37,6 +37,30 @@
 //! benchmark tests do not pass. `out_dir` specifies the directory where the
 //! markdown file will be written.
 //!
+//! Alongside the markdown, the latest run is written to `compute_units.json`,
+//! for CI dashboards and other tooling to consume. It records each
+//! benchmark's name, compute units, delta, and program result, along with the
+//! Solana version and a timestamp. Deltas are computed against the previous
+//! run's JSON file. Setting `csv(true)` also writes the run to
+//! `compute_units.csv`.
+//!
+//! ```json
+//! {
+//!   "timestamp": "2026-01-01T00:00:00Z",
+//!   "solana_version": "solana-cli 4.2.0",
+//!   "results": [
+//!     {
+//!       "name": "bench0",
+//!       "cus_consumed": 450,
+//!       "instruction_cus": [450],
+//!       "delta": -129,
+//!       "success": true,
+//!       "program_result": "Success"
+//!     }
+//!   ]
+//! }
+//! ```
+//!
 //! Developers can invoke this benchmark test with `cargo bench`. They may need
 //! to add a bench to the project's `Cargo.toml`.
 //!
@@ -56,6 +80,108 @@
 //! | bench2 | 1,204 | +754   |
 //! | bench3 | 2,811 | +2,361 |
 //! ```
+//!
+//! ### Instruction Sequences
+//!
+//! The bencher can also measure instruction chains, transactions, and sequences
+//! of instructions processed by a `MolluskContext`. Each of these benches
+//! records the total compute units, along with the compute units of each
+//! instruction, which are written to extra `Ix` columns in the markdown table.
+//! A sequence stops at its first failed instruction.
+//!
+//! ```rust,ignore
+//! let sequence = [deposit.clone(), withdraw];
+//! let context = Mollusk::new(&program_id, "my_program").with_context(store);
+//!
+//! MolluskComputeUnitBencher::new(mollusk)
+//!     .bench(("deposit", &deposit, &accounts))
+//!     .bench_chain(("deposit_withdraw", &sequence, &accounts))
+//!     .bench_transaction(("deposit_withdraw_tx", &sequence, &accounts))
+//!     .bench_context("context", &context, &sequence)
+//!     .execute();
+//! ```
+//!
+//! ```markdown
+//! | Name                | CUs   | Delta   | Ix 1  | Ix 2  |
+//! |---------------------|-------|---------|-------|-------|
+//! | deposit             | 1,204 | - new - | 1,204 |       |
+//! | deposit_withdraw    | 2,391 | - new - | 1,204 | 1,187 |
+//! ```
+//!
+//! ### Execution Time
+//!
+//! The bencher can also measure execution time, which is noisier than compute
+//! units but tracks interpreter and JIT sensitive performance. With
+//! `time_iterations`, each bench is repeated for the provided number of
+//! iterations after an unrecorded warm-up, which defaults to 10 iterations and
+//! can be set with `warmup_iterations`.
+//!
+//! ```rust,ignore
+//! MolluskComputeUnitBencher::new(mollusk)
+//!     .bench(("bench0", &instruction0, &accounts0))
+//!     .time_iterations(1_000)
+//!     .warmup_iterations(100)
+//!     .execute();
+//! ```
+//!
+//! The minimum, median, 95th percentile, and standard deviation of both the
+//! execution time reported by the SVM and the host wall-clock time, which also
+//! covers Mollusk's own overhead, are written in microseconds to `timings.md`
+//! and `timings.json`, and to `timings.csv` if `csv(true)` is set.
+//!
+//! ### Compute Units by Program
+//!
+//! With the `invocation-tree` feature enabled, each bench is broken down by
+//! invoked program and stack height, so that a regression can be traced to the
+//! benchmarked program or one of the programs it invokes. Each program's
+//! compute units exclude those of the programs it invoked, and are compared
+//! against the previous run.
+//!
+//! ```toml
+//! [dev-dependencies]
+//! mollusk-svm-bencher = { version = "0.15.0", features = ["invocation-tree"] }
+//! ```
+//!
+//! The breakdown is written to the JSON file under each result's `programs`,
+//! and to a collapsed section below the markdown table.
+//!
+//! ```markdown
+//! | Name   | Program                                      | Stack Height | Invocations | CUs    | Delta |
+//! |--------|----------------------------------------------|--------------|-------------|--------|-------|
+//! | create | ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL | 1            | 1           | 12,300 | +20   |
+//! | create | TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA  | 2            | 3           | 4,200  | --    |
+//! | create | 11111111111111111111111111111111             | 2            | 1           | -      | -     |
+//! ```
+//!
+//! Builtins, such as the System program, don't report their compute units when
+//! invoked through CPI, so theirs are attributed to their caller.
+//!
+//! ### Regression Thresholds
+//!
+//! The bencher can fail on compute unit regressions, measured against the
+//! previous run. `max_regression_percent` sets a threshold for every bench,
+//! `bench_max_regression_percent` overrides it for specific benches, and
+//! `max_cus` caps the compute units of specific benches. If any bench exceeds
+//! a threshold, `execute` panics with a summary of the offending benches, and
+//! no results are written, so the previous run remains the baseline.
+//!
+//! ```rust,ignore
+//! MolluskComputeUnitBencher::new(mollusk)
+//!     .bench(("transfer", &instruction0, &accounts0))
+//!     .bench(("withdraw", &instruction1, &accounts1))
+//!     .max_regression_percent(2.0)
+//!     .bench_max_regression_percent("withdraw", 5.0)
+//!     .max_cus("transfer", 4_500)
+//!     .baseline_update(BaselineUpdate::OnImprovement)
+//!     .execute();
+//! ```
+//!
+//! By default, every passing run replaces the baseline. With
+//! `BaselineUpdate::OnImprovement`, the baseline is only replaced if a bench
+//! improved or was added and no bench regressed, so that regressions within
+//! the thresholds do not accumulate across runs. `BaselineUpdate::Never` leaves
+//! the baseline untouched, such as when checking for regressions in CI.
+//!
 //! ### Matrix Benchmarking
 //!
 //! If you want to compare multiple program implementations (e.g., comparing
@@ -83,7 +209,8 @@
 //!     .execute();
 //! ```
 //! The matrix markdown file will contain entries comparing all provided
-//! programs.
+//! programs. The latest matrix run is also written to `mx_compute_units.json`,
+//! and to `mx_compute_units.csv` if `csv(true)` is set.
 //!
 //! ```markdown
 //! | Name     | CU (`program_v1`) | CU (`program_v2`) | CU (`program_v3`) |
@@ -92,32 +219,83 @@
 //! | `bench1` | 2,100             | 2,050             | 2,045             |
 //! ```
 
+pub mod regression;
 pub mod result;
+pub mod timing;
 
 use {
     chrono::Utc,
-    mollusk_svm::{result::ProgramResult, Mollusk},
+    mollusk_svm::{
+        account_store::AccountStore,
+        result::{InstructionResult, ProgramResult},
+        Mollusk, MolluskContext,
+    },
+    regression::{BaselineUpdate, RegressionThresholds},
     result::{
-        mx_write_results, write_results, MolluskComputeUnitBenchResult,
-        MolluskComputeUnitMatrixBenchResult,
+        bench_run, mx_write_csv_results, mx_write_results, write_csv_results, write_run,
+        MolluskComputeUnitBenchResult, MolluskComputeUnitMatrixBenchResult,
     },
     solana_account::Account,
     solana_instruction::Instruction,
     solana_pubkey::Pubkey,
     std::{path::PathBuf, process::Command},
+    timing::{time_bench, write_timing_csv, write_timing_run, TimingBenchRun},
 };
 
 /// A bench is a tuple of a name, an instruction, and a list of accounts.
 pub type Bench<'a> = (&'a str, &'a Instruction, &'a [(Pubkey, Account)]);
 
+/// A bench of multiple instructions, such as a chain or a transaction, is a
+/// tuple of a name, the instructions, and a list of accounts.
+pub type InstructionsBench<'a> = (&'a str, &'a [Instruction], &'a [(Pubkey, Account)]);
+
+/// The default number of unrecorded iterations run before sampling execution
+/// times.
+const DEFAULT_WARMUP_ITERATIONS: usize = 10;
+
+/// How a bench's instructions are processed.
+enum BenchKind<'a> {
+    Instruction(&'a Instruction, &'a [(Pubkey, Account)]),
+    Chain(&'a [Instruction], &'a [(Pubkey, Account)]),
+    Transaction(&'a [Instruction], &'a [(Pubkey, Account)]),
+    Context(Box<dyn Fn() -> Vec<InstructionResult> + 'a>),
+}
+
+impl<'a> BenchKind<'a> {
+    fn execute(&self, name: &'a str, mollusk: &Mollusk) -> MolluskComputeUnitBenchResult<'a> {
+        match self {
+            Self::Instruction(instruction, accounts) => {
+                let result = mollusk.process_instruction(instruction, accounts);
+                MolluskComputeUnitBenchResult::new(name, result)
+            }
+            Self::Chain(instructions, accounts) => {
+                let results = mollusk.process_instruction_chain_results(instructions, accounts);
+                MolluskComputeUnitBenchResult::from_instruction_results(name, &results)
+            }
+            Self::Transaction(instructions, accounts) => {
+                let result = mollusk.process_transaction_instructions(instructions, accounts, None);
+                MolluskComputeUnitBenchResult::from_transaction(name, result)
+            }
+            Self::Context(process) => {
+                MolluskComputeUnitBenchResult::from_instruction_results(name, &process())
+            }
+        }
+    }
+}
+
 /// Mollusk's compute unit bencher.
 ///
 /// Allows developers to bench test compute unit usage on their programs.
 pub struct MolluskComputeUnitBencher<'a> {
-    benches: Vec<Bench<'a>>,
+    baseline_update: BaselineUpdate,
+    benches: Vec<(&'a str, BenchKind<'a>)>,
+    csv: bool,
     mollusk: Mollusk,
     must_pass: bool,
     out_dir: PathBuf,
+    thresholds: RegressionThresholds,
+    time_iterations: usize,
+    warmup_iterations: usize,
 }
 
 impl<'a> MolluskComputeUnitBencher<'a> {
@@ -126,16 +304,76 @@ impl<'a> MolluskComputeUnitBencher<'a> {
         let mut out_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
         out_dir.push("benches");
         Self {
+            baseline_update: BaselineUpdate::default(),
             benches: Vec::new(),
+            csv: false,
             mollusk,
             must_pass: false,
             out_dir,
+            thresholds: RegressionThresholds::default(),
+            time_iterations: 0,
+            warmup_iterations: DEFAULT_WARMUP_ITERATIONS,
         }
     }
 
     /// Add a bench to the bencher.
     pub fn bench(mut self, bench: Bench<'a>) -> Self {
-        self.benches.push(bench);
+        let (name, instruction, accounts) = bench;
+        self.benches
+            .push((name, BenchKind::Instruction(instruction, accounts)));
+        self
+    }
+
+    /// Add a bench of an instruction chain to the bencher, processed like
+    /// `Mollusk::process_instruction_chain`.
+    pub fn bench_chain(mut self, bench: InstructionsBench<'a>) -> Self {
+        let (name, instructions, accounts) = bench;
+        self.benches
+            .push((name, BenchKind::Chain(instructions, accounts)));
+        self
+    }
+
+    /// Add a bench of a transaction to the bencher, processed like
+    /// `Mollusk::process_transaction_instructions`.
+    pub fn bench_transaction(mut self, bench: InstructionsBench<'a>) -> Self {
+        let (name, instructions, accounts) = bench;
+        self.benches
+            .push((name, BenchKind::Transaction(instructions, accounts)));
+        self
+    }
+
+    /// Add a bench of a sequence of instructions processed by a
+    /// `MolluskContext`, one after another, so that each instruction sees the
+    /// state left by the previous ones.
+    ///
+    /// The context's own `Mollusk` instance is used, and its account store is
+    /// updated as the instructions are processed.
+    pub fn bench_context<AS: AccountStore + 'a>(
+        mut self,
+        name: &'a str,
+        context: &'a MolluskContext<AS>,
+        instructions: &'a [Instruction],
+    ) -> Self {
+        let process = move || {
+            let mut results = vec![];
+            for instruction in instructions {
+                let result = context.process_instruction(instruction);
+                let failed = result.program_result.is_err();
+                results.push(result);
+                if failed {
+                    break;
+                }
+            }
+            results
+        };
+        self.benches
+            .push((name, BenchKind::Context(Box::new(process))));
+        self
+    }
+
+    /// Set whether the bencher should also write the results to a CSV file.
+    pub const fn csv(mut self, csv: bool) -> Self {
+        self.csv = csv;
         self
     }
 
@@ -151,29 +389,107 @@ impl<'a> MolluskComputeUnitBencher<'a> {
         self
     }
 
+    /// Set the maximum percentage by which any bench's compute units may
+    /// increase over the baseline. Benches with their own threshold, set with
+    /// `bench_max_regression_percent`, use that instead.
+    pub const fn max_regression_percent(mut self, percent: f64) -> Self {
+        self.thresholds.max_regression_percent = Some(percent);
+        self
+    }
+
+    /// Set the maximum percentage by which a specific bench's compute units
+    /// may increase over the baseline.
+    pub fn bench_max_regression_percent(mut self, name: &str, percent: f64) -> Self {
+        self.thresholds
+            .bench_max_regression_percent
+            .push((name.to_string(), percent));
+        self
+    }
+
+    /// Set the maximum compute units a specific bench may consume.
+    pub fn max_cus(mut self, name: &str, cus: u64) -> Self {
+        self.thresholds.max_cus.push((name.to_string(), cus));
+        self
+    }
+
+    /// Set when a passing run should replace the baseline.
+    pub const fn baseline_update(mut self, baseline_update: BaselineUpdate) -> Self {
+        self.baseline_update = baseline_update;
+        self
+    }
+
+    /// Enable execution time benchmarking, sampling each bench for the
+    /// provided number of iterations after warming up.
+    ///
+    /// Benches added with `bench_context` are repeated against the context's
+    /// account store, so each iteration sees the state left by the previous
+    /// ones.
+    pub const fn time_iterations(mut self, iterations: usize) -> Self {
+        self.time_iterations = iterations;
+        self
+    }
+
+    /// Set the number of unrecorded iterations to run before sampling
+    /// execution times. Defaults to 10.
+    pub const fn warmup_iterations(mut self, iterations: usize) -> Self {
+        self.warmup_iterations = iterations;
+        self
+    }
+
     /// Execute the benches.
+    ///
+    /// Panics with a summary of the offending benches if any exceeds a
+    /// regression threshold, in which case no results are written.
     pub fn execute(&mut self) {
         let table_header = Utc::now().to_string();
         let solana_version = get_solana_version();
-        let bench_results = std::mem::take(&mut self.benches)
-            .into_iter()
-            .map(|(name, instruction, accounts)| {
-                let result = self.mollusk.process_instruction(instruction, accounts);
-                match result.program_result {
-                    ProgramResult::Success => (),
-                    _ => {
-                        if self.must_pass {
-                            panic!(
-                                "Program execution failed, but `must_pass` was set. Error: {:?}",
-                                result.program_result
-                            );
-                        }
-                    }
-                }
-                MolluskComputeUnitBenchResult::new(name, result)
-            })
-            .collect::<Vec<_>>();
-        write_results(&self.out_dir, &table_header, &solana_version, bench_results);
+        let mut bench_results = vec![];
+        let mut timing_results = vec![];
+        for (name, bench) in std::mem::take(&mut self.benches) {
+            let result = bench.execute(name, &self.mollusk);
+            if !result.success() && self.must_pass {
+                panic!(
+                    "Program execution failed, but `must_pass` was set. Error: {}",
+                    result.program_result()
+                );
+            }
+            bench_results.push(result);
+
+            if self.time_iterations > 0 {
+                timing_results.push(time_bench(
+                    name,
+                    self.warmup_iterations,
+                    self.time_iterations,
+                    || bench.execute(name, &self.mollusk).execution_time(),
+                ));
+            }
+        }
+        let run = bench_run(&self.out_dir, &solana_version, bench_results);
+
+        let regressions = self.thresholds.check(&run);
+        if !regressions.is_empty() {
+            panic!("{}", regression::summary(&regressions));
+        }
+
+        if self.baseline_update.should_update(&run) {
+            write_run(&self.out_dir, &table_header, &run);
+            if self.csv {
+                write_csv_results(&self.out_dir, &run);
+            }
+        }
+
+        if self.time_iterations > 0 {
+            let timing_run = TimingBenchRun::new(
+                &solana_version,
+                self.warmup_iterations,
+                self.time_iterations,
+                timing_results,
+            );
+            write_timing_run(&self.out_dir, &table_header, &timing_run);
+            if self.csv {
+                write_timing_csv(&self.out_dir, &timing_run);
+            }
+        }
     }
 }
 
@@ -185,6 +501,7 @@ pub struct MolluskComputeUnitMatrixBencher<'a> {
     mollusk: &'a mut Mollusk,
     program_names: Vec<&'a str>,
     benches: Vec<Bench<'a>>,
+    csv: bool,
     must_pass: bool,
     out_dir: PathBuf,
 }
@@ -199,6 +516,7 @@ impl<'a> MolluskComputeUnitMatrixBencher<'a> {
             mollusk,
             program_names: Vec::new(),
             benches: Vec::new(),
+            csv: false,
             must_pass: false,
             out_dir,
         }
@@ -216,6 +534,12 @@ impl<'a> MolluskComputeUnitMatrixBencher<'a> {
         self
     }
 
+    /// Set whether the bencher should also write the results to a CSV file.
+    pub fn csv(mut self, csv: bool) -> Self {
+        self.csv = csv;
+        self
+    }
+
     /// Set whether the bencher should panic if a program execution fails.
     pub fn must_pass(mut self, must_pass: bool) -> Self {
         self.must_pass = must_pass;
@@ -261,12 +585,15 @@ impl<'a> MolluskComputeUnitMatrixBencher<'a> {
             bench_results.push(ix_results);
         }
 
-        mx_write_results(
+        let run = mx_write_results(
             &self.out_dir,
             &table_header,
             &solana_version,
             &bench_results,
         );
+        if let Some(run) = run.filter(|_| self.csv) {
+            mx_write_csv_results(&self.out_dir, &run);
+        }
     }
 }
 
@@ -0,0 +1,173 @@
+//! Compute unit regression thresholds.
+
+use {
+    crate::result::{ComputeUnitBenchRecord, ComputeUnitBenchRun},
+    num_format::{Locale, ToFormattedString},
+    std::fmt::{self, Display},
+};
+
+/// When a passing run should replace the baseline, which is the previous run
+/// that deltas and regressions are measured against.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BaselineUpdate {
+    /// Replace the baseline with every passing run.
+    #[default]
+    Always,
+    /// Only replace the baseline if a bench improved or was added and no
+    /// bench regressed, so that regressions within the thresholds do not
+    /// accumulate across runs.
+    OnImprovement,
+    /// Never replace the baseline, such as when checking for regressions in
+    /// CI.
+    Never,
+}
+
+impl BaselineUpdate {
+    /// Whether the baseline should be replaced with the provided run.
+    pub fn should_update(&self, run: &ComputeUnitBenchRun) -> bool {
+        match self {
+            Self::Always => true,
+            Self::OnImprovement => {
+                let improved = run
+                    .results
+                    .iter()
+                    .any(|record| record.delta.is_none_or(|delta| delta < 0));
+                let regressed = run
+                    .results
+                    .iter()
+                    .any(|record| record.delta.is_some_and(|delta| delta > 0));
+                improved && !regressed
+            }
+            Self::Never => false,
+        }
+    }
+}
+
+/// Thresholds a run's compute units must not exceed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RegressionThresholds {
+    /// The maximum increase over the baseline, as a percentage, for every
+    /// bench without its own threshold.
+    pub max_regression_percent: Option<f64>,
+    /// The maximum increase over the baseline, as a percentage, for specific
+    /// benches.
+    pub bench_max_regression_percent: Vec<(String, f64)>,
+    /// The maximum compute units specific benches may consume.
+    pub max_cus: Vec<(String, u64)>,
+}
+
+impl RegressionThresholds {
+    /// Check a run against the thresholds, returning every bench that
+    /// exceeded one.
+    pub fn check(&self, run: &ComputeUnitBenchRun) -> Vec<Regression> {
+        let mut regressions = vec![];
+        for record in &run.results {
+            if let Some((_, max_cus)) = self.max_cus.iter().find(|(name, _)| *name == record.name) {
+                if record.cus_consumed > *max_cus {
+                    regressions.push(Regression::new(record, RegressionKind::MaxCus(*max_cus)));
+                }
+            }
+
+            let max_percent = self
+                .bench_max_regression_percent
+                .iter()
+                .find(|(name, _)| *name == record.name)
+                .map(|(_, percent)| *percent)
+                .or(self.max_regression_percent);
+            if let (Some(max_percent), Some(delta)) = (max_percent, record.delta) {
+                if delta > 0 && regression_percent(record) > max_percent {
+                    regressions.push(Regression::new(
+                        record,
+                        RegressionKind::MaxRegressionPercent(max_percent),
+                    ));
+                }
+            }
+        }
+        regressions
+    }
+}
+
+/// The threshold a bench exceeded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RegressionKind {
+    /// The bench consumed more than the maximum compute units.
+    MaxCus(u64),
+    /// The bench regressed by more than the maximum percentage.
+    MaxRegressionPercent(f64),
+}
+
+/// A bench that exceeded a threshold.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Regression {
+    /// The name of the bench.
+    pub name: String,
+    /// The compute units consumed.
+    pub cus_consumed: u64,
+    /// The change in compute units since the baseline, if any.
+    pub delta: Option<i64>,
+    /// The threshold that was exceeded.
+    pub kind: RegressionKind,
+}
+
+impl Regression {
+    fn new(record: &ComputeUnitBenchRecord, kind: RegressionKind) -> Self {
+        Self {
+            name: record.name.clone(),
+            cus_consumed: record.cus_consumed,
+            delta: record.delta,
+            kind,
+        }
+    }
+}
+
+impl Display for Regression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cus = self.cus_consumed.to_formatted_string(&Locale::en);
+        match self.kind {
+            RegressionKind::MaxCus(max_cus) => write!(
+                f,
+                "{}: {} CUs exceeds the maximum of {} CUs",
+                self.name,
+                cus,
+                max_cus.to_formatted_string(&Locale::en),
+            ),
+            RegressionKind::MaxRegressionPercent(max_percent) => {
+                let delta = self.delta.unwrap_or_default();
+                let baseline = (self.cus_consumed as i64 - delta) as u64;
+                write!(
+                    f,
+                    "{}: {} CUs is {:+.2}% from {} CUs, exceeding the maximum regression of {}%",
+                    self.name,
+                    cus,
+                    percent(delta, baseline),
+                    baseline.to_formatted_string(&Locale::en),
+                    max_percent,
+                )
+            }
+        }
+    }
+}
+
+fn regression_percent(record: &ComputeUnitBenchRecord) -> f64 {
+    let delta = record.delta.unwrap_or_default();
+    percent(delta, (record.cus_consumed as i64 - delta) as u64)
+}
+
+fn percent(delta: i64, baseline: u64) -> f64 {
+    if baseline == 0 {
+        return if delta > 0 { f64::INFINITY } else { 0.0 };
+    }
+    delta as f64 / baseline as f64 * 100.0
+}
+
+/// Render a panic message summarizing the regressions.
+pub(crate) fn summary(regressions: &[Regression]) -> String {
+    let mut summary = format!(
+        "Compute unit regressions exceeded the configured thresholds in {} bench(es):",
+        regressions.len()
+    );
+    for regression in regressions {
+        summary.push_str(&format!("\n  {regression}"));
+    }
+    summary
+}
@@ -1,23 +1,242 @@
 //! Compute unit benchmarking results and checks.
 
 use {
-    mollusk_svm::result::InstructionResult,
+    chrono::{SecondsFormat, Utc},
+    mollusk_svm::result::{types::TransactionResult, InstructionResult, ProgramResult},
     num_format::{Locale, ToFormattedString},
+    serde::{Deserialize, Serialize},
     std::path::Path,
 };
+#[cfg(feature = "invocation-tree")]
+use {mollusk_svm::result::types::Invocation, solana_pubkey::Pubkey};
 
 pub struct MolluskComputeUnitBenchResult<'a> {
     name: &'a str,
     cus_consumed: u64,
+    instruction_cus: Vec<u64>,
+    execution_time: u64,
+    success: bool,
+    program_result: String,
+    #[cfg(feature = "invocation-tree")]
+    programs: Vec<ProgramComputeUnits>,
 }
 
 impl<'a> MolluskComputeUnitBenchResult<'a> {
     pub fn new(name: &'a str, result: InstructionResult) -> Self {
-        let cus_consumed = result.compute_units_consumed;
-        Self { name, cus_consumed }
+        Self {
+            name,
+            cus_consumed: result.compute_units_consumed,
+            instruction_cus: vec![result.compute_units_consumed],
+            execution_time: result.execution_time,
+            success: result.program_result.is_ok(),
+            program_result: format!("{:?}", result.program_result),
+            #[cfg(feature = "invocation-tree")]
+            programs: program_compute_units(&result.invocations),
+        }
+    }
+
+    /// Create a result from a sequence of instructions processed one after
+    /// another, such as an instruction chain. The sequence stops at the first
+    /// failed instruction, whose result is recorded.
+    pub fn from_instruction_results(name: &'a str, results: &[InstructionResult]) -> Self {
+        let instruction_cus = results
+            .iter()
+            .map(|result| result.compute_units_consumed)
+            .collect::<Vec<_>>();
+        let program_result = results.last().map_or(ProgramResult::Success, |result| {
+            result.program_result.clone()
+        });
+        Self {
+            name,
+            cus_consumed: instruction_cus.iter().sum(),
+            instruction_cus,
+            execution_time: results.iter().map(|result| result.execution_time).sum(),
+            success: program_result.is_ok(),
+            program_result: format!("{:?}", program_result),
+            #[cfg(feature = "invocation-tree")]
+            programs: program_compute_units(results.iter().flat_map(|result| &result.invocations)),
+        }
+    }
+
+    /// Create a result from a transaction.
+    pub fn from_transaction(name: &'a str, result: TransactionResult) -> Self {
+        Self {
+            name,
+            cus_consumed: result.compute_units_consumed,
+            instruction_cus: result.instruction_compute_units,
+            execution_time: result.execution_time,
+            success: result.program_result.is_ok(),
+            program_result: format!("{:?}", result.program_result),
+            #[cfg(feature = "invocation-tree")]
+            programs: program_compute_units(&result.invocations),
+        }
+    }
+
+    /// Whether every instruction executed successfully.
+    pub const fn success(&self) -> bool {
+        self.success
+    }
+
+    /// The execution time reported by the SVM, in microseconds.
+    pub const fn execution_time(&self) -> u64 {
+        self.execution_time
+    }
+
+    /// The program result, formatted with `Debug`.
+    pub fn program_result(&self) -> &str {
+        &self.program_result
+    }
+
+    fn record(&self, delta: Option<i64>) -> ComputeUnitBenchRecord {
+        ComputeUnitBenchRecord {
+            name: self.name.to_string(),
+            cus_consumed: self.cus_consumed,
+            instruction_cus: self.instruction_cus.clone(),
+            delta,
+            success: self.success,
+            program_result: self.program_result.clone(),
+            #[cfg(feature = "invocation-tree")]
+            programs: self.programs.clone(),
+            #[cfg(not(feature = "invocation-tree"))]
+            programs: vec![],
+        }
     }
 }
 
+/// Attribute the compute units of a tree of invocations to each program and
+/// stack height, excluding the compute units of the programs they invoked.
+///
+/// Builtins don't report their compute units when invoked through CPI, so
+/// theirs remain attributed to their caller.
+#[cfg(feature = "invocation-tree")]
+fn program_compute_units<'b>(
+    invocations: impl IntoIterator<Item = &'b Invocation>,
+) -> Vec<ProgramComputeUnits> {
+    let mut programs: Vec<(Pubkey, ProgramComputeUnits)> = vec![];
+    for invocation in invocations.into_iter().flat_map(Invocation::iter) {
+        let inner_cus = invocation
+            .inner_invocations
+            .iter()
+            .filter_map(|inner| inner.compute_units_consumed)
+            .sum::<u64>();
+        let cus_consumed = invocation
+            .compute_units_consumed
+            .map(|cus| cus.saturating_sub(inner_cus));
+
+        let position = programs.iter().position(|(program_id, program)| {
+            *program_id == invocation.program_id && program.stack_height == invocation.stack_height
+        });
+        let program = match position {
+            Some(position) => &mut programs[position].1,
+            None => {
+                programs.push((
+                    invocation.program_id,
+                    ProgramComputeUnits {
+                        program_id: invocation.program_id.to_string(),
+                        stack_height: invocation.stack_height,
+                        invocations: 0,
+                        cus_consumed: None,
+                        delta: None,
+                    },
+                ));
+                &mut programs.last_mut().unwrap().1
+            }
+        };
+        program.invocations += 1;
+        if let Some(cus_consumed) = cus_consumed {
+            *program.cus_consumed.get_or_insert(0) += cus_consumed;
+        }
+    }
+    programs.into_iter().map(|(_, program)| program).collect()
+}
+
+fn program_deltas(programs: &mut [ProgramComputeUnits], previous: &[ProgramComputeUnits]) {
+    for program in programs {
+        program.delta = previous
+            .iter()
+            .find(|prev| {
+                prev.program_id == program.program_id && prev.stack_height == program.stack_height
+            })
+            .and_then(|prev| Some(program.cus_consumed? as i64 - prev.cus_consumed? as i64));
+    }
+}
+
+/// The compute units a program consumed at a stack height within a
+/// benchmark, excluding those of the programs it invoked.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProgramComputeUnits {
+    /// The invoked program.
+    pub program_id: String,
+    /// The stack height of the invocations, where `1` is a top-level
+    /// instruction and each CPI adds one.
+    pub stack_height: u32,
+    /// The number of times the program was invoked at this stack height.
+    pub invocations: u64,
+    /// The compute units consumed, or `None` if the program doesn't report
+    /// them, such as a builtin invoked through CPI. In that case, they're
+    /// attributed to the caller.
+    pub cus_consumed: Option<u64>,
+    /// The change in compute units since the previous run, if the program
+    /// was present at this stack height in the previous run.
+    pub delta: Option<i64>,
+}
+
+/// A single benchmark's result, as written to the JSON and CSV files.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComputeUnitBenchRecord {
+    /// The name of the benchmark.
+    pub name: String,
+    /// The compute units consumed, in total across all instructions.
+    pub cus_consumed: u64,
+    /// The compute units consumed by each instruction, in order.
+    #[serde(default)]
+    pub instruction_cus: Vec<u64>,
+    /// The change in compute units since the previous run, if the benchmark
+    /// was present in the previous run.
+    pub delta: Option<i64>,
+    /// Whether the program executed successfully.
+    pub success: bool,
+    /// The program result, formatted with `Debug`.
+    pub program_result: String,
+    /// The compute units consumed by each invoked program, in the order they
+    /// were first invoked. Only recorded with the `invocation-tree` feature.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub programs: Vec<ProgramComputeUnits>,
+}
+
+/// A single run of the compute unit bencher, as written to
+/// `compute_units.json`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComputeUnitBenchRun {
+    /// When the run was executed, in RFC 3339 format.
+    pub timestamp: String,
+    /// The Solana CLI version the run was executed with.
+    pub solana_version: String,
+    /// The results of each benchmark, in the order they were defined.
+    pub results: Vec<ComputeUnitBenchRecord>,
+}
+
+/// A single program's results within a matrix run.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComputeUnitMatrixProgramRecord {
+    /// The name of the program.
+    pub program_name: String,
+    /// The results of each benchmark, in the order they were defined.
+    pub results: Vec<ComputeUnitBenchRecord>,
+}
+
+/// A single run of the matrix compute unit bencher, as written to
+/// `mx_compute_units.json`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComputeUnitMatrixBenchRun {
+    /// When the run was executed, in RFC 3339 format.
+    pub timestamp: String,
+    /// The Solana CLI version the run was executed with.
+    pub solana_version: String,
+    /// The results of each program.
+    pub programs: Vec<ComputeUnitMatrixProgramRecord>,
+}
+
 pub struct MolluskComputeUnitMatrixBenchResult<'a> {
     program_name: &'a str,
     results: Vec<MolluskComputeUnitBenchResult<'a>>,
@@ -37,48 +256,108 @@ impl<'a> MolluskComputeUnitMatrixBenchResult<'a> {
     }
 }
 
+/// Write the results of a run to `compute_units.md` and
+/// `compute_units.json` in the output directory, returning the run.
+///
+/// Deltas are computed against the previous run, as with `bench_run`. A new
+/// markdown table is only written if any results changed, while the JSON file
+/// is always replaced with the latest run.
 pub fn write_results(
     out_dir: &Path,
     table_header: &str,
     solana_version: &str,
     results: Vec<MolluskComputeUnitBenchResult>,
-) {
-    let path = out_dir.join("compute_units.md");
+) -> ComputeUnitBenchRun {
+    let run = bench_run(out_dir, solana_version, results);
+    write_run(out_dir, table_header, &run);
+    run
+}
 
-    // Load the existing bench content and parse the most recent table.
-    let mut no_changes = true;
-    let existing_content = if path.exists() {
-        Some(std::fs::read_to_string(&path).unwrap())
-    } else {
-        None
-    };
-    let previous = existing_content
+/// Evaluate the results of a run against the previous run in the output
+/// directory, without writing anything.
+///
+/// Deltas are computed against the previous run's `compute_units.json`,
+/// falling back to the most recent table in `compute_units.md` if no JSON file
+/// exists yet. A malformed table is treated as no previous run.
+pub fn bench_run(
+    out_dir: &Path,
+    solana_version: &str,
+    results: Vec<MolluskComputeUnitBenchResult>,
+) -> ComputeUnitBenchRun {
+    // Load the previous run, preferring the structured file.
+    let previous_run = read_json::<ComputeUnitBenchRun>(&out_dir.join("compute_units.json"));
+    let previous = previous_run
         .as_ref()
-        .map(|content| parse_last_md_table(content));
+        .map(|run| {
+            run.results
+                .iter()
+                .map(|record| (record.name.clone(), record.cus_consumed))
+                .collect::<Vec<_>>()
+        })
+        .or_else(|| {
+            let content = std::fs::read_to_string(out_dir.join("compute_units.md")).ok()?;
+            parse_last_md_table(&content)
+        });
+
+    let records = results
+        .into_iter()
+        .map(|result| {
+            let delta = previous.as_ref().and_then(|prev_results| {
+                prev_results
+                    .iter()
+                    .find(|(name, _)| name == result.name)
+                    .map(|(_, cus_consumed)| result.cus_consumed as i64 - *cus_consumed as i64)
+            });
+            let mut record = result.record(delta);
+            // Per-program compute units are only recorded in the JSON file.
+            if let Some(previous_record) = previous_run
+                .as_ref()
+                .and_then(|run| run.results.iter().find(|prev| prev.name == record.name))
+            {
+                program_deltas(&mut record.programs, &previous_record.programs);
+            }
+            record
+        })
+        .collect();
+
+    ComputeUnitBenchRun {
+        timestamp: timestamp(),
+        solana_version: solana_version.to_string(),
+        results: records,
+    }
+}
+
+/// Write a run to `compute_units.md` and `compute_units.json` in the output
+/// directory.
+///
+/// A new markdown table is only written if any results changed, while the
+/// JSON file is always replaced with the run.
+pub fn write_run(out_dir: &Path, table_header: &str, run: &ComputeUnitBenchRun) {
+    // Benches of multiple instructions get a column per instruction.
+    let instruction_columns = run
+        .results
+        .iter()
+        .map(|record| record.instruction_cus.len())
+        .max()
+        .filter(|count| *count > 1)
+        .unwrap_or_default();
 
     // Prepare to write a new table.
-    let mut md_table = md_header(table_header, solana_version);
+    let mut no_changes = true;
+    let mut md_table = md_header(table_header, &run.solana_version, instruction_columns);
 
-    // Evaluate the results against the previous table, if any.
+    // Evaluate the results against the previous run, if any.
     // If there are changes, write a new table.
-    // If there are no changes, break out and abort gracefully.
-    for result in results {
-        let delta = match previous.as_ref().and_then(|prev_results| {
-            prev_results
-                .iter()
-                .find(|prev_result| prev_result.name == result.name)
-        }) {
-            Some(prev) => {
-                let delta = result.cus_consumed as i64 - prev.cus_consumed as i64;
-                if delta == 0 {
-                    "--".to_string()
+    // If there are no changes, skip the table.
+    for record in &run.results {
+        let delta_cell = match record.delta {
+            Some(0) => "--".to_string(),
+            Some(delta) => {
+                no_changes = false;
+                if delta > 0 {
+                    format!("+{}", delta.to_formatted_string(&Locale::en))
                 } else {
-                    no_changes = false;
-                    if delta > 0 {
-                        format!("+{}", delta.to_formatted_string(&Locale::en))
-                    } else {
-                        delta.to_formatted_string(&Locale::en)
-                    }
+                    delta.to_formatted_string(&Locale::en)
                 }
             }
             None => {
@@ -87,32 +366,100 @@ pub fn write_results(
             }
         };
         md_table.push_str(&format!(
-            "| {} | {} | {} |\n",
-            result.name, result.cus_consumed, delta
+            "| {} | {} | {} |",
+            record.name, record.cus_consumed, delta_cell
         ));
+        for column in 0..instruction_columns {
+            match record.instruction_cus.get(column) {
+                Some(cus) => md_table.push_str(&format!(" {} |", cus)),
+                None => md_table.push_str("  |"),
+            }
+        }
+        md_table.push('\n');
+    }
+
+    // Break each bench down by program in a collapsed section.
+    if run.results.iter().any(|record| !record.programs.is_empty()) {
+        md_table.push_str(
+            r#"
+<details>
+<summary>Compute units by program</summary>
+
+| Name | Program | Stack Height | Invocations | CUs | Delta |
+|------|---------|--------------|-------------|------|-------|
+"#,
+        );
+        for record in &run.results {
+            for program in &record.programs {
+                if program.delta.is_some_and(|delta| delta != 0) {
+                    no_changes = false;
+                }
+                md_table.push_str(&format!(
+                    "| {} | {} | {} | {} | {} | {} |\n",
+                    record.name,
+                    program.program_id,
+                    program.stack_height,
+                    program.invocations,
+                    program.cus_consumed.map_or("-".to_string(), |cus| {
+                        cus.to_formatted_string(&Locale::en)
+                    }),
+                    match program.delta {
+                        Some(0) => "--".to_string(),
+                        Some(delta) if delta > 0 => {
+                            format!("+{}", delta.to_formatted_string(&Locale::en))
+                        }
+                        Some(delta) => delta.to_formatted_string(&Locale::en),
+                        None => "-".to_string(),
+                    },
+                ));
+            }
+        }
+        md_table.push_str("\n</details>\n");
     }
 
     // Only create a new table if there were changes.
     if !no_changes {
         md_table.push('\n');
-        prepend_to_md_file(&path, &md_table);
+        prepend_to_md_file(&out_dir.join("compute_units.md"), &md_table);
+    }
+
+    write_json(&out_dir.join("compute_units.json"), run);
+}
+
+/// Write the results of a run to `compute_units.csv` in the output directory,
+/// replacing any previous file.
+pub fn write_csv_results(out_dir: &Path, run: &ComputeUnitBenchRun) {
+    let mut csv = String::from(
+        "name,cus_consumed,instruction_cus,delta,success,program_result,solana_version,timestamp\n",
+    );
+    for record in &run.results {
+        csv.push_str(&csv_row(record, &[], &run.solana_version, &run.timestamp));
     }
+    write_file(&out_dir.join("compute_units.csv"), &csv);
 }
 
-fn md_header(table_header: &str, solana_version: &str) -> String {
+fn md_header(table_header: &str, solana_version: &str, instruction_columns: usize) -> String {
+    let mut header_row = String::from("| Name | CUs | Delta |");
+    let mut separator = String::from("|------|------|-------|");
+    for column in 1..=instruction_columns {
+        header_row.push_str(&format!(" Ix {} |", column));
+        separator.push_str("------|");
+    }
     format!(
         r#"#### {}
 
 Solana CLI Version: {}
 
-| Name | CUs | Delta |
-|------|------|-------|
+{}
+{}
 "#,
-        table_header, solana_version,
+        table_header, solana_version, header_row, separator,
     )
 }
 
-fn parse_last_md_table(content: &str) -> Vec<MolluskComputeUnitBenchResult<'_>> {
+// Parse the names and compute units of the most recent table in
+// `compute_units.md`, returning `None` if the table is malformed.
+fn parse_last_md_table(content: &str) -> Option<Vec<(String, u64)>> {
     let mut results = vec![];
 
     for line in content.lines().skip(6) {
@@ -121,23 +468,25 @@ fn parse_last_md_table(content: &str) -> Vec<MolluskComputeUnitBenchResult<'_>>
         }
 
         let mut parts = line.split('|').skip(1).map(str::trim);
-        let name = parts.next().unwrap();
-        let cus_consumed = parts.next().unwrap().parse().unwrap();
+        let name = parts.next()?;
+        let cus_consumed = parts.next()?.replace(',', "").parse().ok()?;
 
-        results.push(MolluskComputeUnitBenchResult { name, cus_consumed });
+        results.push((name.to_string(), cus_consumed));
     }
 
-    results
+    Some(results)
 }
 
+/// Write the results of a matrix run to `mx_compute_units.md` and
+/// `mx_compute_units.json` in the output directory, returning the run.
 pub fn mx_write_results(
     out_dir: &Path,
     table_header: &str,
     solana_version: &str,
     results: &[MolluskComputeUnitMatrixBenchResult],
-) {
+) -> Option<ComputeUnitMatrixBenchRun> {
     if results.is_empty() {
-        return;
+        return None;
     }
     let mut mx_md_table = mx_md_header(table_header, solana_version, results);
 
@@ -162,6 +511,44 @@ pub fn mx_write_results(
 
     let path = out_dir.join("mx_compute_units.md");
     prepend_to_md_file(&path, &mx_md_table);
+
+    let run = ComputeUnitMatrixBenchRun {
+        timestamp: timestamp(),
+        solana_version: solana_version.to_string(),
+        programs: results
+            .iter()
+            .map(|program| ComputeUnitMatrixProgramRecord {
+                program_name: program.program_name.to_string(),
+                results: program
+                    .results
+                    .iter()
+                    .map(|result| result.record(None))
+                    .collect(),
+            })
+            .collect(),
+    };
+    write_json(&out_dir.join("mx_compute_units.json"), &run);
+    Some(run)
+}
+
+/// Write the results of a matrix run to `mx_compute_units.csv` in the output
+/// directory, replacing any previous file.
+pub fn mx_write_csv_results(out_dir: &Path, run: &ComputeUnitMatrixBenchRun) {
+    let mut csv = String::from(
+        "program_name,name,cus_consumed,instruction_cus,delta,success,program_result,\
+         solana_version,timestamp\n",
+    );
+    for program in &run.programs {
+        for record in &program.results {
+            csv.push_str(&csv_row(
+                record,
+                &[&program.program_name],
+                &run.solana_version,
+                &run.timestamp,
+            ));
+        }
+    }
+    write_file(&out_dir.join("mx_compute_units.csv"), &csv);
 }
 
 fn mx_md_header(
@@ -191,7 +578,76 @@ Solana CLI Version: {}
     )
 }
 
-fn prepend_to_md_file(path: &Path, content: &str) {
+pub(crate) fn timestamp() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Option<T> {
+    let content = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+pub(crate) fn write_json<T: Serialize>(path: &Path, value: &T) {
+    let mut json = serde_json::to_string_pretty(value).unwrap();
+    json.push('\n');
+    write_file(path, &json);
+}
+
+fn csv_row(
+    record: &ComputeUnitBenchRecord,
+    leading: &[&str],
+    solana_version: &str,
+    timestamp: &str,
+) -> String {
+    let delta = record
+        .delta
+        .map(|delta| delta.to_string())
+        .unwrap_or_default();
+    let cus_consumed = record.cus_consumed.to_string();
+    let instruction_cus = record
+        .instruction_cus
+        .iter()
+        .map(|cus| cus.to_string())
+        .collect::<Vec<_>>()
+        .join(";");
+    let success = record.success.to_string();
+    let mut fields = leading.to_vec();
+    fields.extend([
+        record.name.as_str(),
+        &cus_consumed,
+        &instruction_cus,
+        &delta,
+        &success,
+        &record.program_result,
+        solana_version,
+        timestamp,
+    ]);
+    let mut row = fields
+        .into_iter()
+        .map(csv_field)
+        .collect::<Vec<_>>()
+        .join(",");
+    row.push('\n');
+    row
+}
+
+// Quote a CSV field if it contains a delimiter, quote, or newline.
+pub(crate) fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub(crate) fn write_file(path: &Path, content: &str) {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).unwrap();
+    }
+    std::fs::write(path, content).unwrap();
+}
+
+pub(crate) fn prepend_to_md_file(path: &Path, content: &str) {
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent).unwrap();
     }
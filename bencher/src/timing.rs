@@ -0,0 +1,196 @@
+//! Wall-clock execution time benchmarking.
+
+use {
+    crate::result::{csv_field, prepend_to_md_file, timestamp, write_file, write_json},
+    serde::{Deserialize, Serialize},
+    std::path::Path,
+};
+
+/// Summary statistics of a set of time samples, in microseconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TimingStats {
+    /// The fastest sample.
+    pub min: f64,
+    /// The median sample, by nearest rank.
+    pub median: f64,
+    /// The 95th percentile sample, by nearest rank.
+    pub p95: f64,
+    /// The mean of the samples.
+    pub mean: f64,
+    /// The population standard deviation of the samples.
+    pub stddev: f64,
+}
+
+impl TimingStats {
+    /// Compute the statistics of the provided samples.
+    pub fn from_samples(samples: &[f64]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let percentile = |percent: f64| {
+            let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+            sorted[rank.saturating_sub(1)]
+        };
+
+        let count = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / count;
+        let variance = samples
+            .iter()
+            .map(|sample| (sample - mean).powi(2))
+            .sum::<f64>()
+            / count;
+
+        Self {
+            min: sorted[0],
+            median: percentile(50.0),
+            p95: percentile(95.0),
+            mean,
+            stddev: variance.sqrt(),
+        }
+    }
+}
+
+/// The timings of a single benchmark.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimingBenchRecord {
+    /// The name of the benchmark.
+    pub name: String,
+    /// The execution time reported by the SVM, which covers only program
+    /// execution.
+    pub execution_time: TimingStats,
+    /// The host wall-clock time, which also covers Mollusk's own overhead,
+    /// such as account loading and result construction.
+    pub wall_clock: TimingStats,
+}
+
+/// A timing benchmark run.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimingBenchRun {
+    /// The time the run was recorded, in RFC 3339 format.
+    pub timestamp: String,
+    /// The Solana CLI version, or `Unknown` if unavailable.
+    pub solana_version: String,
+    /// The number of unrecorded iterations run before sampling.
+    pub warmup_iterations: usize,
+    /// The number of sampled iterations.
+    pub iterations: usize,
+    /// The timings of each benchmark.
+    pub results: Vec<TimingBenchRecord>,
+}
+
+impl TimingBenchRun {
+    /// Create a run from the benchmark timings.
+    pub fn new(
+        solana_version: &str,
+        warmup_iterations: usize,
+        iterations: usize,
+        results: Vec<TimingBenchRecord>,
+    ) -> Self {
+        Self {
+            timestamp: timestamp(),
+            solana_version: solana_version.to_string(),
+            warmup_iterations,
+            iterations,
+            results,
+        }
+    }
+}
+
+/// Sample a benchmark's execution time and wall-clock time.
+///
+/// `execute` processes the benchmark once and returns the execution time
+/// reported by the SVM, in microseconds. It is first called
+/// `warmup_iterations` times without recording, then `iterations` times.
+pub fn time_bench(
+    name: &str,
+    warmup_iterations: usize,
+    iterations: usize,
+    mut execute: impl FnMut() -> u64,
+) -> TimingBenchRecord {
+    for _ in 0..warmup_iterations {
+        execute();
+    }
+
+    let mut execution_time = Vec::with_capacity(iterations);
+    let mut wall_clock = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
+        let start = std::time::Instant::now();
+        let execution_us = execute();
+        wall_clock.push(start.elapsed().as_secs_f64() * 1_000_000.0);
+        execution_time.push(execution_us as f64);
+    }
+
+    TimingBenchRecord {
+        name: name.to_string(),
+        execution_time: TimingStats::from_samples(&execution_time),
+        wall_clock: TimingStats::from_samples(&wall_clock),
+    }
+}
+
+/// Write a timing run to the output directory.
+///
+/// A new markdown table is prepended to `timings.md`, and `timings.json` is
+/// replaced with the run.
+pub fn write_timing_run(out_dir: &Path, table_header: &str, run: &TimingBenchRun) {
+    let mut md_table = format!(
+        r#"#### {}
+
+Solana CLI Version: {}
+
+Iterations: {} ({} warm-up)
+
+| Name | Exec Min (µs) | Exec Median (µs) | Exec p95 (µs) | Exec Std Dev (µs) | Wall Min (µs) | Wall Median (µs) | Wall p95 (µs) | Wall Std Dev (µs) |
+|------|------|------|------|------|------|------|------|------|
+"#,
+        table_header, run.solana_version, run.iterations, run.warmup_iterations,
+    );
+    for record in &run.results {
+        let (exec, wall) = (&record.execution_time, &record.wall_clock);
+        md_table.push_str(&format!(
+            "| {} | {:.1} | {:.1} | {:.1} | {:.1} | {:.1} | {:.1} | {:.1} | {:.1} |\n",
+            record.name,
+            exec.min,
+            exec.median,
+            exec.p95,
+            exec.stddev,
+            wall.min,
+            wall.median,
+            wall.p95,
+            wall.stddev,
+        ));
+    }
+    md_table.push('\n');
+
+    prepend_to_md_file(&out_dir.join("timings.md"), &md_table);
+    write_json(&out_dir.join("timings.json"), run);
+}
+
+/// Write a timing run to `timings.csv` in the output directory, replacing any
+/// previous file.
+pub fn write_timing_csv(out_dir: &Path, run: &TimingBenchRun) {
+    let mut csv = String::from(
+        "name,exec_min_us,exec_median_us,exec_p95_us,exec_mean_us,exec_stddev_us,wall_min_us,\
+         wall_median_us,wall_p95_us,wall_mean_us,wall_stddev_us,iterations,solana_version,\
+         timestamp\n",
+    );
+    for record in &run.results {
+        let mut fields = vec![csv_field(&record.name)];
+        for stats in [&record.execution_time, &record.wall_clock] {
+            fields.extend(
+                [stats.min, stats.median, stats.p95, stats.mean, stats.stddev]
+                    .map(|value| format!("{value:.3}")),
+            );
+        }
+        fields.extend([
+            run.iterations.to_string(),
+            csv_field(&run.solana_version),
+            csv_field(&run.timestamp),
+        ]);
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    write_file(&out_dir.join("timings.csv"), &csv);
+}
@@ -0,0 +1,164 @@
+use {
+    mollusk_svm::result::{InstructionResult, ProgramResult},
+    mollusk_svm_bencher::result::{
+        write_csv_results, write_results, ComputeUnitBenchRun, MolluskComputeUnitBenchResult,
+    },
+    solana_program_error::ProgramError,
+    std::path::PathBuf,
+};
+
+fn out_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mollusk-bencher-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn bench_result(name: &str, cus_consumed: u64) -> MolluskComputeUnitBenchResult<'_> {
+    MolluskComputeUnitBenchResult::new(
+        name,
+        InstructionResult {
+            compute_units_consumed: cus_consumed,
+            ..Default::default()
+        },
+    )
+}
+
+fn read_run(out_dir: &std::path::Path) -> ComputeUnitBenchRun {
+    let json = std::fs::read_to_string(out_dir.join("compute_units.json")).unwrap();
+    serde_json::from_str(&json).unwrap()
+}
+
+fn deltas(run: &ComputeUnitBenchRun) -> Vec<(&str, u64, Option<i64>)> {
+    run.results
+        .iter()
+        .map(|record| (record.name.as_str(), record.cus_consumed, record.delta))
+        .collect()
+}
+
+#[test]
+fn test_json_results() {
+    let out_dir = out_dir("json");
+
+    let run = write_results(
+        &out_dir,
+        "first",
+        "solana-cli 4.2.0",
+        vec![bench_result("bench0", 100), bench_result("bench1", 200)],
+    );
+    assert_eq!(read_run(&out_dir), run);
+    assert_eq!(run.solana_version, "solana-cli 4.2.0");
+    assert_eq!(
+        deltas(&run),
+        vec![("bench0", 100, None), ("bench1", 200, None)]
+    );
+
+    // Deltas are computed against the previous JSON file.
+    let failed = MolluskComputeUnitBenchResult::new(
+        "bench2",
+        InstructionResult {
+            compute_units_consumed: 5,
+            program_result: ProgramResult::Failure(ProgramError::InvalidArgument),
+            ..Default::default()
+        },
+    );
+    let run = write_results(
+        &out_dir,
+        "second",
+        "solana-cli 4.2.0",
+        vec![
+            bench_result("bench0", 90),
+            bench_result("bench1", 200),
+            failed,
+        ],
+    );
+    assert_eq!(read_run(&out_dir), run);
+    assert_eq!(
+        deltas(&run),
+        vec![
+            ("bench0", 90, Some(-10)),
+            ("bench1", 200, Some(0)),
+            ("bench2", 5, None),
+        ]
+    );
+    assert!(run.results[0].success);
+    assert!(!run.results[2].success);
+    assert_eq!(run.results[2].program_result, "Failure(InvalidArgument)");
+
+    let markdown = std::fs::read_to_string(out_dir.join("compute_units.md")).unwrap();
+    assert!(markdown.starts_with("#### second"));
+    assert!(markdown.contains("| bench0 | 90 | -10 |\n"));
+
+    write_csv_results(&out_dir, &run);
+    let csv = std::fs::read_to_string(out_dir.join("compute_units.csv")).unwrap();
+    let timestamp = &run.timestamp;
+    assert_eq!(
+        csv,
+        format!(
+            "\
+name,cus_consumed,instruction_cus,delta,success,program_result,solana_version,timestamp
+bench0,90,90,-10,true,Success,solana-cli 4.2.0,{timestamp}
+bench1,200,200,0,true,Success,solana-cli 4.2.0,{timestamp}
+bench2,5,5,,false,Failure(InvalidArgument),solana-cli 4.2.0,{timestamp}
+"
+        )
+    );
+
+    std::fs::remove_dir_all(&out_dir).unwrap();
+}
+
+#[test]
+fn test_json_results_from_markdown() {
+    let out_dir = out_dir("markdown");
+
+    write_results(
+        &out_dir,
+        "first",
+        "Unknown",
+        vec![bench_result("bench0", 100)],
+    );
+
+    // Without a JSON file, deltas fall back to the markdown table.
+    std::fs::remove_file(out_dir.join("compute_units.json")).unwrap();
+    let run = write_results(
+        &out_dir,
+        "second",
+        "Unknown",
+        vec![bench_result("bench0", 150)],
+    );
+    assert_eq!(deltas(&run), vec![("bench0", 150, Some(50))]);
+
+    // Compute units are parsed with thousands separators.
+    std::fs::remove_file(out_dir.join("compute_units.json")).unwrap();
+    write_results(
+        &out_dir,
+        "third",
+        "Unknown",
+        vec![bench_result("bench0", 1_500)],
+    );
+    std::fs::remove_file(out_dir.join("compute_units.json")).unwrap();
+    let run = write_results(
+        &out_dir,
+        "fourth",
+        "Unknown",
+        vec![bench_result("bench0", 1_600)],
+    );
+    assert_eq!(deltas(&run), vec![("bench0", 1_600, Some(100))]);
+
+    // A malformed table is treated as no previous run, rather than panicking.
+    std::fs::remove_file(out_dir.join("compute_units.json")).unwrap();
+    std::fs::write(
+        out_dir.join("compute_units.md"),
+        "#### malformed\n\nSolana CLI Version: Unknown\n\n| Name | CUs |\n|---|---|\n| bench0 | \
+         many |\n",
+    )
+    .unwrap();
+    let run = write_results(
+        &out_dir,
+        "fifth",
+        "Unknown",
+        vec![bench_result("bench0", 1_600)],
+    );
+    assert_eq!(deltas(&run), vec![("bench0", 1_600, None)]);
+
+    std::fs::remove_dir_all(&out_dir).unwrap();
+}
@@ -0,0 +1,208 @@
+#![cfg(feature = "invocation-tree")]
+
+use {
+    mollusk_svm::{
+        file::read_file,
+        program::{
+            create_program_account_loader_v2, keyed_account_for_system_program, loader_keys,
+        },
+        Mollusk,
+    },
+    mollusk_svm_bencher::{
+        result::{ComputeUnitBenchRun, ProgramComputeUnits},
+        MolluskComputeUnitBencher,
+    },
+    solana_account::Account,
+    solana_instruction::{AccountMeta, Instruction},
+    solana_program_option::COption,
+    solana_program_pack::Pack,
+    solana_pubkey::Pubkey,
+    spl_token_interface::state::Mint,
+    std::path::{Path, PathBuf},
+};
+
+const TOKEN_ELF_PATH: &str = "../programs/token/src/elf/token.so";
+const ASSOCIATED_TOKEN_ELF_PATH: &str = "../programs/token/src/elf/associated_token.so";
+const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    solana_pubkey::pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+const TOKEN_PROGRAM_ID: Pubkey =
+    solana_pubkey::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+fn out_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "mollusk-bencher-programs-{name}-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn read_run(out_dir: &Path) -> ComputeUnitBenchRun {
+    let json = std::fs::read_to_string(out_dir.join("compute_units.json")).unwrap();
+    serde_json::from_str(&json).unwrap()
+}
+
+// An initialized SPL Token mint with no freeze authority.
+fn mint_data() -> Vec<u8> {
+    let mint = Mint {
+        mint_authority: COption::Some(Pubkey::new_unique()),
+        supply: 0,
+        decimals: 9,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    };
+    let mut data = vec![0; Mint::LEN];
+    Mint::pack(mint, &mut data).unwrap();
+    data
+}
+
+// Set up the creation of an associated token account, which CPIs into the
+// token and system programs.
+fn create_associated_token_account() -> (Mollusk, Instruction, Vec<(Pubkey, Account)>) {
+    let payer = Pubkey::new_unique();
+    let wallet = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let associated_token_address = Pubkey::find_program_address(
+        &[wallet.as_ref(), TOKEN_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0;
+
+    let token_elf = read_file(TOKEN_ELF_PATH);
+
+    let mut mollusk = Mollusk::default();
+    mollusk.add_program_with_loader_and_elf(
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+        &loader_keys::LOADER_V2,
+        &read_file(ASSOCIATED_TOKEN_ELF_PATH),
+    );
+    mollusk.add_program_with_loader_and_elf(&TOKEN_PROGRAM_ID, &loader_keys::LOADER_V2, &token_elf);
+
+    let instruction = Instruction::new_with_bytes(
+        ASSOCIATED_TOKEN_PROGRAM_ID,
+        &[],
+        vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(associated_token_address, false),
+            AccountMeta::new_readonly(wallet, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(solana_sdk_ids::system_program::id(), false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ],
+    );
+    let accounts = vec![
+        (
+            payer,
+            Account::new(10_000_000_000, 0, &solana_sdk_ids::system_program::id()),
+        ),
+        (associated_token_address, Account::default()),
+        (wallet, Account::default()),
+        (mint, {
+            let mut account = Account::new(1_000_000_000, 0, &TOKEN_PROGRAM_ID);
+            account.data = mint_data();
+            account
+        }),
+        keyed_account_for_system_program(),
+        (
+            TOKEN_PROGRAM_ID,
+            create_program_account_loader_v2(&token_elf),
+        ),
+    ];
+
+    (mollusk, instruction, accounts)
+}
+
+#[test]
+fn test_program_compute_units() {
+    solana_logger::setup_with("");
+    let out_dir = out_dir("bench");
+    let (mollusk, instruction, accounts) = create_associated_token_account();
+
+    let bench = |mollusk: Mollusk| {
+        MolluskComputeUnitBencher::new(mollusk)
+            .bench(("create", &instruction, &accounts))
+            .must_pass(true)
+            .out_dir(out_dir.to_str().unwrap())
+            .execute();
+        read_run(&out_dir)
+    };
+
+    let run = bench(mollusk);
+    let record = &run.results[0];
+    let programs = record
+        .programs
+        .iter()
+        .map(|program| {
+            (
+                program.program_id.as_str(),
+                program.stack_height,
+                program.invocations,
+            )
+        })
+        .collect::<Vec<_>>();
+    let associated_token = ASSOCIATED_TOKEN_PROGRAM_ID.to_string();
+    let token = TOKEN_PROGRAM_ID.to_string();
+    let system = solana_sdk_ids::system_program::id().to_string();
+    assert_eq!(
+        programs,
+        vec![
+            (associated_token.as_str(), 1, 1),
+            (token.as_str(), 2, 3),
+            (system.as_str(), 2, 1),
+        ]
+    );
+
+    // Builtins don't report their compute units through CPI, so they're
+    // attributed to the caller, and the breakdown adds up to the total.
+    assert_eq!(record.programs[2].cus_consumed, None);
+    let attributed = record
+        .programs
+        .iter()
+        .filter_map(|program| program.cus_consumed)
+        .sum::<u64>();
+    assert_eq!(attributed, record.cus_consumed);
+    assert!(record.programs[1].cus_consumed.unwrap() > 0);
+    assert!(record
+        .programs
+        .iter()
+        .all(|program| program.delta.is_none()));
+
+    let markdown = std::fs::read_to_string(out_dir.join("compute_units.md")).unwrap();
+    assert!(markdown.contains("<summary>Compute units by program</summary>"));
+    assert!(markdown.contains(&format!("| create | {system} | 2 | 1 | - | - |\n")));
+
+    // Deltas are computed per program against the previous run. The same
+    // instruction is benched again, with a fresh instance of the programs.
+    let (mollusk, _, _) = create_associated_token_account();
+    let run = bench(mollusk);
+    let deltas = run.results[0]
+        .programs
+        .iter()
+        .map(|program| program.delta)
+        .collect::<Vec<_>>();
+    assert_eq!(deltas, vec![Some(0), Some(0), None]);
+
+    std::fs::remove_dir_all(&out_dir).unwrap();
+}
+
+#[test]
+fn test_program_compute_units_json() {
+    let program = ProgramComputeUnits {
+        program_id: TOKEN_PROGRAM_ID.to_string(),
+        stack_height: 2,
+        invocations: 3,
+        cus_consumed: Some(4_200),
+        delta: Some(-10),
+    };
+    let json = serde_json::to_value(&program).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "program_id": TOKEN_PROGRAM_ID.to_string(),
+            "stack_height": 2,
+            "invocations": 3,
+            "cus_consumed": 4_200,
+            "delta": -10,
+        })
+    );
+}
@@ -0,0 +1,187 @@
+use {
+    mollusk_svm::Mollusk,
+    mollusk_svm_bencher::{
+        regression::BaselineUpdate,
+        result::{write_run, ComputeUnitBenchRecord, ComputeUnitBenchRun},
+        MolluskComputeUnitBencher,
+    },
+    solana_account::Account,
+    solana_pubkey::Pubkey,
+    std::{
+        panic::{catch_unwind, AssertUnwindSafe},
+        path::{Path, PathBuf},
+    },
+};
+
+// A system transfer consumes 150 compute units.
+const TRANSFER_CUS: u64 = 150;
+
+fn out_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "mollusk-bencher-regression-{name}-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+// Write a baseline run with the given compute units for each bench.
+fn write_baseline(out_dir: &Path, benches: &[(&str, u64)]) {
+    let run = ComputeUnitBenchRun {
+        timestamp: "baseline".to_string(),
+        solana_version: "Unknown".to_string(),
+        results: benches
+            .iter()
+            .map(|(name, cus_consumed)| ComputeUnitBenchRecord {
+                name: name.to_string(),
+                cus_consumed: *cus_consumed,
+                instruction_cus: vec![*cus_consumed],
+                delta: None,
+                success: true,
+                program_result: "Success".to_string(),
+                programs: vec![],
+            })
+            .collect(),
+    };
+    write_run(out_dir, "baseline", &run);
+}
+
+fn baseline_timestamp(out_dir: &Path) -> String {
+    let json = std::fs::read_to_string(out_dir.join("compute_units.json")).unwrap();
+    serde_json::from_str::<ComputeUnitBenchRun>(&json)
+        .unwrap()
+        .timestamp
+}
+
+// Bench two system transfers, named "transfer" and "transfer_again", with the
+// provided configuration.
+fn bench(
+    out_dir: &Path,
+    configure: impl FnOnce(MolluskComputeUnitBencher) -> MolluskComputeUnitBencher,
+) -> Result<(), String> {
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let instruction = solana_system_interface::instruction::transfer(&sender, &recipient, 42);
+    let accounts = [
+        (
+            sender,
+            Account::new(100_000_000, 0, &solana_sdk_ids::system_program::id()),
+        ),
+        (
+            recipient,
+            Account::new(100_000_000, 0, &solana_sdk_ids::system_program::id()),
+        ),
+    ];
+
+    let bencher = MolluskComputeUnitBencher::new(Mollusk::default())
+        .bench(("transfer", &instruction, &accounts))
+        .bench(("transfer_again", &instruction, &accounts))
+        .must_pass(true)
+        .out_dir(out_dir.to_str().unwrap());
+    let mut bencher = configure(bencher);
+    catch_unwind(AssertUnwindSafe(|| bencher.execute()))
+        .map_err(|panic| panic.downcast_ref::<String>().cloned().unwrap_or_default())
+}
+
+#[test]
+fn test_max_regression_percent() {
+    solana_logger::setup_with("");
+    let out_dir = out_dir("percent");
+
+    // 150 is within 2% of 148.
+    write_baseline(&out_dir, &[("transfer", 148), ("transfer_again", 148)]);
+    bench(&out_dir, |bencher| bencher.max_regression_percent(2.0)).unwrap();
+    assert_ne!(baseline_timestamp(&out_dir), "baseline");
+
+    // 150 is not within 2% of 140, but the per-bench threshold overrides
+    // the global one.
+    write_baseline(&out_dir, &[("transfer", 140), ("transfer_again", 140)]);
+    let panic = bench(&out_dir, |bencher| {
+        bencher
+            .max_regression_percent(2.0)
+            .bench_max_regression_percent("transfer_again", 10.0)
+    })
+    .unwrap_err();
+    assert_eq!(
+        panic,
+        "Compute unit regressions exceeded the configured thresholds in 1 bench(es):\n  transfer: \
+         150 CUs is +7.14% from 140 CUs, exceeding the maximum regression of 2%"
+    );
+
+    // The baseline is left untouched.
+    assert_eq!(baseline_timestamp(&out_dir), "baseline");
+
+    std::fs::remove_dir_all(&out_dir).unwrap();
+}
+
+#[test]
+fn test_max_cus() {
+    solana_logger::setup_with("");
+    let out_dir = out_dir("max-cus");
+
+    // Caps apply without a baseline.
+    bench(&out_dir, |bencher| {
+        bencher
+            .max_cus("transfer", TRANSFER_CUS)
+            .max_cus("transfer_again", 1_000)
+    })
+    .unwrap();
+
+    let panic = bench(&out_dir, |bencher| {
+        bencher
+            .max_cus("transfer", 100)
+            .max_cus("transfer_again", 149)
+    })
+    .unwrap_err();
+    assert_eq!(
+        panic,
+        "Compute unit regressions exceeded the configured thresholds in 2 bench(es):\n  transfer: \
+         150 CUs exceeds the maximum of 100 CUs\n  transfer_again: 150 CUs exceeds the maximum of \
+         149 CUs"
+    );
+
+    std::fs::remove_dir_all(&out_dir).unwrap();
+}
+
+#[test]
+fn test_baseline_update() {
+    solana_logger::setup_with("");
+    let out_dir = out_dir("baseline");
+
+    // Without improvements, the baseline is kept.
+    write_baseline(&out_dir, &[("transfer", 149), ("transfer_again", 150)]);
+    bench(&out_dir, |bencher| {
+        bencher.baseline_update(BaselineUpdate::OnImprovement)
+    })
+    .unwrap();
+    assert_eq!(baseline_timestamp(&out_dir), "baseline");
+
+    // With an improvement, the baseline is replaced.
+    write_baseline(&out_dir, &[("transfer", 160), ("transfer_again", 150)]);
+    bench(&out_dir, |bencher| {
+        bencher.baseline_update(BaselineUpdate::OnImprovement)
+    })
+    .unwrap();
+    assert_ne!(baseline_timestamp(&out_dir), "baseline");
+
+    // An improvement doesn't replace the baseline if another bench regressed
+    // in the same run.
+    write_baseline(&out_dir, &[("transfer", 160), ("transfer_again", 149)]);
+    bench(&out_dir, |bencher| {
+        bencher.baseline_update(BaselineUpdate::OnImprovement)
+    })
+    .unwrap();
+    assert_eq!(baseline_timestamp(&out_dir), "baseline");
+
+    // The baseline is never replaced, and no CSV is written alongside it.
+    std::fs::remove_dir_all(&out_dir).unwrap();
+    write_baseline(&out_dir, &[("transfer", 160), ("transfer_again", 150)]);
+    bench(&out_dir, |bencher| {
+        bencher.baseline_update(BaselineUpdate::Never).csv(true)
+    })
+    .unwrap();
+    assert_eq!(baseline_timestamp(&out_dir), "baseline");
+    assert!(!out_dir.join("compute_units.csv").exists());
+
+    std::fs::remove_dir_all(&out_dir).unwrap();
+}
@@ -0,0 +1,100 @@
+use {
+    mollusk_svm::Mollusk,
+    mollusk_svm_bencher::{result::ComputeUnitBenchRun, MolluskComputeUnitBencher},
+    solana_account::Account,
+    solana_pubkey::Pubkey,
+    std::{collections::HashMap, path::PathBuf},
+};
+
+fn out_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "mollusk-bencher-sequences-{name}-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn read_run(out_dir: &std::path::Path) -> ComputeUnitBenchRun {
+    let json = std::fs::read_to_string(out_dir.join("compute_units.json")).unwrap();
+    serde_json::from_str(&json).unwrap()
+}
+
+#[test]
+fn test_bench_sequences() {
+    solana_logger::setup_with("");
+    let out_dir = out_dir("bench");
+
+    let alice = Pubkey::new_unique();
+    let bob = Pubkey::new_unique();
+    let instructions = [
+        solana_system_interface::instruction::transfer(&alice, &bob, 1_000),
+        solana_system_interface::instruction::transfer(&bob, &alice, 500),
+    ];
+    let accounts = [
+        (
+            alice,
+            Account::new(100_000_000, 0, &solana_sdk_ids::system_program::id()),
+        ),
+        (
+            bob,
+            Account::new(100_000_000, 0, &solana_sdk_ids::system_program::id()),
+        ),
+    ];
+
+    let context = Mollusk::default().with_context(HashMap::from(accounts.clone()));
+
+    // The second transfer fails, since Alice cannot cover it.
+    let overdraft = [
+        solana_system_interface::instruction::transfer(&alice, &bob, 1_000),
+        solana_system_interface::instruction::transfer(&alice, &bob, 100_000_000),
+        solana_system_interface::instruction::transfer(&bob, &alice, 500),
+    ];
+
+    MolluskComputeUnitBencher::new(Mollusk::default())
+        .bench(("single", &instructions[0], &accounts))
+        .bench_chain(("chain", &instructions, &accounts))
+        .bench_transaction(("transaction", &instructions, &accounts))
+        .bench_context("context", &context, &instructions)
+        .bench_chain(("overdraft", &overdraft, &accounts))
+        .out_dir(out_dir.to_str().unwrap())
+        .execute();
+
+    let run = read_run(&out_dir);
+    let results = run
+        .results
+        .iter()
+        .map(|record| {
+            (
+                record.name.as_str(),
+                record.cus_consumed,
+                record.instruction_cus.clone(),
+                record.success,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        results,
+        vec![
+            ("single", 150, vec![150], true),
+            ("chain", 300, vec![150, 150], true),
+            ("transaction", 300, vec![150, 150], true),
+            ("context", 300, vec![150, 150], true),
+            ("overdraft", 300, vec![150, 150], false),
+        ]
+    );
+
+    // The context's account store reflects the processed instructions.
+    let store = context.account_store.borrow();
+    assert_eq!(store.get(&alice).unwrap().lamports, 99_999_500);
+    assert_eq!(store.get(&bob).unwrap().lamports, 100_000_500);
+    drop(store);
+
+    // The markdown table has a column per instruction.
+    let markdown = std::fs::read_to_string(out_dir.join("compute_units.md")).unwrap();
+    assert!(markdown.contains("| Name | CUs | Delta | Ix 1 | Ix 2 |\n"));
+    assert!(markdown.contains("| single | 150 | - new - | 150 |  |\n"));
+    assert!(markdown.contains("| chain | 300 | - new - | 150 | 150 |\n"));
+
+    std::fs::remove_dir_all(&out_dir).unwrap();
+}
@@ -0,0 +1,98 @@
+use {
+    mollusk_svm::Mollusk,
+    mollusk_svm_bencher::{
+        timing::{TimingBenchRun, TimingStats},
+        MolluskComputeUnitBencher,
+    },
+    solana_account::Account,
+    solana_pubkey::Pubkey,
+    std::{collections::HashMap, path::PathBuf},
+};
+
+fn out_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "mollusk-bencher-timing-{name}-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn test_timing_stats() {
+    let samples = (1..=20).rev().map(f64::from).collect::<Vec<_>>();
+    let stats = TimingStats::from_samples(&samples);
+    assert_eq!(stats.min, 1.0);
+    assert_eq!(stats.median, 10.0);
+    assert_eq!(stats.p95, 19.0);
+    assert_eq!(stats.mean, 10.5);
+    assert!((stats.stddev - 5.766_281).abs() < 1e-6);
+
+    assert_eq!(TimingStats::from_samples(&[]), TimingStats::default());
+}
+
+#[test]
+fn test_time_iterations() {
+    solana_logger::setup_with("");
+    let out_dir = out_dir("bench");
+
+    let alice = Pubkey::new_unique();
+    let bob = Pubkey::new_unique();
+    let instruction = solana_system_interface::instruction::transfer(&alice, &bob, 1_000);
+    let accounts = [
+        (
+            alice,
+            Account::new(100_000_000, 0, &solana_sdk_ids::system_program::id()),
+        ),
+        (
+            bob,
+            Account::new(100_000_000, 0, &solana_sdk_ids::system_program::id()),
+        ),
+    ];
+    let context = Mollusk::default().with_context(HashMap::from(accounts.clone()));
+    let context_instructions = [instruction.clone()];
+
+    MolluskComputeUnitBencher::new(Mollusk::default())
+        .bench(("transfer", &instruction, &accounts))
+        .bench_context("context", &context, &context_instructions)
+        .time_iterations(5)
+        .warmup_iterations(2)
+        .csv(true)
+        .out_dir(out_dir.to_str().unwrap())
+        .execute();
+
+    // The context bench ran once for compute units, twice to warm up, and
+    // five times for timings.
+    let store = context.account_store.borrow();
+    assert_eq!(store.get(&alice).unwrap().lamports, 100_000_000 - 8 * 1_000);
+    drop(store);
+
+    let json = std::fs::read_to_string(out_dir.join("timings.json")).unwrap();
+    let run = serde_json::from_str::<TimingBenchRun>(&json).unwrap();
+    assert_eq!(run.warmup_iterations, 2);
+    assert_eq!(run.iterations, 5);
+    let names = run
+        .results
+        .iter()
+        .map(|record| record.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["transfer", "context"]);
+    for record in &run.results {
+        let wall_clock = &record.wall_clock;
+        assert!(wall_clock.min > 0.0);
+        assert!(wall_clock.min <= wall_clock.median && wall_clock.median <= wall_clock.p95);
+    }
+
+    let markdown = std::fs::read_to_string(out_dir.join("timings.md")).unwrap();
+    assert!(markdown.contains("Iterations: 5 (2 warm-up)"));
+    assert!(markdown.contains("\n| transfer | "));
+
+    let csv = std::fs::read_to_string(out_dir.join("timings.csv")).unwrap();
+    assert_eq!(csv.lines().count(), 3);
+    assert!(csv.lines().nth(1).unwrap().starts_with("transfer,"));
+
+    // Compute unit results are still written.
+    assert!(out_dir.join("compute_units.json").exists());
+
+    std::fs::remove_dir_all(&out_dir).unwrap();
+}
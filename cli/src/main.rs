@@ -19,6 +19,19 @@ use {
 
 #[derive(Subcommand)]
 enum SubCommand {
+    /// Generate lcov and Cobertura coverage reports from the coverage data
+    /// recorded by Mollusk's `coverage` feature.
+    #[cfg(feature = "coverage")]
+    Coverage {
+        /// The directory the coverage data was recorded to
+        /// (`SBF_COVERAGE_DIR`).
+        #[arg(required = true)]
+        coverage_dir: String,
+        /// Directory to write the reports to. Defaults to the coverage
+        /// directory.
+        #[arg(short, long)]
+        output_dir: Option<String>,
+    },
     /// Execute a fixture using Mollusk and inspect the effects.
     ExecuteFixture {
         /// The path to the ELF file.
@@ -47,7 +60,8 @@ enum SubCommand {
         /// else.
         ///
         /// Note this flag is ignored if `inputs_only` is set, and will
-        /// override a `Compare::ComputeUnits` check in the config file.
+        /// override a `Compare::ComputeUnits` or `Compare::ComputeUnitsWithin`
+        /// check in the config file.
         #[arg(long)]
         ignore_compute_units: bool,
         /// Just execute the fixture without any validation.
@@ -99,8 +113,8 @@ enum SubCommand {
         /// Skip comparing compute unit consumption, but compare everything
         /// else.
         ///
-        /// Note this flag will override a `Compare::ComputeUnits` check in the
-        /// config file.
+        /// Note this flag will override a `Compare::ComputeUnits` or
+        /// `Compare::ComputeUnitsWithin` check in the config file.
         #[arg(long)]
         ignore_compute_units: bool,
         /// Enable emission of program logs to stdout. Disabled by default.
@@ -158,6 +172,24 @@ fn add_elf_to_mollusk(mollusk: &mut Mollusk, elf_path: &str, program_id: &Pubkey
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     match Cli::parse().command {
+        #[cfg(feature = "coverage")]
+        SubCommand::Coverage {
+            coverage_dir,
+            output_dir,
+        } => {
+            let report = mollusk_svm::coverage::CoverageReport::load(&coverage_dir)?;
+            report.write_reports(output_dir.as_deref().unwrap_or(&coverage_dir))?;
+            for program in &report.programs {
+                println!(
+                    "{}: {}/{} lines, {}/{} instructions",
+                    program.program_id,
+                    program.lines_covered(),
+                    program.lines_valid(),
+                    program.executed_instructions,
+                    program.instructions,
+                );
+            }
+        }
         SubCommand::ExecuteFixture {
             elf_path,
             fixture,
@@ -179,7 +211,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let checks = if let Some(config_path) = config {
                 let mut checks = ConfigFile::try_load(&config_path)?.checks;
                 if ignore_compute_units {
-                    checks.retain(|check| !matches!(check, Compare::ComputeUnits));
+                    checks.retain(|check| !check.is_compute_units());
                 }
                 checks
             } else if ignore_compute_units {
@@ -228,7 +260,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let checks = if let Some(config_path) = config {
                 let mut checks = ConfigFile::try_load(&config_path)?.checks;
                 if ignore_compute_units {
-                    checks.retain(|check| !matches!(check, Compare::ComputeUnits));
+                    checks.retain(|check| !check.is_compute_units());
                 }
                 checks
             } else if ignore_compute_units {
@@ -34,6 +34,12 @@ pub enum MolluskError<'a> {
     /// execution.
     #[error("    [MOLLUSK]: Failed to prepare instructions for execution")]
     InstructionPreparationFailed,
+    /// Rent state transitions are enforced, but the instruction APIs can't
+    /// report the resulting transaction error.
+    #[error(
+        "    [MOLLUSK]: Rent state transitions are only enforced by the transaction APIs: {0}"
+    )]
+    RentStateTransitionsUnsupported(&'a str),
 }
 
 pub trait MolluskPanic<T> {
@@ -1,12 +1,87 @@
 //! Post-invocation effects of an instruction.
 
 use {
-    super::proto::{AcctState as ProtoAccount, InstrEffects as ProtoEffects},
+    super::proto::{
+        AcctState as ProtoAccount, InnerInstr as ProtoInnerInstruction,
+        InnerInstrAcct as ProtoInnerInstructionAccount, InstrEffects as ProtoEffects,
+    },
     solana_account::Account,
+    solana_instruction::{AccountMeta, Instruction},
     solana_keccak_hasher::Hasher,
     solana_pubkey::Pubkey,
 };
 
+/// An inner instruction (CPI) invoked by an instruction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InnerInstruction {
+    /// The invoked instruction.
+    pub instruction: Instruction,
+    /// The stack height of the invocation.
+    pub stack_height: u32,
+}
+
+impl From<ProtoInnerInstruction> for InnerInstruction {
+    fn from(value: ProtoInnerInstruction) -> Self {
+        let ProtoInnerInstruction {
+            program_id,
+            accounts,
+            data,
+            stack_height,
+        } = value;
+
+        let program_id_bytes: [u8; 32] = program_id.try_into().expect("Invalid bytes for pubkey");
+        let accounts = accounts
+            .into_iter()
+            .map(|account| {
+                let pubkey_bytes: [u8; 32] = account
+                    .address
+                    .try_into()
+                    .expect("Invalid bytes for pubkey");
+                AccountMeta {
+                    pubkey: Pubkey::new_from_array(pubkey_bytes),
+                    is_signer: account.is_signer,
+                    is_writable: account.is_writable,
+                }
+            })
+            .collect();
+
+        Self {
+            instruction: Instruction {
+                program_id: Pubkey::new_from_array(program_id_bytes),
+                accounts,
+                data,
+            },
+            stack_height,
+        }
+    }
+}
+
+impl From<InnerInstruction> for ProtoInnerInstruction {
+    fn from(value: InnerInstruction) -> Self {
+        let InnerInstruction {
+            instruction,
+            stack_height,
+        } = value;
+
+        let accounts = instruction
+            .accounts
+            .into_iter()
+            .map(|account| ProtoInnerInstructionAccount {
+                address: account.pubkey.to_bytes().to_vec(),
+                is_signer: account.is_signer,
+                is_writable: account.is_writable,
+            })
+            .collect();
+
+        Self {
+            program_id: instruction.program_id.to_bytes().to_vec(),
+            accounts,
+            data: instruction.data,
+            stack_height,
+        }
+    }
+}
+
 /// Represents the effects of a single instruction.
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Effects {
@@ -19,6 +94,9 @@ pub struct Effects {
     pub return_data: Vec<u8>,
     /// Resulting accounts with state, to be checked post-simulation.
     pub resulting_accounts: Vec<(Pubkey, Account)>,
+    /// Inner instructions (CPIs) invoked by the instruction, in the order
+    /// they were invoked.
+    pub inner_instructions: Vec<InnerInstruction>,
 }
 
 impl From<ProtoEffects> for Effects {
@@ -29,10 +107,13 @@ impl From<ProtoEffects> for Effects {
             program_result,
             return_data,
             resulting_accounts,
+            inner_instructions,
         } = value;
 
         let resulting_accounts: Vec<(Pubkey, Account)> =
             resulting_accounts.into_iter().map(Into::into).collect();
+        let inner_instructions: Vec<InnerInstruction> =
+            inner_instructions.into_iter().map(Into::into).collect();
 
         Self {
             compute_units_consumed,
@@ -40,6 +121,7 @@ impl From<ProtoEffects> for Effects {
             program_result,
             return_data,
             resulting_accounts,
+            inner_instructions,
         }
     }
 }
@@ -52,10 +134,13 @@ impl From<Effects> for ProtoEffects {
             program_result,
             return_data,
             resulting_accounts,
+            inner_instructions,
         } = value;
 
         let resulting_accounts: Vec<ProtoAccount> =
             resulting_accounts.into_iter().map(Into::into).collect();
+        let inner_instructions: Vec<ProtoInnerInstruction> =
+            inner_instructions.into_iter().map(Into::into).collect();
 
         Self {
             compute_units_consumed,
@@ -63,6 +148,7 @@ impl From<Effects> for ProtoEffects {
             program_result,
             return_data,
             resulting_accounts,
+            inner_instructions,
         }
     }
 }
@@ -72,4 +158,13 @@ pub(crate) fn hash_proto_effects(hasher: &mut Hasher, effects: &ProtoEffects) {
     hasher.hash(&effects.execution_time.to_le_bytes());
     hasher.hash(&effects.program_result.to_le_bytes());
     crate::account::hash_proto_accounts(hasher, &effects.resulting_accounts);
+    for inner_instruction in &effects.inner_instructions {
+        hasher.hash(&inner_instruction.program_id);
+        for account in &inner_instruction.accounts {
+            hasher.hash(&account.address);
+            hasher.hash(&[account.is_signer as u8, account.is_writable as u8]);
+        }
+        hasher.hash(&inner_instruction.data);
+        hasher.hash(&inner_instruction.stack_height.to_le_bytes());
+    }
 }
@@ -0,0 +1,126 @@
+//! Address lookup table support for versioned (v0) transaction messages.
+//!
+//! Lookup table addresses are resolved from the provided accounts, using the
+//! clock and slot hashes sysvars to determine whether a table is active.
+
+use {
+    crate::sysvar::Sysvars,
+    solana_account::Account,
+    solana_address_lookup_table_interface::{
+        error::AddressLookupError,
+        state::{AddressLookupTable, LookupTableMeta},
+    },
+    solana_message::{
+        v0::{LoadedAddresses, MessageAddressTableLookup},
+        AddressLoader, SanitizedMessage, SanitizedVersionedMessage, VersionedMessage,
+    },
+    solana_pubkey::Pubkey,
+    solana_rent::Rent,
+    solana_transaction_error::{AddressLoaderError, SanitizeMessageError, TransactionError},
+    std::{borrow::Cow, collections::HashSet},
+};
+
+/// Create an active address lookup table account containing the provided
+/// addresses.
+///
+/// The addresses are treated as if they were added in a prior slot, so they
+/// are immediately available for lookups.
+pub fn create_address_lookup_table_account(addresses: &[Pubkey]) -> Account {
+    let data = AddressLookupTable {
+        meta: LookupTableMeta {
+            last_extended_slot_start_index: u8::try_from(addresses.len()).unwrap_or(u8::MAX),
+            ..LookupTableMeta::default()
+        },
+        addresses: Cow::Borrowed(addresses),
+    }
+    .serialize_for_tests()
+    .unwrap();
+    let lamports = Rent::default().minimum_balance(data.len());
+    Account {
+        lamports,
+        data,
+        owner: solana_sdk_ids::address_lookup_table::id(),
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+/// Resolves lookup table addresses from a provided set of accounts.
+#[derive(Clone)]
+struct MolluskAddressLoader<'a> {
+    accounts: &'a [(Pubkey, Account)],
+    sysvars: &'a Sysvars,
+}
+
+impl MolluskAddressLoader<'_> {
+    fn load_lookup_table(
+        &self,
+        key: &Pubkey,
+    ) -> Result<AddressLookupTable<'_>, AddressLoaderError> {
+        let account = self
+            .accounts
+            .iter()
+            .find(|(k, account)| k == key && account.lamports > 0)
+            .map(|(_, account)| account)
+            .ok_or(AddressLoaderError::LookupTableAccountNotFound)?;
+
+        if account.owner != solana_sdk_ids::address_lookup_table::id() {
+            return Err(AddressLoaderError::InvalidAccountOwner);
+        }
+
+        AddressLookupTable::deserialize(&account.data)
+            .map_err(|_| AddressLoaderError::InvalidAccountData)
+    }
+
+    fn lookup(
+        &self,
+        table: &AddressLookupTable,
+        indexes: &[u8],
+    ) -> Result<Vec<Pubkey>, AddressLoaderError> {
+        table
+            .lookup(self.sysvars.clock.slot, indexes, &self.sysvars.slot_hashes)
+            .map_err(|err| match err {
+                AddressLookupError::LookupTableAccountNotFound => {
+                    AddressLoaderError::LookupTableAccountNotFound
+                }
+                AddressLookupError::InvalidAccountOwner => AddressLoaderError::InvalidAccountOwner,
+                AddressLookupError::InvalidAccountData => AddressLoaderError::InvalidAccountData,
+                AddressLookupError::InvalidLookupIndex => AddressLoaderError::InvalidLookupIndex,
+            })
+    }
+}
+
+impl AddressLoader for MolluskAddressLoader<'_> {
+    fn load_addresses(
+        self,
+        lookups: &[MessageAddressTableLookup],
+    ) -> Result<LoadedAddresses, AddressLoaderError> {
+        lookups
+            .iter()
+            .map(|lookup| {
+                let table = self.load_lookup_table(&lookup.account_key)?;
+                Ok(LoadedAddresses {
+                    writable: self.lookup(&table, &lookup.writable_indexes)?,
+                    readonly: self.lookup(&table, &lookup.readonly_indexes)?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Sanitize a versioned message, resolving any lookup table addresses from
+/// the provided accounts.
+pub(crate) fn sanitize_versioned_message(
+    message: &VersionedMessage,
+    accounts: &[(Pubkey, Account)],
+    sysvars: &Sysvars,
+) -> Result<SanitizedMessage, TransactionError> {
+    let sanitized_versioned_message =
+        SanitizedVersionedMessage::try_new(message.clone()).map_err(SanitizeMessageError::from)?;
+    let address_loader = MolluskAddressLoader { accounts, sysvars };
+    Ok(SanitizedMessage::try_new(
+        sanitized_versioned_message,
+        address_loader,
+        &HashSet::new(),
+    )?)
+}
@@ -4,7 +4,7 @@
 use {
     mollusk_svm_error::error::{MolluskError, MolluskPanic},
     solana_account::{Account, AccountSharedData, WritableAccount},
-    solana_instruction::Instruction,
+    solana_instruction::{AccountMeta, Instruction},
     solana_message::{LegacyMessage, Message, SanitizedMessage},
     solana_pubkey::Pubkey,
     std::collections::{HashMap, HashSet},
@@ -27,6 +27,43 @@ pub fn compile_accounts_with_payer<'a>(
     let message = Message::new(instructions, payer);
     let sanitized_message = SanitizedMessage::Legacy(LegacyMessage::new(message, &HashSet::new()));
 
+    let transaction_accounts = build_message_accounts(
+        &sanitized_message,
+        payer,
+        instructions,
+        accounts,
+        fallback_accounts,
+    );
+
+    (sanitized_message, transaction_accounts)
+}
+
+/// Build the transaction accounts for an already-sanitized message, such as
+/// one compiled from a `VersionedMessage`.
+pub fn compile_sanitized_message_accounts<'a>(
+    message: &SanitizedMessage,
+    instructions: &[Instruction],
+    accounts: impl Iterator<Item = &'a (Pubkey, Account)>,
+    fallback_accounts: &HashMap<Pubkey, Account>,
+) -> Vec<(Pubkey, AccountSharedData)> {
+    build_message_accounts(
+        message,
+        Some(message.fee_payer()),
+        instructions,
+        accounts,
+        fallback_accounts,
+    )
+}
+
+// Build the transaction accounts for a message, defaulting the fee payer's
+// account if it wasn't provided.
+fn build_message_accounts<'a>(
+    message: &SanitizedMessage,
+    payer: Option<&Pubkey>,
+    instructions: &[Instruction],
+    accounts: impl Iterator<Item = &'a (Pubkey, Account)>,
+    fallback_accounts: &HashMap<Pubkey, Account>,
+) -> Vec<(Pubkey, AccountSharedData)> {
     let mut accounts: Vec<_> = accounts.collect();
 
     let payer_account = payer
@@ -37,14 +74,29 @@ pub fn compile_accounts_with_payer<'a>(
         accounts.insert(0, payer_account);
     }
 
-    let transaction_accounts = build_transaction_accounts(
-        &sanitized_message,
-        &accounts,
-        instructions,
-        fallback_accounts,
-    );
+    build_transaction_accounts(message, &accounts, instructions, fallback_accounts)
+}
 
-    (sanitized_message, transaction_accounts)
+/// Convert a sanitized message's instructions back into `Instruction`s, with
+/// account privileges as resolved by the message.
+pub fn decompile_instructions(message: &SanitizedMessage) -> Vec<Instruction> {
+    message
+        .decompile_instructions()
+        .into_iter()
+        .map(|instruction| Instruction {
+            program_id: *instruction.program_id,
+            accounts: instruction
+                .accounts
+                .iter()
+                .map(|meta| AccountMeta {
+                    pubkey: *meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: instruction.data.to_vec(),
+        })
+        .collect()
 }
 
 fn build_transaction_accounts(
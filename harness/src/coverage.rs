@@ -0,0 +1,401 @@
+//! Source-level code coverage from register traces.
+//!
+//! Coverage is collected in two steps. While tests run, [`CoverageCallback`]
+//! records the program counters executed by every traced invocation to the
+//! coverage directory, along with a copy of each program's ELF. Since every
+//! test process writes its own files, a whole `cargo test` run can be
+//! collected into the same directory.
+//!
+//! Once the tests have finished, [`CoverageReport::load`] merges everything in
+//! the directory and maps the executed program counters to source lines using
+//! the DWARF debug info of each ELF. Programs must be built with debug info
+//! for line coverage to be reported, but instruction coverage is always
+//! available.
+
+use {
+    crate::{
+        register_tracing::{as_bytes, compute_hash},
+        InvocationInspectCallback, Mollusk,
+    },
+    addr2line::gimli::{EndianSlice, LittleEndian},
+    object::{Object, ObjectSection},
+    solana_program_runtime::{
+        invoke_context::{Executable, InvokeContext, RegisterTrace},
+        solana_sbpf::ebpf,
+    },
+    solana_pubkey::Pubkey,
+    solana_transaction_context::{
+        instruction::InstructionContext, instruction_accounts::InstructionAccount,
+    },
+    std::{
+        collections::{BTreeMap, HashMap},
+        fs::{File, OpenOptions},
+        io::Write,
+        path::Path,
+        str::FromStr,
+        sync::atomic::{AtomicUsize, Ordering},
+    },
+};
+
+const DEFAULT_PATH: &str = "target/sbf/coverage";
+
+/// Distinguishes the hit files written by a single process.
+static HITS_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// An invocation inspect callback which records executed program counters
+/// for coverage reports.
+pub struct CoverageCallback {
+    pub coverage_dir: String,
+}
+
+impl Default for CoverageCallback {
+    fn default() -> Self {
+        Self {
+            // User can override default path with `SBF_COVERAGE_DIR` environment variable.
+            coverage_dir: std::env::var("SBF_COVERAGE_DIR").unwrap_or(DEFAULT_PATH.to_string()),
+        }
+    }
+}
+
+impl CoverageCallback {
+    pub fn new(coverage_dir: &str) -> Self {
+        Self {
+            coverage_dir: coverage_dir.to_string(),
+        }
+    }
+
+    pub fn post_handler(
+        &self,
+        mollusk: &Mollusk,
+        instruction_context: InstructionContext,
+        register_trace: RegisterTrace,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if register_trace.is_empty() {
+            return Ok(());
+        }
+
+        let program_id = instruction_context.get_program_key()?;
+        let Some(elf_data) = mollusk.program_cache.get_program_elf_bytes(program_id) else {
+            // Without the ELF there's nothing to map program counters to.
+            return Ok(());
+        };
+
+        let coverage_dir = std::env::current_dir()?.join(&self.coverage_dir);
+        std::fs::create_dir_all(&coverage_dir)?;
+
+        let elf_hash = compute_hash(&elf_data);
+        let base_fname = coverage_dir.join(&elf_hash);
+
+        // Persist the ELF once, since every trace of it maps through it.
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(base_fname.with_extension("so"))
+        {
+            Ok(mut elf_file) => elf_file.write_all(&elf_data)?,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e.into()),
+        }
+        std::fs::write(
+            base_fname.with_extension("program_id"),
+            program_id.to_string(),
+        )?;
+
+        let mut hits = BTreeMap::<u64, u64>::new();
+        for regs in register_trace.iter() {
+            // The program counter is stored in r11.
+            *hits.entry(regs[11]).or_default() += 1;
+        }
+        let hits = hits
+            .into_iter()
+            .flat_map(|(pc, count)| [pc, count])
+            .collect::<Vec<_>>();
+
+        let hits_fname = coverage_dir.join(format!(
+            "{elf_hash}-{}-{}.hits",
+            std::process::id(),
+            HITS_FILE_COUNTER.fetch_add(1, Ordering::Relaxed),
+        ));
+        File::create(hits_fname)?.write_all(as_bytes(&hits))?;
+
+        Ok(())
+    }
+}
+
+impl InvocationInspectCallback for CoverageCallback {
+    fn before_invocation(
+        &self,
+        _mollusk: &Mollusk,
+        _program_id: &Pubkey,
+        _instruction_data: &[u8],
+        _instruction_accounts: &[InstructionAccount],
+        _invoke_context: &mut InvokeContext,
+        _register_tracing_enabled: bool,
+    ) {
+    }
+
+    fn after_invocation(
+        &self,
+        mollusk: &Mollusk,
+        invoke_context: &InvokeContext,
+        register_tracing_enabled: bool,
+    ) {
+        if register_tracing_enabled {
+            invoke_context.iterate_vm_traces(
+                &|instruction_context: InstructionContext,
+                  _executable: &Executable,
+                  register_trace: RegisterTrace| {
+                    if let Err(e) = self.post_handler(mollusk, instruction_context, register_trace)
+                    {
+                        eprintln!("Error collecting the coverage: {e}");
+                    }
+                },
+            );
+        }
+    }
+}
+
+/// The coverage of every program recorded in a coverage directory.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CoverageReport {
+    pub programs: Vec<ProgramCoverage>,
+}
+
+impl CoverageReport {
+    /// Load and merge all coverage data recorded in a directory by
+    /// [`CoverageCallback`].
+    pub fn load<P: AsRef<Path>>(coverage_dir: P) -> std::io::Result<Self> {
+        let coverage_dir = coverage_dir.as_ref();
+
+        let mut hits_by_elf = BTreeMap::<String, HashMap<u64, u64>>::new();
+        for entry in std::fs::read_dir(coverage_dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "hits") {
+                continue;
+            }
+            let Some((elf_hash, _)) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.split_once('-'))
+            else {
+                continue;
+            };
+            let hits = hits_by_elf.entry(elf_hash.to_string()).or_default();
+            let data = std::fs::read(&path)?;
+            for pair in data.chunks_exact(16) {
+                let pc = u64::from_le_bytes(pair[..8].try_into().unwrap());
+                let count = u64::from_le_bytes(pair[8..].try_into().unwrap());
+                *hits.entry(pc).or_default() += count;
+            }
+        }
+
+        let mut programs = vec![];
+        for (elf_hash, hits) in hits_by_elf {
+            let base_fname = coverage_dir.join(&elf_hash);
+            let elf = std::fs::read(base_fname.with_extension("so"))?;
+            let program_id = std::fs::read_to_string(base_fname.with_extension("program_id"))
+                .ok()
+                .and_then(|program_id| Pubkey::from_str(program_id.trim()).ok())
+                .unwrap_or_default();
+            programs.push(ProgramCoverage::new(program_id, elf_hash, &elf, &hits));
+        }
+
+        Ok(Self { programs })
+    }
+
+    /// Write an lcov report (`{program_id}.lcov`) and a Cobertura report
+    /// (`{program_id}.cobertura.xml`) for each program to a directory.
+    pub fn write_reports<P: AsRef<Path>>(&self, output_dir: P) -> std::io::Result<()> {
+        let output_dir = output_dir.as_ref();
+        std::fs::create_dir_all(output_dir)?;
+        for program in &self.programs {
+            let base_fname = output_dir.join(program.program_id.to_string());
+            program.write_lcov(&mut File::create(base_fname.with_extension("lcov"))?)?;
+            program.write_cobertura(&mut File::create(
+                base_fname.with_extension("cobertura.xml"),
+            )?)?;
+        }
+        Ok(())
+    }
+}
+
+/// The coverage of a single program.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProgramCoverage {
+    pub program_id: Pubkey,
+    /// The SHA-256 hash of the program's ELF.
+    pub elf_hash: String,
+    /// The number of instructions in the program.
+    pub instructions: usize,
+    /// The number of instructions executed at least once.
+    pub executed_instructions: usize,
+    /// The number of times each source line was executed, keyed by source
+    /// file and line number. Empty if the ELF has no DWARF debug info.
+    pub lines: BTreeMap<String, BTreeMap<u32, u64>>,
+}
+
+impl ProgramCoverage {
+    fn new(program_id: Pubkey, elf_hash: String, elf: &[u8], hits: &HashMap<u64, u64>) -> Self {
+        let mut coverage = Self {
+            program_id,
+            elf_hash,
+            ..Default::default()
+        };
+
+        let Some(object) = object::File::parse(elf).ok() else {
+            return coverage;
+        };
+        let Some((text_address, text)) = object
+            .section_by_name(".text")
+            .and_then(|section| Some((section.address(), section.data().ok()?)))
+        else {
+            return coverage;
+        };
+        let dwarf = addr2line::gimli::Dwarf::load(|id| {
+            let data = object
+                .section_by_name(id.name())
+                .and_then(|section| section.data().ok())
+                .unwrap_or_default();
+            Ok::<_, addr2line::gimli::Error>(EndianSlice::new(data, LittleEndian))
+        })
+        .ok()
+        .and_then(|dwarf| addr2line::Context::from_dwarf(dwarf).ok());
+
+        let mut pc = 0;
+        while pc * ebpf::INSN_SIZE < text.len() {
+            let count = hits.get(&(pc as u64)).copied().unwrap_or_default();
+            coverage.instructions += 1;
+            if count > 0 {
+                coverage.executed_instructions += 1;
+            }
+
+            // A line counts as executed as many times as its most executed
+            // instruction.
+            let address = text_address + (pc * ebpf::INSN_SIZE) as u64;
+            if let Some(location) = dwarf
+                .as_ref()
+                .and_then(|dwarf| dwarf.find_location(address).ok().flatten())
+            {
+                if let (Some(file), Some(line)) = (location.file, location.line) {
+                    let hits = coverage
+                        .lines
+                        .entry(file.to_string())
+                        .or_default()
+                        .entry(line)
+                        .or_default();
+                    *hits = (*hits).max(count);
+                }
+            }
+
+            // Wide instructions occupy two slots.
+            pc += if text[pc * ebpf::INSN_SIZE] == ebpf::LD_DW_IMM {
+                2
+            } else {
+                1
+            };
+        }
+
+        coverage
+    }
+
+    /// The number of source lines.
+    pub fn lines_valid(&self) -> usize {
+        self.lines.values().map(BTreeMap::len).sum()
+    }
+
+    /// The number of source lines executed at least once.
+    pub fn lines_covered(&self) -> usize {
+        self.lines
+            .values()
+            .flat_map(BTreeMap::values)
+            .filter(|hits| **hits > 0)
+            .count()
+    }
+
+    /// Write the coverage in lcov tracefile format.
+    pub fn write_lcov<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writeln!(writer, "TN:{}", self.program_id)?;
+        for (file, lines) in &self.lines {
+            writeln!(writer, "SF:{file}")?;
+            for (line, hits) in lines {
+                writeln!(writer, "DA:{line},{hits}")?;
+            }
+            writeln!(writer, "LF:{}", lines.len())?;
+            writeln!(
+                writer,
+                "LH:{}",
+                lines.values().filter(|hits| **hits > 0).count()
+            )?;
+            writeln!(writer, "end_of_record")?;
+        }
+        Ok(())
+    }
+
+    /// Write the coverage in Cobertura XML format.
+    pub fn write_cobertura<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let package_line_rate = line_rate(self.lines_covered(), self.lines_valid());
+
+        writeln!(writer, r#"<?xml version="1.0" ?>"#)?;
+        writeln!(
+            writer,
+            r#"<!DOCTYPE coverage SYSTEM "http://cobertura.sourceforge.net/xml/coverage-04.dtd">"#
+        )?;
+        writeln!(
+            writer,
+            r#"<coverage line-rate="{package_line_rate}" branch-rate="0" lines-covered="{}" lines-valid="{}" branches-covered="0" branches-valid="0" complexity="0" version="{}" timestamp="{timestamp}">"#,
+            self.lines_covered(),
+            self.lines_valid(),
+            env!("CARGO_PKG_VERSION"),
+        )?;
+        writeln!(writer, "  <sources><source>.</source></sources>")?;
+        writeln!(writer, "  <packages>")?;
+        writeln!(
+            writer,
+            r#"    <package name="{}" line-rate="{package_line_rate}" branch-rate="0" complexity="0">"#,
+            self.program_id,
+        )?;
+        writeln!(writer, "      <classes>")?;
+        for (file, lines) in &self.lines {
+            let file = escape_xml(file);
+            let covered = lines.values().filter(|hits| **hits > 0).count();
+            writeln!(
+                writer,
+                r#"        <class name="{file}" filename="{file}" line-rate="{}" branch-rate="0" complexity="0">"#,
+                line_rate(covered, lines.len()),
+            )?;
+            writeln!(writer, "          <methods/>")?;
+            writeln!(writer, "          <lines>")?;
+            for (line, hits) in lines {
+                writeln!(
+                    writer,
+                    r#"            <line number="{line}" hits="{hits}" branch="false"/>"#
+                )?;
+            }
+            writeln!(writer, "          </lines>")?;
+            writeln!(writer, "        </class>")?;
+        }
+        writeln!(writer, "      </classes>")?;
+        writeln!(writer, "    </package>")?;
+        writeln!(writer, "  </packages>")?;
+        writeln!(writer, "</coverage>")
+    }
+}
+
+fn line_rate(covered: usize, valid: usize) -> f64 {
+    if valid == 0 {
+        0.0
+    } else {
+        covered as f64 / valid as f64
+    }
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,80 @@
+//! Transaction fee calculation and fee payer validation.
+//!
+//! Mirrors the checks the runtime performs before executing a transaction:
+//! fees are computed from the message's signatures and compute budget
+//! instructions, then charged to the fee payer up front.
+
+use {
+    crate::{feature_set::svm_feature_set_to_feature_set, rent_state},
+    mollusk_svm_result::types::TransactionFee,
+    solana_account::{AccountSharedData, ReadableAccount, WritableAccount},
+    solana_compute_budget_instruction::instructions_processor::process_compute_budget_instructions,
+    solana_fee::FeeFeatures,
+    solana_message::SanitizedMessage,
+    solana_rent::Rent,
+    solana_svm_feature_set::SVMFeatureSet,
+    solana_svm_transaction::svm_message::SVMStaticMessage,
+    solana_transaction_error::TransactionError,
+};
+
+/// Calculate the signature and prioritization fees for a message.
+///
+/// Fails if the message contains invalid compute budget instructions.
+pub(crate) fn calculate_fee(
+    message: &SanitizedMessage,
+    lamports_per_signature: u64,
+    feature_set: &SVMFeatureSet,
+) -> Result<TransactionFee, TransactionError> {
+    let feature_set = svm_feature_set_to_feature_set(feature_set);
+    let compute_budget_limits = process_compute_budget_instructions(
+        SVMStaticMessage::program_instructions_iter(message),
+        &feature_set,
+    )?;
+    let fee_details = solana_fee::calculate_fee_details(
+        message,
+        lamports_per_signature,
+        compute_budget_limits.get_prioritization_fee(),
+        FeeFeatures::from(&feature_set),
+    );
+    Ok(TransactionFee {
+        signature_fee: fee_details.transaction_fee(),
+        prioritization_fee: fee_details.prioritization_fee(),
+    })
+}
+
+/// Validate that the fee payer can pay the provided fee, then debit it.
+///
+/// Only system accounts with no data may pay fees. Durable nonce accounts
+/// are not supported as fee payers.
+pub(crate) fn validate_and_charge_fee_payer(
+    payer_account: &mut AccountSharedData,
+    fee: u64,
+    rent: &Rent,
+    relax_post_exec_min_balance_check: bool,
+) -> Result<(), TransactionError> {
+    if payer_account.lamports() == 0 {
+        return Err(TransactionError::AccountNotFound);
+    }
+    if !solana_sdk_ids::system_program::check_id(payer_account.owner())
+        || !payer_account.data().is_empty()
+    {
+        return Err(TransactionError::InvalidAccountForFee);
+    }
+
+    let pre_lamports = payer_account.lamports();
+    let post_lamports = pre_lamports
+        .checked_sub(fee)
+        .ok_or(TransactionError::InsufficientFundsForFee)?;
+
+    rent_state::check_static_account_transition(
+        pre_lamports,
+        post_lamports,
+        payer_account.data().len(),
+        rent,
+        /* account_index */ 0,
+        relax_post_exec_min_balance_check,
+    )?;
+
+    payer_account.set_lamports(post_lamports);
+    Ok(())
+}
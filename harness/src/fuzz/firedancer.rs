@@ -254,10 +254,17 @@ pub(crate) fn parse_fixture_effects(
         compute_units_consumed: compute_unit_limit.saturating_sub(effects.compute_units_available),
         return_data,
         resulting_accounts,
+        logs: vec![],
         #[cfg(feature = "inner-instructions")]
         inner_instructions: vec![],
         #[cfg(feature = "inner-instructions")]
+        resolved_inner_instructions: vec![],
+        #[cfg(feature = "inner-instructions")]
         message: None,
+        #[cfg(feature = "syscall-trace")]
+        syscall_trace: vec![],
+        #[cfg(feature = "invocation-tree")]
+        invocations: vec![],
     }
 }
 
@@ -0,0 +1,222 @@
+//! Building the invocation tree of an execution.
+//!
+//! The shape of the tree and each invocation's instruction come from the
+//! transaction's instruction trace. Everything else is read from the
+//! runtime's logs, which are matched to invocations in the order they were
+//! invoked.
+
+use {
+    base64::{engine::general_purpose::STANDARD, Engine},
+    mollusk_svm_result::types::{Invocation, ProgramResult},
+    solana_instruction::{error::InstructionError, AccountMeta, Instruction},
+    solana_pubkey::Pubkey,
+    solana_transaction_context::transaction::TransactionContext,
+    std::str::FromStr,
+};
+
+/// The outcome of processing a top-level instruction.
+pub(crate) struct TopLevelOutcome {
+    pub compute_units_consumed: u64,
+    pub result: Result<(), InstructionError>,
+}
+
+struct Node {
+    invocation: Invocation,
+    children: Vec<usize>,
+    // Whether the runtime logged the invocation as successful, if it was
+    // logged at all.
+    succeeded: Option<bool>,
+}
+
+/// Build the invocation tree of each processed top-level instruction.
+pub(crate) fn build_invocation_tree(
+    transaction_context: &TransactionContext,
+    outcomes: &[TopLevelOutcome],
+    logs: &[String],
+) -> Vec<Invocation> {
+    let mut nodes = (0..transaction_context.get_instruction_trace_length())
+        .filter_map(|index_in_trace| {
+            let instruction_context = transaction_context
+                .get_instruction_context_at_index_in_trace(index_in_trace)
+                .ok()?;
+            let program_id = instruction_context
+                .get_program_key()
+                .copied()
+                .unwrap_or_default();
+            let accounts = instruction_context
+                .instruction_accounts()
+                .iter()
+                .map(|account| AccountMeta {
+                    pubkey: transaction_context
+                        .get_key_of_account_at_index(account.index_in_transaction)
+                        .copied()
+                        .unwrap_or_default(),
+                    is_signer: account.is_signer(),
+                    is_writable: account.is_writable(),
+                })
+                .collect();
+            Some(Node {
+                invocation: Invocation {
+                    program_id,
+                    stack_height: instruction_context.get_stack_height() as u32,
+                    instruction: Instruction {
+                        program_id,
+                        accounts,
+                        data: instruction_context.get_instruction_data().to_vec(),
+                    },
+                    compute_units_consumed: None,
+                    program_result: ProgramResult::Success,
+                    return_data: vec![],
+                    logs: vec![],
+                    inner_invocations: vec![],
+                },
+                children: vec![],
+                succeeded: None,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // The trace lays out all top-level instructions first, followed by CPIs
+    // in the order they were invoked.
+    for index in outcomes.len()..nodes.len() {
+        if nodes[index].invocation.stack_height > 1 {
+            let caller = transaction_context
+                .get_instruction_context_at_index_in_trace(index)
+                .map(|instruction_context| instruction_context.get_index_of_caller())
+                .unwrap_or(usize::MAX);
+            if let Some(caller) = nodes.get_mut(caller) {
+                caller.children.push(index);
+            }
+        }
+    }
+
+    let mut invocation_order = vec![];
+    for root in 0..outcomes.len().min(nodes.len()) {
+        push_invocation_order(&nodes, root, &mut invocation_order);
+    }
+    apply_logs(&mut nodes, &invocation_order, logs);
+
+    outcomes
+        .iter()
+        .enumerate()
+        .take(nodes.len())
+        .map(|(root, outcome)| {
+            let program_result = ProgramResult::from(outcome.result.clone());
+            nodes[root].invocation.compute_units_consumed = Some(outcome.compute_units_consumed);
+            finish(&mut nodes, root, &program_result, outcome.result.is_err())
+        })
+        .collect()
+}
+
+fn push_invocation_order(nodes: &[Node], index: usize, order: &mut Vec<usize>) {
+    order.push(index);
+    for child in &nodes[index].children {
+        push_invocation_order(nodes, *child, order);
+    }
+}
+
+fn apply_logs(nodes: &mut [Node], invocation_order: &[usize], logs: &[String]) {
+    let mut next = 0;
+    // The invocation each logged frame belongs to, or `None` if it couldn't
+    // be matched.
+    let mut stack: Vec<Option<usize>> = vec![];
+
+    for message in logs {
+        let current = stack.last().copied().flatten();
+        match parse_runtime_message(message) {
+            Some((program_id, RuntimeMessage::Invoke(stack_height))) => {
+                let position = invocation_order[next..].iter().position(|index| {
+                    let invocation = &nodes[*index].invocation;
+                    invocation.program_id == program_id && invocation.stack_height == stack_height
+                });
+                stack.push(position.map(|position| {
+                    next += position + 1;
+                    invocation_order[next - 1]
+                }));
+            }
+            Some((_, RuntimeMessage::Consumed(compute_units_consumed))) => {
+                if let Some(current) = current {
+                    nodes[current].invocation.compute_units_consumed = Some(compute_units_consumed);
+                }
+            }
+            Some((_, RuntimeMessage::Finished { succeeded })) => {
+                if let Some(current) = current {
+                    nodes[current].succeeded = Some(succeeded);
+                }
+                stack.pop();
+            }
+            None => {
+                if let Some(current) = current {
+                    if let Some(return_data) = parse_return_data(message) {
+                        nodes[current].invocation.return_data = return_data;
+                    }
+                    nodes[current].invocation.logs.push(message.clone());
+                }
+            }
+        }
+    }
+}
+
+// Resolve the results of an invocation and its inner invocations, then
+// assemble them into a tree.
+//
+// A failed CPI fails its caller, so only the last inner invocation of a failed
+// invocation can have failed. The logs decide whether it did, if they were
+// captured.
+fn finish(nodes: &mut [Node], index: usize, failure: &ProgramResult, failed: bool) -> Invocation {
+    if failed {
+        nodes[index].invocation.program_result = failure.clone();
+    }
+    let children = std::mem::take(&mut nodes[index].children);
+    let inner_invocations = children
+        .iter()
+        .enumerate()
+        .map(|(position, child)| {
+            let child_failed = failed
+                && nodes[*child]
+                    .succeeded
+                    .map_or(position + 1 == children.len(), |succeeded| !succeeded);
+            finish(nodes, *child, failure, child_failed)
+        })
+        .collect();
+    let mut invocation = nodes[index].invocation.clone();
+    invocation.inner_invocations = inner_invocations;
+    invocation
+}
+
+enum RuntimeMessage {
+    Invoke(u32),
+    Consumed(u64),
+    Finished { succeeded: bool },
+}
+
+// Parse a message logged by the runtime about an invocation, such as
+// `Program <id> invoke [1]`.
+fn parse_runtime_message(message: &str) -> Option<(Pubkey, RuntimeMessage)> {
+    let (program_id, rest) = message.strip_prefix("Program ")?.split_once(' ')?;
+    let program_id = Pubkey::from_str(program_id).ok()?;
+    let message = if let Some(stack_height) = rest
+        .strip_prefix("invoke [")
+        .and_then(|rest| rest.strip_suffix(']'))
+    {
+        RuntimeMessage::Invoke(stack_height.parse().ok()?)
+    } else if let Some(consumed) = rest
+        .strip_prefix("consumed ")
+        .and_then(|rest| rest.strip_suffix(" compute units"))
+    {
+        RuntimeMessage::Consumed(consumed.split_once(" of ")?.0.parse().ok()?)
+    } else if rest == "success" {
+        RuntimeMessage::Finished { succeeded: true }
+    } else if rest.starts_with("failed: ") {
+        RuntimeMessage::Finished { succeeded: false }
+    } else {
+        return None;
+    };
+    Some((program_id, message))
+}
+
+// Parse the data from a `Program return: <id> <base64>` message.
+fn parse_return_data(message: &str) -> Option<Vec<u8>> {
+    let (_program_id, data) = message.strip_prefix("Program return: ")?.split_once(' ')?;
+    STANDARD.decode(data).ok()
+}
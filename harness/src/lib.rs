@@ -131,6 +131,34 @@
 //! default builtin programs. For more builtin programs, you can add them
 //! yourself or use the `all-builtins` feature.
 //!
+//! Exact compute unit checks break whenever the toolchain or runtime changes a
+//! program's consumption. `Check::compute_units_at_most` sets an upper bound
+//! instead, and `Check::compute_units_within` allows the consumption to differ
+//! from the expected amount by a percentage, in either direction.
+//!
+//! ```rust,ignore
+//! let checks = vec![
+//!     Check::success(),
+//!     Check::compute_units_at_most(5_000),
+//!     Check::compute_units_within(4_200, 2.0),
+//! ];
+//! ```
+//!
+//! When comparing results, such as against a fixture's effects,
+//! `Compare::ComputeUnitsWithin` does the same, allowing the greater of an
+//! absolute number of units and a percentage of the expected units. With the
+//! `serde` feature it can be loaded from the CLI's config file, to allow
+//! compute units to differ by at most 2% when replaying fixtures with
+//! `run-test`:
+//!
+//! ```yaml
+//! checks:
+//!   - !computeUnitsWithin
+//!     percent: 2.0
+//!   - programResult
+//!   - returnData
+//! ```
+//!
 //! ## Instruction Chains
 //!
 //! Both `process_instruction_chain` and
@@ -276,6 +304,419 @@
 //! Developers should recognize that instruction chains are primarily used for
 //! testing program execution.
 //!
+//! ## Fee-Paying Transactions
+//!
+//! To test flows that depend on transaction fees, `process_transaction`
+//! compiles the instructions into a message with an explicit fee payer and set
+//! of signers. The base signature fee and any prioritization fee are deducted
+//! from the fee payer before execution, and the deduction is kept even if an
+//! instruction fails. The charged fee is reported in the result.
+//!
+//! ```rust,ignore
+//! use {
+//!     mollusk_svm::{Mollusk, result::Check},
+//!     solana_account::Account,
+//!     solana_pubkey::Pubkey,
+//!     solana_system_interface::{instruction::transfer, program::id as system_program},
+//! };
+//!
+//! let mollusk = Mollusk::default();
+//!
+//! let payer = Pubkey::new_unique();
+//! let recipient = Pubkey::new_unique();
+//!
+//! let base_lamports = 100_000_000u64;
+//! let transfer_amount = 42_000u64;
+//!
+//! let result = mollusk.process_and_validate_transaction(
+//!     &[transfer(&payer, &recipient, transfer_amount)],
+//!     &[
+//!         (payer, Account::new(base_lamports, 0, &system_program())),
+//!         (recipient, Account::new(base_lamports, 0, &system_program())),
+//!     ],
+//!     &payer,
+//!     &[],
+//!     &[
+//!         Check::success(),
+//!         Check::account(&payer)
+//!             .lamports(base_lamports - transfer_amount - mollusk.lamports_per_signature)
+//!             .build(),
+//!     ],
+//! );
+//!
+//! assert_eq!(result.fee.total(), mollusk.lamports_per_signature);
+//! ```
+//!
+//! The lamports charged per signature can be configured through
+//! `Mollusk::lamports_per_signature`.
+//!
+//! ## Versioned Messages and Address Lookup Tables
+//!
+//! `process_versioned_message` processes a `VersionedMessage`, including v0
+//! messages that load accounts through address lookup tables. Lookup table
+//! addresses are resolved from the lookup table accounts provided alongside the
+//! other accounts, and loaded writable and readonly accounts are marked
+//! accordingly. `create_address_lookup_table_account` creates an active lookup
+//! table account for testing.
+//!
+//! ```rust,ignore
+//! use {
+//!     mollusk_svm::{
+//!         Mollusk, address_lookup_table::create_address_lookup_table_account,
+//!         result::Check,
+//!     },
+//!     solana_account::Account,
+//!     solana_hash::Hash,
+//!     solana_message::{AddressLookupTableAccount, VersionedMessage, v0},
+//!     solana_pubkey::Pubkey,
+//!     solana_system_interface::{instruction::transfer, program::id as system_program},
+//! };
+//!
+//! let mollusk = Mollusk::default();
+//!
+//! let sender = Pubkey::new_unique();
+//! let recipient = Pubkey::new_unique();
+//! let lookup_table = Pubkey::new_unique();
+//!
+//! let message = v0::Message::try_compile(
+//!     &sender,
+//!     &[transfer(&sender, &recipient, 42_000)],
+//!     &[AddressLookupTableAccount {
+//!         key: lookup_table,
+//!         addresses: vec![recipient],
+//!     }],
+//!     Hash::default(),
+//! )
+//! .unwrap();
+//!
+//! mollusk.process_and_validate_versioned_message(
+//!     &VersionedMessage::V0(message),
+//!     &[
+//!         (sender, Account::new(100_000_000, 0, &system_program())),
+//!         (recipient, Account::new(100_000_000, 0, &system_program())),
+//!         (lookup_table, create_address_lookup_table_account(&[recipient])),
+//!     ],
+//!     &[Check::success()],
+//! );
+//! ```
+//!
+//! When using `MolluskContext`, lookup tables and the accounts they reference
+//! are loaded from the account store.
+//!
+//! ## Rent State Enforcement
+//!
+//! By default, Mollusk does not apply the runtime's rent state checks to
+//! processed accounts. Setting `enforce_rent_state_transitions` makes the
+//! transaction APIs (`process_transaction_instructions`,
+//! `process_versioned_message`, and `process_transaction`) verify every
+//! writable account after execution, as the validator does. A transaction that
+//! leaves an account in a disallowed rent state, such as a newly funded account
+//! below the rent-exempt minimum, fails with
+//! `TransactionError::InsufficientFundsForRent`. The instruction APIs, such as
+//! `process_instruction` and `process_instruction_chain`, can't represent a
+//! transaction error, so they panic if the checks are enforced.
+//!
+//! ```rust,ignore
+//! use {
+//!     mollusk_svm::{Mollusk, result::Check},
+//!     solana_account::Account,
+//!     solana_pubkey::Pubkey,
+//!     solana_system_interface::{instruction::transfer, program::id as system_program},
+//!     solana_transaction_error::TransactionError,
+//! };
+//!
+//! let mut mollusk = Mollusk::default();
+//! mollusk.enforce_rent_state_transitions = true;
+//!
+//! let sender = Pubkey::new_unique();
+//! let recipient = Pubkey::new_unique();
+//!
+//! mollusk.process_and_validate_transaction_instructions(
+//!     &[transfer(&sender, &recipient, 42)],
+//!     &[
+//!         (sender, Account::new(100_000_000, 0, &system_program())),
+//!         (recipient, Account::default()),
+//!     ],
+//!     &[Check::transaction_err(
+//!         TransactionError::InsufficientFundsForRent { account_index: 1 },
+//!     )],
+//!     None,
+//! );
+//! ```
+//!
+//! ## Mock Programs
+//!
+//! To test CPIs into a program whose ELF isn't available, register a mock
+//! program backed by a closure with `add_mock_program`. The closure is called
+//! whenever the program is invoked, whether directly or via CPI. It receives a
+//! `MockInvocation` with the program ID, instruction data, and accounts. It can
+//! modify the accounts, set return data, and log messages. Returning an error
+//! fails the invocation, which is useful for simulating failing dependencies.
+//!
+//! ```rust,ignore
+//! use {
+//!     mollusk_svm::{Mollusk, result::Check},
+//!     solana_account::Account,
+//!     solana_instruction::{AccountMeta, Instruction},
+//!     solana_program_error::ProgramError,
+//!     solana_pubkey::Pubkey,
+//! };
+//!
+//! let oracle_id = Pubkey::new_unique();
+//! let price_feed = Pubkey::new_unique();
+//!
+//! let mut mollusk = Mollusk::default();
+//! mollusk.add_mock_program(&oracle_id, |mut ctx| {
+//!     match ctx.instruction_data.first() {
+//!         // Write a price into the feed account.
+//!         Some(0) => ctx.accounts[0].account.data = 42u64.to_le_bytes().to_vec(),
+//!         // Simulate an outage.
+//!         _ => return Err(ProgramError::Custom(1)),
+//!     }
+//!     ctx.log("Price updated");
+//!     Ok(())
+//! });
+//!
+//! mollusk.process_and_validate_instruction(
+//!     &Instruction::new_with_bytes(
+//!         oracle_id,
+//!         &[0],
+//!         vec![AccountMeta::new(price_feed, false)],
+//!     ),
+//!     &[(price_feed, Account::new(1_000_000_000, 8, &oracle_id))],
+//!     &[
+//!         Check::success(),
+//!         Check::account(&price_feed).data(&42u64.to_le_bytes()).build(),
+//!     ],
+//! );
+//! ```
+//!
+//! Changes made by the closure are subject to the runtime's usual account
+//! modification rules. Programs that CPI into a mock must be given its program
+//! account, which can be created with
+//! `create_keyed_account_for_builtin_program`.
+//!
+//! ## Native Programs
+//!
+//! For debugging, a program's Rust entrypoint can be executed natively on the
+//! host with `add_native_program`. The program runs inside the same invoke
+//! context as every other program, so checks, fixtures, and `MolluskContext`
+//! work unchanged, while breakpoints, `dbg!`, and sanitizers work on the
+//! program logic.
+//!
+//! Accounts are serialized exactly as they would be for an SBF program, and
+//! changes are subject to the runtime's usual account modification rules.
+//! Off-chain, the SDK's syscall wrappers (`invoke_signed`, `msg!`,
+//! `set_return_data`, etc.) are no-ops, so native programs should log, CPI, and
+//! set return data through `solana_sysvar::program_stubs`, which Mollusk routes
+//! to the runtime.
+//!
+//! ```rust,ignore
+//! use {
+//!     mollusk_svm::{Mollusk, result::Check},
+//!     solana_account::Account,
+//!     solana_account_info::AccountInfo,
+//!     solana_instruction::{AccountMeta, Instruction},
+//!     solana_program_error::ProgramResult,
+//!     solana_pubkey::Pubkey,
+//!     solana_sysvar::program_stubs,
+//! };
+//!
+//! fn process_instruction(
+//!     _program_id: &Pubkey,
+//!     accounts: &[AccountInfo],
+//!     instruction_data: &[u8],
+//! ) -> ProgramResult {
+//!     accounts[0]
+//!         .try_borrow_mut_data()?
+//!         .copy_from_slice(instruction_data);
+//!     program_stubs::sol_log("Data written");
+//!     Ok(())
+//! }
+//!
+//! let program_id = Pubkey::new_unique();
+//! let key = Pubkey::new_unique();
+//!
+//! let mut mollusk = Mollusk::default();
+//! mollusk.add_native_program(&program_id, process_instruction);
+//!
+//! mollusk.process_and_validate_instruction(
+//!     &Instruction::new_with_bytes(
+//!         program_id,
+//!         &[1, 2, 3],
+//!         vec![AccountMeta::new(key, false)],
+//!     ),
+//!     &[(key, Account::new(1_000_000_000, 3, &program_id))],
+//!     &[
+//!         Check::success(),
+//!         Check::account(&key).data(&[1, 2, 3]).build(),
+//!     ],
+//! );
+//! ```
+//!
+//! Compute units are approximated: each invocation consumes a fixed base cost,
+//! and calls through the stubs consume what their syscalls would, but the
+//! program's own instructions aren't metered. Programs that CPI into a native
+//! program must be given its program account, which can be created with
+//! `create_keyed_account_for_builtin_program`.
+//!
+//! ## Syscall Interception
+//!
+//! To test how a program reacts to syscall failures or unusual values,
+//! intercept the syscall by name with `intercept_syscall`. The interceptor is
+//! called for every call with a `SyscallCall`, which includes the raw arguments
+//! and the number of previous calls while processing the current instruction or
+//! transaction. It returns a `SyscallAction`:
+//!
+//! * `PassThrough`: Execute the original syscall, after observing the call.
+//! * `Return(value)`: Skip the syscall and return `value` to the program.
+//! * `Error(err)`: Skip the syscall and abort the program with `err`.
+//!
+//! ```rust,ignore
+//! use {
+//!     mollusk_svm::{Mollusk, syscall_intercept::SyscallAction},
+//!     solana_instruction::error::InstructionError,
+//!     solana_pubkey::Pubkey,
+//! };
+//!
+//! let program_id = Pubkey::new_unique();
+//!
+//! let mut mollusk = Mollusk::default();
+//! mollusk.add_program(
+//!     &program_id,
+//!     "my_program",
+//!     &mollusk_svm::program::loader_keys::LOADER_V3,
+//! );
+//!
+//! // Fail the second log.
+//! mollusk
+//!     .intercept_syscall("sol_log_", |call| match call.count {
+//!         1 => SyscallAction::Error(InstructionError::Custom(1)),
+//!         _ => SyscallAction::PassThrough,
+//!     })
+//!     .unwrap();
+//!
+//! // Report that the Clock sysvar isn't available.
+//! mollusk
+//!     .intercept_syscall("sol_get_clock_sysvar", |_| SyscallAction::Return(1))
+//!     .unwrap();
+//! ```
+//!
+//! Programs already added to Mollusk are reloaded with the intercepted
+//! syscalls. Intercepting a syscall again replaces its interceptor.
+//! Interception applies to SBF programs only, not to native or mock programs.
+//!
+//! Each intercepted syscall takes one of a fixed number of wrapper slots,
+//! which are shared by the whole process and never released. Once
+//! `MAX_WRAPPED_SYSCALLS` (128) distinct syscalls have been intercepted,
+//! `intercept_syscall` returns `SyscallInterceptError::SlotsExhausted`. It
+//! returns `SyscallInterceptError::NotRegistered` for an unknown syscall
+//! name.
+//!
+//! ## Program Logs
+//!
+//! The log messages emitted while processing an instruction or transaction are
+//! captured in the result's `logs`, including the messages written by the
+//! runtime for each invocation, such as `Program <id> invoke [1]`. If
+//! `Mollusk::logger` is set, the messages are also appended to it, and its byte
+//! limit applies to the captured logs.
+//!
+//! Logs can be validated with checks:
+//!
+//! * `Check::logs_contain(text)`: A log message contains `text`.
+//! * `Check::log_matches(regex)`: A log message matches the regular expression.
+//!   Requires the `regex` feature.
+//! * `Check::no_logs()`: The program didn't log any messages. The runtime's
+//!   invocation messages are ignored.
+//!
+//! ```rust,ignore
+//! use {
+//!     mollusk_svm::{result::Check, Mollusk},
+//!     solana_instruction::Instruction,
+//!     solana_pubkey::Pubkey,
+//! };
+//!
+//! let program_id = Pubkey::new_unique();
+//! let mollusk = Mollusk::new(&program_id, "my_program");
+//!
+//! let result = mollusk.process_and_validate_instruction(
+//!     &Instruction::new_with_bytes(program_id, &[], vec![]),
+//!     &[],
+//!     &[
+//!         Check::success(),
+//!         Check::logs_contain("Instruction: Initialize"),
+//!         Check::log_matches(r"^Program data: \S+$"),
+//!     ],
+//! );
+//!
+//! println!("{:#?}", result.logs);
+//! ```
+//!
+//! When comparing two results, such as against a fixture, logs are compared
+//! with `Compare::Logs`. Since fixtures don't record logs, this isn't part of
+//! `Compare::everything()`.
+//!
+//! ## Check Reports
+//!
+//! `Check`s passed to methods like `process_and_validate_instruction` panic at
+//! the first failure. To see every failure at once, such as after a refactor
+//! that changes many accounts, run the checks on a result with `check_report`,
+//! which returns a `CheckReport` listing each failed check. Each failure
+//! includes the name of the check, the account it was run against, if any, and
+//! the expected and actual values.
+//!
+//! ```rust,ignore
+//! use mollusk_svm::result::{Check, Compare};
+//!
+//! let result = mollusk.process_instruction(&instruction, &accounts);
+//!
+//! let report = result.check_report(
+//!     &[
+//!         Check::success(),
+//!         Check::account(&sender).lamports(0).build(),
+//!         Check::account(&recipient).lamports(1_000).build(),
+//!     ],
+//!     &mollusk,
+//! );
+//!
+//! for failure in &report.failures {
+//!     println!(
+//!         "{:?} {}: {} != {}",
+//!         failure.pubkey, failure.check, failure.expected, failure.actual,
+//!     );
+//! }
+//!
+//! // Render every failure, or convert the report into an error.
+//! println!("{report}");
+//! report.into_result()?;
+//!
+//! // Comparisons between two results can be reported the same way.
+//! let report = result.compare_report(&expected, &Compare::everything());
+//! ```
+//!
+//! Account data mismatches are rendered as a hex diff rather than two full byte
+//! arrays. Only the changed rows are shown, with their offsets and the changed
+//! bytes marked, along with any change in length. With a `DataLayout`, changed
+//! fields are also reported by name and decoded value, such as
+//! `field 'amount' @ 64..72: 100 -> 90`.
+//!
+//! ```rust,ignore
+//! use mollusk_svm::result::diff::{DataDiff, DataLayout};
+//!
+//! let layout = DataLayout::new()
+//!     .pubkey("mint", 0)
+//!     .pubkey("owner", 32)
+//!     .u64("amount", 64);
+//!
+//! Check::account(&token_account)
+//!     .data(&expected_data)
+//!     .data_layout(&layout)
+//!     .build();
+//!
+//! // Diffs can also be rendered directly.
+//! println!("{}", DataDiff::new(&expected_data, &actual_data).layout(&layout));
+//! ```
+//!
 //! ## Stateful Testing with MolluskContext
 //!
 //! For complex testing scenarios that involve multiple instructions or require
@@ -342,6 +783,76 @@
 //! so you can use it as a simple in-memory account store without needing
 //! to implement your own.
 //!
+//! ### Snapshots and Rollback
+//!
+//! When the account store implements `Clone`, a `MolluskContext` can capture
+//! its state with `snapshot` and return to it with `restore`. A snapshot
+//! includes the account store, sysvars, epoch stake, and program cache, so many
+//! scenarios can branch from a single expensive setup. `with_rollback` runs a
+//! closure against the context and restores the prior state afterwards.
+//!
+//! ```rust,ignore
+//! use {
+//!     mollusk_svm::Mollusk,
+//!     solana_pubkey::Pubkey,
+//!     solana_system_interface::instruction as system_instruction,
+//!     std::collections::HashMap,
+//! };
+//!
+//! let alice = Pubkey::new_unique();
+//! let bob = Pubkey::new_unique();
+//!
+//! let mut context = Mollusk::default().with_context(HashMap::new());
+//!
+//! // Perform some expensive setup once, then capture it.
+//! context.process_instruction(&system_instruction::transfer(&alice, &bob, 1_000_000));
+//! let snapshot = context.snapshot();
+//!
+//! for amount in [1_000, 2_000, 3_000] {
+//!     context.process_instruction(&system_instruction::transfer(&bob, &alice, amount));
+//!
+//!     // Return to the post-setup state for the next scenario.
+//!     context.restore(&snapshot);
+//! }
+//!
+//! context.with_rollback(|context| {
+//!     context.mollusk.warp_to_slot(1_000);
+//!     // ...
+//! });
+//! ```
+//!
+//! ### Program Upgrades
+//!
+//! `MolluskContext` keeps its program cache in sync with the account store.
+//! When a BPF Loader v3 (Upgradeable) instruction such as
+//! `DeployWithMaxDataLen`, `Upgrade`, or `ExtendProgram` succeeds, the program
+//! is reloaded from its program data account, so subsequent instructions
+//! execute the new code. Closed programs are evicted from the cache.
+//!
+//! Loader v4 programs are supported as well. Add them with
+//! `add_program_with_loader` using `loader_keys::LOADER_V4`, or store an
+//! account created with `create_program_account_loader_v4_with_status` to
+//! manage it in-harness. `Deploy` loads the program from its account, while
+//! `Retract` or closing the program evicts it.
+//!
+//! ```rust,ignore
+//! let mut account_store = HashMap::new();
+//! account_store.insert(
+//!     program_id,
+//!     create_program_account_loader_v4_with_status(
+//!         &elf,
+//!         &authority,
+//!         LoaderV4Status::Retracted,
+//!     ),
+//! );
+//! let context = mollusk.with_context(account_store);
+//!
+//! context.process_and_validate_instruction(
+//!     &loader_v4_instruction::deploy(&program_id, &authority),
+//!     &[Check::success()],
+//! );
+//! ```
+//!
 //! ## Fixtures
 //!
 //! Mollusk also supports working with multiple kinds of fixtures, which can
@@ -440,21 +951,33 @@
 //! capabilities are provided by the respective fixture crates.
 
 pub mod account_store;
+pub mod address_lookup_table;
 mod callback;
 mod compile_accounts;
+#[cfg(feature = "coverage")]
+pub mod coverage;
 #[cfg(feature = "sbpf-debugger")]
 pub mod debugger;
 pub mod epoch_stake;
-#[cfg(any(feature = "fuzz", feature = "fuzz-fd", feature = "precompiles"))]
 mod feature_set;
+mod fees;
 pub mod file;
 #[cfg(any(feature = "fuzz", feature = "fuzz-fd"))]
 pub mod fuzz;
 pub mod instructions_sysvar;
+#[cfg(feature = "invocation-tree")]
+mod invocation_tree;
+mod loader_v4;
 mod message_result;
+pub mod mock;
+pub mod native;
+#[cfg(feature = "profiler")]
+pub mod profiler;
 pub mod program;
 #[cfg(feature = "register-tracing")]
 pub mod register_tracing;
+mod rent_state;
+pub mod syscall_intercept;
 pub mod sysvar;
 
 #[cfg(feature = "invocation-inspect-callback")]
@@ -466,19 +989,27 @@ pub use mollusk_svm_result as result;
 use mollusk_svm_result::Compare;
 use {
     crate::{
-        account_store::AccountStore, callback::invoke_context::MolluskInvokeContextCallback,
-        epoch_stake::EpochStake, message_result::MessageResult, program::ProgramCache,
+        account_store::AccountStore,
+        callback::invoke_context::MolluskInvokeContextCallback,
+        epoch_stake::EpochStake,
+        message_result::MessageResult,
+        mock::MockInvocation,
+        program::{ProgramCache, ProgramCacheSnapshot},
+        syscall_intercept::{SyscallAction, SyscallCall, SyscallInterceptError},
         sysvar::Sysvars,
     },
     mollusk_svm_error::error::{MolluskError, MolluskPanic},
     mollusk_svm_result::{
-        types::TransactionResult, Check, CheckContext, Config, InstructionResult,
+        types::{TransactionFee, TransactionProgramResult, TransactionResult},
+        Check, CheckContext, Config, InstructionResult,
     },
     solana_account::{Account, AccountSharedData, ReadableAccount},
     solana_compute_budget::compute_budget::ComputeBudget,
     solana_hash::Hash,
     solana_instruction::{AccountMeta, Instruction},
-    solana_message::SanitizedMessage,
+    solana_message::{SanitizedMessage, VersionedMessage},
+    solana_program_entrypoint::ProcessInstruction,
+    solana_program_error::ProgramError,
     solana_program_runtime::{
         invoke_context::{EnvironmentConfig, InvokeContext},
         loaded_programs::ProgramRuntimeEnvironments,
@@ -500,12 +1031,16 @@ use {
 };
 #[cfg(feature = "inner-instructions")]
 use {
+    mollusk_svm_result::types::ResolvedInnerInstruction,
     solana_message::compiled_instruction::CompiledInstruction,
     solana_transaction_status_client_types::InnerInstruction,
 };
 
 pub(crate) const DEFAULT_LOADER_KEY: Pubkey = solana_sdk_ids::bpf_loader_upgradeable::id();
 
+/// The default number of lamports charged per signature.
+pub const DEFAULT_LAMPORTS_PER_SIGNATURE: u64 = 5000;
+
 /// The Mollusk API, providing a simple interface for testing Solana programs.
 ///
 /// All fields can be manipulated through a handful of helper methods, but
@@ -519,6 +1054,22 @@ pub struct Mollusk {
     pub program_cache: ProgramCache,
     pub sysvars: Sysvars,
 
+    /// The blockhash provided to the runtime environment.
+    pub blockhash: Hash,
+    /// The lamports charged per signature when processing fee-paying
+    /// transactions with `process_transaction`.
+    pub lamports_per_signature: u64,
+    /// Whether to enforce the runtime's rent state transition rules on all
+    /// writable accounts after processing a transaction. Disabled by default.
+    ///
+    /// Only supported by the transaction APIs, such as
+    /// `process_transaction_instructions`, `process_versioned_message`, and
+    /// `process_transaction`. A rent state violation is a transaction error,
+    /// which an `InstructionResult` can't represent, so the instruction APIs,
+    /// such as `process_instruction` and `process_instruction_chain`, panic
+    /// when it's set.
+    pub enforce_rent_state_transitions: bool,
+
     /// The callback which can be used to inspect invoke_context
     /// and extract low-level information such as bpf traces, transaction
     /// context, detailed timings, etc.
@@ -549,6 +1100,11 @@ impl Default for Mollusk {
         #[cfg(feature = "register-tracing")]
         let _enable_register_tracing = std::env::var("SBF_TRACE_DIR").is_ok();
 
+        // Likewise for coverage, provided `SBF_COVERAGE_DIR` is set.
+        #[cfg(feature = "coverage")]
+        let _enable_register_tracing =
+            _enable_register_tracing || std::env::var("SBF_COVERAGE_DIR").is_ok();
+
         Self::new_inner(_enable_register_tracing)
     }
 }
@@ -594,6 +1150,10 @@ impl Mollusk {
             program_cache,
             sysvars: Sysvars::default(),
 
+            blockhash: Hash::default(),
+            lamports_per_signature: DEFAULT_LAMPORTS_PER_SIGNATURE,
+            enforce_rent_state_transitions: false,
+
             #[cfg(feature = "invocation-inspect-callback")]
             invocation_inspect_callback: Box::new(EmptyInvocationInspectCallback {}),
 
@@ -611,6 +1171,13 @@ impl Mollusk {
             me.invocation_inspect_callback = Box::new(DefaultRegisterTracingCallback::default());
         }
 
+        #[cfg(feature = "coverage")]
+        if enable_register_tracing && std::env::var("SBF_COVERAGE_DIR").is_ok() {
+            // Collecting coverage takes precedence over persisting the raw
+            // register traces.
+            me.invocation_inspect_callback = Box::new(coverage::CoverageCallback::default());
+        }
+
         me
     }
 
@@ -645,6 +1212,8 @@ impl Mollusk {
     /// - A default [`DefaultRegisterTracingCallback`] is installed
     /// - Trace data is written to `SBF_TRACE_DIR` (or `target/sbf/trace` by
     ///   default)
+    /// - With the `coverage` feature, a [`coverage::CoverageCallback`] is
+    ///   installed instead if `SBF_COVERAGE_DIR` is set
     #[cfg(feature = "register-tracing")]
     pub fn new_debuggable(
         program_id: &Pubkey,
@@ -689,6 +1258,66 @@ impl Mollusk {
         self.program_cache.add_program(program_id, loader_key, elf);
     }
 
+    /// Add a mock program to the test environment, backed by a closure.
+    ///
+    /// The closure is called whenever the program is invoked, including via
+    /// CPI. It can inspect the instruction data and accounts, modify
+    /// accounts, set return data, and log messages. Returning an error fails
+    /// the invocation with that error. Each invocation consumes the same
+    /// compute units as a System program instruction.
+    ///
+    /// This is useful for simulating programs whose ELFs aren't available,
+    /// such as third-party CPI targets.
+    pub fn add_mock_program(
+        &mut self,
+        program_id: &Pubkey,
+        processor: impl Fn(MockInvocation) -> Result<(), ProgramError> + 'static,
+    ) {
+        self.program_cache
+            .add_mock_program(program_id, Rc::new(processor));
+    }
+
+    /// Add a native program to the test environment, executed by calling its
+    /// Rust entrypoint directly on the host.
+    ///
+    /// The program runs inside the same invoke context as every other
+    /// program, so checks, fixtures, and `MolluskContext` work unchanged,
+    /// while the program logic can be debugged like any other Rust code.
+    ///
+    /// Off-chain, the SDK's syscall wrappers are no-ops, so native programs
+    /// must log, CPI, and set return data through
+    /// `solana_sysvar::program_stubs`. Compute units are approximated, since
+    /// the program's own instructions aren't metered.
+    pub fn add_native_program(&mut self, program_id: &Pubkey, entrypoint: ProcessInstruction) {
+        self.program_cache
+            .add_native_program(program_id, entrypoint);
+    }
+
+    /// Intercept a syscall made by SBF programs, such as
+    /// `sol_get_clock_sysvar` or `sol_log_`.
+    ///
+    /// The interceptor is called for every call to the syscall with a
+    /// `SyscallCall`, which includes the call's arguments and the number of
+    /// previous calls while processing the current instruction or
+    /// transaction. It returns a `SyscallAction`, which either passes the call
+    /// through to the original syscall, returns a value in its place, or
+    /// aborts the program with an error. Intercepting a syscall again
+    /// replaces its interceptor.
+    ///
+    /// At most `MAX_WRAPPED_SYSCALLS` (128) distinct syscalls can be
+    /// intercepted in a single process, across all `Mollusk` instances.
+    ///
+    /// Returns an error if no syscall with the provided name is registered,
+    /// or once the limit is reached.
+    pub fn intercept_syscall(
+        &mut self,
+        name: &str,
+        interceptor: impl Fn(&SyscallCall) -> SyscallAction + 'static,
+    ) -> Result<(), SyscallInterceptError> {
+        self.program_cache
+            .intercept_syscall(name, Rc::new(interceptor))
+    }
+
     /// Warp the test environment to a slot by updating sysvars.
     pub fn warp_to_slot(&mut self, slot: u64) {
         self.sysvars.warp_to_slot(slot)
@@ -757,10 +1386,23 @@ impl Mollusk {
         )
     }
 
+    // Returns both the compiled inner instructions and their resolved
+    // counterparts, grouped by top-level instruction.
     #[cfg(feature = "inner-instructions")]
     fn deconstruct_inner_instructions(
         transaction_context: &mut TransactionContext,
-    ) -> Vec<Vec<InnerInstruction>> {
+    ) -> (
+        Vec<Vec<InnerInstruction>>,
+        Vec<Vec<ResolvedInnerInstruction>>,
+    ) {
+        let account_keys = (0..transaction_context.get_number_of_accounts())
+            .map(|index_in_transaction| {
+                transaction_context
+                    .get_key_of_account_at_index(index_in_transaction)
+                    .copied()
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<_>>();
         let (frames, accounts, data) = transaction_context.take_instruction_trace();
 
         // The instruction trace lays out all top-level frames first
@@ -775,9 +1417,11 @@ impl Mollusk {
 
         let mut all_inner_instructions: Vec<Vec<InnerInstruction>> =
             (0..num_top_level).map(|_| Vec::new()).collect();
+        let mut all_resolved_inner_instructions: Vec<Vec<ResolvedInnerInstruction>> =
+            (0..num_top_level).map(|_| Vec::new()).collect();
 
         for (i, frame) in frames.iter().enumerate().skip(num_top_level) {
-            let stack_height = frame.nesting_level.saturating_add(1);
+            let stack_height = u32::from(frame.nesting_level.saturating_add(1));
 
             let mut ancestor = i;
             while ancestor >= num_top_level {
@@ -793,12 +1437,29 @@ impl Mollusk {
                         .map(|acc| acc.index_in_transaction as u8)
                         .collect(),
                 ),
-                stack_height: Some(u32::from(stack_height)),
+                stack_height: Some(stack_height),
             };
             all_inner_instructions[ancestor].push(inner_instruction);
+
+            let resolved_inner_instruction = ResolvedInnerInstruction {
+                instruction: Instruction {
+                    program_id: account_keys[frame.program_account_index_in_tx as usize],
+                    accounts: accounts[i]
+                        .iter()
+                        .map(|acc| AccountMeta {
+                            pubkey: account_keys[acc.index_in_transaction as usize],
+                            is_signer: acc.is_signer(),
+                            is_writable: acc.is_writable(),
+                        })
+                        .collect(),
+                    data: data[i].to_vec(),
+                },
+                stack_height,
+            };
+            all_resolved_inner_instructions[ancestor].push(resolved_inner_instruction);
         }
 
-        all_inner_instructions
+        (all_inner_instructions, all_resolved_inner_instructions)
     }
 
     fn deconstruct_resulting_accounts(
@@ -835,7 +1496,17 @@ impl Mollusk {
         let mut compute_units_consumed = 0;
         let mut timings = ExecuteTimings::default();
 
+        let _active_mocks = crate::mock::ActiveMocksGuard::new(self.program_cache.mocks());
+        let _active_native_programs =
+            crate::native::ActiveNativeProgramsGuard::new(self.program_cache.native_programs());
+        let _active_syscall_interceptors =
+            crate::syscall_intercept::ActiveSyscallInterceptorsGuard::new(
+                self.program_cache.syscall_interceptors(),
+            );
         let mut program_cache = self.program_cache.cache();
+        // Programs deployed during execution are deployed at the current
+        // slot, which must match the cache's slot.
+        program_cache.set_slot_for_tests(self.sysvars.clock.slot);
         let callback = MolluskInvokeContextCallback {
             epoch_stake: &self.epoch_stake,
             feature_set: &self.feature_set,
@@ -846,31 +1517,38 @@ impl Mollusk {
         #[cfg(feature = "register-tracing")]
         let _enable_register_tracing = self.enable_register_tracing;
 
-        let program_runtime_environment = create_program_runtime_environment(
-            &self.feature_set,
-            &execution_budget,
-            /* reject_deployment_of_broken_elfs */ false,
-            /* debugging_features */ _enable_register_tracing,
-        )
-        .unwrap();
+        let program_runtime_environment = self.program_cache.wrap_environment(
+            &create_program_runtime_environment(
+                &self.feature_set,
+                &execution_budget,
+                /* reject_deployment_of_broken_elfs */ false,
+                /* debugging_features */ _enable_register_tracing,
+            )
+            .unwrap(),
+        );
         let program_runtime_environments = ProgramRuntimeEnvironments::new(
             /* execution */ program_runtime_environment.clone(),
             /* deployment */ program_runtime_environment,
         );
 
+        // Logs are written to `self.logger` if set, so its byte limit applies,
+        // and the messages appended during this execution are captured.
+        let log_collector = self.logger.clone().unwrap_or_else(LogCollector::new_ref);
+        let logs_start = log_collector.borrow().get_recorded_content().len();
+
         let mut invoke_context = InvokeContext::new(
             transaction_context,
             &mut program_cache,
             EnvironmentConfig::new(
-                Hash::default(),
-                /* blockhash_lamports_per_signature */ 5000, // The default value
+                self.blockhash,
+                self.lamports_per_signature,
                 /* alpenglow_migration_succeeded */ false,
                 &callback,
                 &self.feature_set,
                 &program_runtime_environments,
                 sysvar_cache,
             ),
-            self.logger.clone(),
+            Some(Rc::clone(&log_collector)),
             self.compute_budget.to_budget(),
             self.compute_budget.to_cost(),
         );
@@ -881,6 +1559,9 @@ impl Mollusk {
             .or_panic_with(MolluskError::InstructionPreparationFailed);
 
         let mut raw_result = Ok(());
+        let mut instruction_compute_units = vec![];
+        #[cfg(feature = "invocation-tree")]
+        let mut top_level_outcomes = vec![];
 
         for (instruction_index, (program_id, compiled_ix)) in
             sanitized_message.program_instructions_iter().enumerate()
@@ -914,6 +1595,13 @@ impl Mollusk {
                     .process_instruction(&mut compute_units_consumed_instruction, &mut timings)
             };
             compute_units_consumed += compute_units_consumed_instruction;
+            instruction_compute_units.push(compute_units_consumed_instruction);
+
+            #[cfg(feature = "invocation-tree")]
+            top_level_outcomes.push(crate::invocation_tree::TopLevelOutcome {
+                compute_units_consumed: compute_units_consumed_instruction,
+                result: invoke_result.clone(),
+            });
 
             #[cfg(feature = "invocation-inspect-callback")]
             self.invocation_inspect_callback.after_invocation(
@@ -931,20 +1619,86 @@ impl Mollusk {
             }
         }
 
+        // Programs deployed or modified during the transaction are not
+        // persisted to the cache. `MolluskContext` reloads them from the
+        // resulting accounts instead.
+        program_cache.drain_modified_entries();
+
         let return_data = transaction_context.get_return_data().1.to_vec();
 
+        let logs = log_collector.borrow().get_recorded_content()[logs_start..].to_vec();
+
+        #[cfg(feature = "invocation-tree")]
+        let invocations = crate::invocation_tree::build_invocation_tree(
+            transaction_context,
+            &top_level_outcomes,
+            &logs,
+        );
+
         #[cfg(feature = "inner-instructions")]
-        let inner_instructions = Self::deconstruct_inner_instructions(transaction_context);
+        let (inner_instructions, resolved_inner_instructions) =
+            Self::deconstruct_inner_instructions(transaction_context);
 
         MessageResult {
             compute_units_consumed,
+            instruction_compute_units,
             execution_time: timings.details.execute_us.0,
             raw_result,
             return_data,
+            logs,
             #[cfg(feature = "inner-instructions")]
             inner_instructions,
             #[cfg(feature = "inner-instructions")]
+            resolved_inner_instructions,
+            #[cfg(feature = "inner-instructions")]
             message: Some(sanitized_message.clone()),
+            #[cfg(feature = "syscall-trace")]
+            syscall_trace: _active_syscall_interceptors.take_trace(),
+            #[cfg(feature = "invocation-tree")]
+            invocations,
+        }
+    }
+
+    // Process a transaction message, then verify the rent state transitions
+    // of all writable accounts if `enforce_rent_state_transitions` is set.
+    fn process_transaction_message_with_rent_checks<'a>(
+        &self,
+        sanitized_message: &'a SanitizedMessage,
+        transaction_context: &mut TransactionContext<'a>,
+        sysvar_cache: &SysvarCache,
+    ) -> MessageResult {
+        let relax_post_exec_min_balance_check = self.feature_set.relax_post_exec_min_balance_check;
+        let pre_state_infos = self.enforce_rent_state_transitions.then(|| {
+            crate::rent_state::pre_exec_state_infos(
+                transaction_context,
+                sanitized_message,
+                &self.sysvars.rent,
+                relax_post_exec_min_balance_check,
+            )
+        });
+
+        let mut message_result =
+            self.process_transaction_message(sanitized_message, transaction_context, sysvar_cache);
+
+        if let Some(pre_state_infos) = pre_state_infos {
+            if message_result.raw_result.is_ok() {
+                message_result.raw_result = crate::rent_state::verify_post_exec_state_transitions(
+                    &pre_state_infos,
+                    transaction_context,
+                    &self.sysvars.rent,
+                    relax_post_exec_min_balance_check,
+                );
+            }
+        }
+
+        message_result
+    }
+
+    // The instruction APIs can't report a rent state violation, so refuse to
+    // run them rather than silently skip the checks.
+    fn assert_rent_state_transitions_not_enforced(&self, api: &str) {
+        if self.enforce_rent_state_transitions {
+            panic!("{}", MolluskError::RentStateTransitionsUnsupported(api));
         }
     }
 
@@ -986,6 +1740,7 @@ impl Mollusk {
             raw_result,
             return_data: message_result.return_data,
             resulting_accounts,
+            logs: message_result.logs,
             #[cfg(feature = "inner-instructions")]
             inner_instructions: message_result
                 .inner_instructions
@@ -993,7 +1748,17 @@ impl Mollusk {
                 .next()
                 .unwrap_or_default(),
             #[cfg(feature = "inner-instructions")]
+            resolved_inner_instructions: message_result
+                .resolved_inner_instructions
+                .into_iter()
+                .next()
+                .unwrap_or_default(),
+            #[cfg(feature = "inner-instructions")]
             message: message_result.message,
+            #[cfg(feature = "syscall-trace")]
+            syscall_trace: message_result.syscall_trace,
+            #[cfg(feature = "invocation-tree")]
+            invocations: message_result.invocations,
         };
 
         #[cfg(any(feature = "fuzz", feature = "fuzz-fd"))]
@@ -1028,6 +1793,8 @@ impl Mollusk {
         instruction: &Instruction,
         accounts: &[(Pubkey, Account)],
     ) -> InstructionResult {
+        self.assert_rent_state_transitions_not_enforced("process_instruction");
+
         let fallback_accounts = self.get_account_fallbacks(
             std::iter::once(&instruction.program_id),
             std::iter::once(instruction),
@@ -1066,6 +1833,7 @@ impl Mollusk {
             raw_result,
             return_data: message_result.return_data,
             resulting_accounts,
+            logs: message_result.logs,
             #[cfg(feature = "inner-instructions")]
             inner_instructions: message_result
                 .inner_instructions
@@ -1073,7 +1841,17 @@ impl Mollusk {
                 .next()
                 .unwrap_or_default(),
             #[cfg(feature = "inner-instructions")]
+            resolved_inner_instructions: message_result
+                .resolved_inner_instructions
+                .into_iter()
+                .next()
+                .unwrap_or_default(),
+            #[cfg(feature = "inner-instructions")]
             message: message_result.message,
+            #[cfg(feature = "syscall-trace")]
+            syscall_trace: message_result.syscall_trace,
+            #[cfg(feature = "invocation-tree")]
+            invocations: message_result.invocations,
         };
 
         #[cfg(any(feature = "fuzz", feature = "fuzz-fd"))]
@@ -1139,6 +1917,24 @@ impl Mollusk {
             resulting_accounts: accounts.to_vec(),
             ..Default::default()
         };
+        for this_result in self.process_instruction_chain_results(instructions, accounts) {
+            composite_result.absorb(this_result);
+        }
+        composite_result
+    }
+
+    /// Process a chain of instructions like `process_instruction_chain`, but
+    /// return the result of each processed instruction, rather than a single
+    /// composite result.
+    ///
+    /// Processing stops at the first failed instruction, whose result is the
+    /// last one returned.
+    pub fn process_instruction_chain_results(
+        &self,
+        instructions: &[Instruction],
+        accounts: &[(Pubkey, Account)],
+    ) -> Vec<InstructionResult> {
+        self.assert_rent_state_transitions_not_enforced("process_instruction_chain");
 
         let fallback_accounts = self.get_account_fallbacks(
             instructions.iter().map(|ix| &ix.program_id),
@@ -1148,22 +1944,25 @@ impl Mollusk {
 
         let sysvar_cache = self.sysvars.setup_sysvar_cache(accounts);
 
+        let mut results: Vec<InstructionResult> = Vec::with_capacity(instructions.len());
         for instruction in instructions.iter() {
             let this_result = self.process_instruction_chain_element(
                 instruction,
-                &composite_result.resulting_accounts,
+                results
+                    .last()
+                    .map_or(accounts, |result| &result.resulting_accounts),
                 &fallback_accounts,
                 &sysvar_cache,
             );
 
-            composite_result.absorb(this_result);
-
-            if composite_result.program_result.is_err() {
+            let failed = this_result.program_result.is_err();
+            results.push(this_result);
+            if failed {
                 break;
             }
         }
 
-        composite_result
+        results
     }
 
     /// Process multiple instructions using a single shared transaction context.
@@ -1205,12 +2004,76 @@ impl Mollusk {
                 payer,
             );
 
-        let mut transaction_context =
-            self.create_transaction_context(transaction_accounts, instructions.len());
-        let sysvar_cache = self.sysvars.setup_sysvar_cache(accounts);
+        self.process_sanitized_transaction(&sanitized_message, transaction_accounts, accounts)
+    }
 
-        let message_result = self.process_transaction_message(
+    /// Process a versioned transaction message using the minified Solana
+    /// Virtual Machine (SVM) environment.
+    ///
+    /// Both legacy and v0 messages are supported. Addresses referenced
+    /// through address lookup tables are resolved from the lookup table
+    /// accounts in the provided `accounts` slice, which must be owned by the
+    /// address lookup table program. Lookup tables are considered active
+    /// based on the configured `Clock` and `SlotHashes` sysvars. See
+    /// `address_lookup_table::create_address_lookup_table_account` for
+    /// creating lookup table accounts.
+    ///
+    /// The `accounts` slice should also contain every account referenced by
+    /// the message, including those loaded from lookup tables. Missing
+    /// program and fee payer accounts are stubbed, as with
+    /// `process_transaction_instructions`.
+    ///
+    /// Like `process_transaction_instructions`, the message is processed
+    /// atomically in a single transaction context, and no fees are charged.
+    /// If the message fails sanitization or lookup table resolution, the
+    /// result's `program_result` will be
+    /// `TransactionProgramResult::TransactionError`.
+    pub fn process_versioned_message(
+        &self,
+        message: &VersionedMessage,
+        accounts: &[(Pubkey, Account)],
+    ) -> TransactionResult {
+        let sanitized_message = match crate::address_lookup_table::sanitize_versioned_message(
+            message,
+            accounts,
+            &self.sysvars,
+        ) {
+            Ok(sanitized_message) => sanitized_message,
+            Err(err) => return Self::rejected_transaction_result(err, accounts, None),
+        };
+
+        let instructions = crate::compile_accounts::decompile_instructions(&sanitized_message);
+
+        let fallback_accounts = self.get_account_fallbacks(
+            instructions.iter().map(|ix| &ix.program_id),
+            instructions.iter(),
+            accounts,
+        );
+
+        let transaction_accounts = crate::compile_accounts::compile_sanitized_message_accounts(
             &sanitized_message,
+            &instructions,
+            accounts.iter(),
+            &fallback_accounts,
+        );
+
+        self.process_sanitized_transaction(&sanitized_message, transaction_accounts, accounts)
+    }
+
+    fn process_sanitized_transaction(
+        &self,
+        sanitized_message: &SanitizedMessage,
+        transaction_accounts: Vec<(Pubkey, AccountSharedData)>,
+        accounts: &[(Pubkey, Account)],
+    ) -> TransactionResult {
+        let mut transaction_context = self.create_transaction_context(
+            transaction_accounts,
+            sanitized_message.instructions().len(),
+        );
+        let sysvar_cache = self.sysvars.setup_sysvar_cache(accounts);
+
+        let message_result = self.process_transaction_message_with_rent_checks(
+            sanitized_message,
             &mut transaction_context,
             &sysvar_cache,
         );
@@ -1225,15 +2088,53 @@ impl Mollusk {
 
         TransactionResult {
             compute_units_consumed: message_result.compute_units_consumed,
+            instruction_compute_units: message_result.instruction_compute_units,
             execution_time: message_result.execution_time,
             program_result,
             raw_result: message_result.raw_result,
+            fee: TransactionFee::default(),
             return_data: message_result.return_data,
             resulting_accounts,
+            logs: message_result.logs,
             #[cfg(feature = "inner-instructions")]
             inner_instructions: message_result.inner_instructions,
             #[cfg(feature = "inner-instructions")]
+            resolved_inner_instructions: message_result.resolved_inner_instructions,
+            #[cfg(feature = "inner-instructions")]
             message: message_result.message,
+            #[cfg(feature = "syscall-trace")]
+            syscall_trace: message_result.syscall_trace,
+            #[cfg(feature = "invocation-tree")]
+            invocations: message_result.invocations,
+        }
+    }
+
+    // The result of a transaction rejected by the runtime before execution.
+    fn rejected_transaction_result(
+        err: TransactionError,
+        accounts: &[(Pubkey, Account)],
+        _sanitized_message: Option<SanitizedMessage>,
+    ) -> TransactionResult {
+        TransactionResult {
+            compute_units_consumed: 0,
+            instruction_compute_units: vec![],
+            execution_time: 0,
+            program_result: TransactionProgramResult::TransactionError(err.clone()),
+            raw_result: Err(err),
+            fee: TransactionFee::default(),
+            return_data: vec![],
+            resulting_accounts: accounts.to_vec(),
+            logs: vec![],
+            #[cfg(feature = "inner-instructions")]
+            inner_instructions: vec![],
+            #[cfg(feature = "inner-instructions")]
+            resolved_inner_instructions: vec![],
+            #[cfg(feature = "inner-instructions")]
+            message: _sanitized_message,
+            #[cfg(feature = "syscall-trace")]
+            syscall_trace: vec![],
+            #[cfg(feature = "invocation-tree")]
+            invocations: vec![],
         }
     }
 
@@ -1317,6 +2218,8 @@ impl Mollusk {
         instructions: &[(&Instruction, &[Check])],
         accounts: &[(Pubkey, Account)],
     ) -> InstructionResult {
+        self.assert_rent_state_transitions_not_enforced("process_and_validate_instruction_chain");
+
         let mut composite_result = InstructionResult {
             resulting_accounts: accounts.to_vec(),
             ..Default::default()
@@ -1382,6 +2285,186 @@ impl Mollusk {
         result
     }
 
+    /// Process a versioned transaction message using the minified Solana
+    /// Virtual Machine (SVM) environment, then perform checks on the result.
+    /// Panics if any checks fail.
+    ///
+    /// See `process_versioned_message` for details on how lookup table
+    /// addresses are resolved.
+    pub fn process_and_validate_versioned_message(
+        &self,
+        message: &VersionedMessage,
+        accounts: &[(Pubkey, Account)],
+        checks: &[Check],
+    ) -> TransactionResult {
+        let result = self.process_versioned_message(message, accounts);
+        result.run_checks(checks, &self.config, self);
+        result
+    }
+
+    // Verify the transaction's signatures and charge the fee payer, which is
+    // always the first account in the compiled message.
+    fn charge_transaction_fee(
+        &self,
+        sanitized_message: &SanitizedMessage,
+        transaction_accounts: &mut [(Pubkey, AccountSharedData)],
+        payer: &Pubkey,
+        signers: &[Pubkey],
+    ) -> Result<TransactionFee, TransactionError> {
+        let num_required_signatures = sanitized_message.header().num_required_signatures as usize;
+        if !sanitized_message
+            .account_keys()
+            .iter()
+            .take(num_required_signatures)
+            .all(|key| key == payer || signers.contains(key))
+        {
+            return Err(TransactionError::SignatureFailure);
+        }
+
+        let fee = fees::calculate_fee(
+            sanitized_message,
+            self.lamports_per_signature,
+            &self.feature_set,
+        )?;
+
+        fees::validate_and_charge_fee_payer(
+            &mut transaction_accounts[0].1,
+            fee.total(),
+            &self.sysvars.rent,
+            self.feature_set.relax_post_exec_min_balance_check,
+        )?;
+
+        Ok(fee)
+    }
+
+    /// Process multiple instructions as a fee-paying transaction.
+    ///
+    /// Unlike `process_transaction_instructions`, this API mirrors the
+    /// runtime's transaction processing more closely:
+    ///
+    /// * Every account required to sign the message must be either the `payer`
+    ///   or one of the provided `signers`. Otherwise, the transaction is
+    ///   rejected with `TransactionError::SignatureFailure`.
+    /// * The fee payer must be a funded system account. The base signature fee
+    ///   (`lamports_per_signature` per signature) plus any prioritization fee
+    ///   requested through compute budget instructions is deducted before
+    ///   execution.
+    /// * If any instruction fails, all account changes are discarded except for
+    ///   the fee deduction, which is kept.
+    ///
+    /// Transactions rejected before execution, such as for an invalid fee
+    /// payer, are not charged a fee, and their `program_result` will be
+    /// `TransactionProgramResult::TransactionError`.
+    ///
+    /// Note: Compute budget instructions are only used to calculate the
+    /// prioritization fee. Execution still uses Mollusk's `compute_budget`.
+    /// Processing compute budget instructions requires the `all-builtins`
+    /// feature.
+    ///
+    /// The charged fee is reported in the result's `fee` field.
+    pub fn process_transaction(
+        &self,
+        instructions: &[Instruction],
+        accounts: &[(Pubkey, Account)],
+        payer: &Pubkey,
+        signers: &[Pubkey],
+    ) -> TransactionResult {
+        let fallback_accounts = self.get_account_fallbacks(
+            instructions.iter().map(|ix| &ix.program_id),
+            instructions.iter(),
+            accounts,
+        );
+
+        let (sanitized_message, mut transaction_accounts) =
+            crate::compile_accounts::compile_accounts_with_payer(
+                instructions,
+                accounts.iter(),
+                &fallback_accounts,
+                Some(payer),
+            );
+
+        let fee = match self.charge_transaction_fee(
+            &sanitized_message,
+            &mut transaction_accounts,
+            payer,
+            signers,
+        ) {
+            Ok(fee) => fee,
+            Err(err) => {
+                return Self::rejected_transaction_result(err, accounts, Some(sanitized_message))
+            }
+        };
+
+        let charged_payer_account = Account::from(transaction_accounts[0].1.clone());
+
+        let mut transaction_context =
+            self.create_transaction_context(transaction_accounts, instructions.len());
+        let sysvar_cache = self.sysvars.setup_sysvar_cache(accounts);
+
+        let message_result = self.process_transaction_message_with_rent_checks(
+            &sanitized_message,
+            &mut transaction_context,
+            &sysvar_cache,
+        );
+
+        let resulting_accounts = if message_result.raw_result.is_ok() {
+            Self::deconstruct_resulting_accounts(&transaction_context, accounts)
+        } else {
+            // The fee is charged even if the transaction fails.
+            accounts
+                .iter()
+                .map(|(key, account)| {
+                    if key == payer {
+                        (*key, charged_payer_account.clone())
+                    } else {
+                        (*key, account.clone())
+                    }
+                })
+                .collect()
+        };
+
+        let program_result = MessageResult::extract_txn_program_result(&message_result.raw_result);
+
+        TransactionResult {
+            compute_units_consumed: message_result.compute_units_consumed,
+            instruction_compute_units: message_result.instruction_compute_units,
+            execution_time: message_result.execution_time,
+            program_result,
+            raw_result: message_result.raw_result,
+            fee,
+            return_data: message_result.return_data,
+            resulting_accounts,
+            logs: message_result.logs,
+            #[cfg(feature = "inner-instructions")]
+            inner_instructions: message_result.inner_instructions,
+            #[cfg(feature = "inner-instructions")]
+            resolved_inner_instructions: message_result.resolved_inner_instructions,
+            #[cfg(feature = "inner-instructions")]
+            message: message_result.message,
+            #[cfg(feature = "syscall-trace")]
+            syscall_trace: message_result.syscall_trace,
+            #[cfg(feature = "invocation-tree")]
+            invocations: message_result.invocations,
+        }
+    }
+
+    /// Process multiple instructions as a fee-paying transaction, then
+    /// perform checks on the result. Panics if any checks fail.
+    ///
+    /// See `process_transaction` for details on how fees are charged.
+    pub fn process_and_validate_transaction(
+        &self,
+        instructions: &[Instruction],
+        accounts: &[(Pubkey, Account)],
+        payer: &Pubkey,
+        signers: &[Pubkey],
+        checks: &[Check],
+    ) -> TransactionResult {
+        let result = self.process_transaction(instructions, accounts, payer, signers);
+        result.run_checks(checks, &self.config, self);
+        result
+    }
+
     #[cfg(feature = "fuzz")]
     /// Process a fuzz fixture using the minified Solana Virtual Machine (SVM)
     /// environment.
@@ -1668,13 +2751,17 @@ impl<AS: AccountStore> MolluskContext<AS> {
     ) -> Vec<(Pubkey, Account)> {
         let mut accounts = Vec::new();
 
+        let store = self.account_store.borrow();
+
         // If hydration is enabled, add sysvars and program accounts regardless
-        // of whether or not they exist already.
+        // of whether or not they exist already. Program accounts in the store
+        // take precedence, since Loader v4 program accounts carry state.
         if self.hydrate_store {
             self.mollusk
                 .program_cache
                 .get_all_keyed_program_accounts()
                 .into_iter()
+                .filter(|(pubkey, _)| store.get_account(pubkey).is_none())
                 .chain(self.mollusk.sysvars.get_all_keyed_sysvar_accounts())
                 .for_each(|(pubkey, account)| {
                     accounts.push((pubkey, account));
@@ -1684,7 +2771,6 @@ impl<AS: AccountStore> MolluskContext<AS> {
         // Regardless of hydration, only add an account if the caller hasn't
         // already loaded it into the store.
         let mut seen = HashSet::new();
-        let store = self.account_store.borrow();
         instructions.for_each(|instruction| {
             instruction
                 .accounts
@@ -1712,21 +2798,119 @@ impl<AS: AccountStore> MolluskContext<AS> {
         accounts
     }
 
-    fn consume_mollusk_result(&self, result: &InstructionResult) {
-        if result.program_result.is_ok() {
-            // Only store resulting accounts if the result was success.
+    fn store_resulting_accounts(&self, resulting_accounts: &[(Pubkey, Account)]) {
+        let mut modified_loader_v3_accounts = Vec::new();
+        let mut modified_loader_v4_accounts = Vec::new();
+        {
             let mut store = self.account_store.borrow_mut();
-            for (pubkey, account) in result.resulting_accounts.iter() {
+            for (pubkey, account) in resulting_accounts.iter() {
+                if store.get_account(pubkey).as_ref() != Some(account) {
+                    if account.owner == crate::program::loader_keys::LOADER_V3 {
+                        modified_loader_v3_accounts.push((*pubkey, account.clone()));
+                    } else if account.owner == crate::program::loader_keys::LOADER_V4 {
+                        modified_loader_v4_accounts.push((*pubkey, account.clone()));
+                    }
+                }
                 store.store_account(*pubkey, account.clone());
             }
         }
+
+        // Keep the program cache in sync with any deployed, upgraded,
+        // extended, retracted, or closed programs.
+        if !modified_loader_v3_accounts.is_empty() {
+            let store = self.account_store.borrow();
+            self.mollusk
+                .program_cache
+                .refresh_loader_v3_programs(&modified_loader_v3_accounts, |pubkey| {
+                    store.get_account(pubkey)
+                });
+        }
+        if !modified_loader_v4_accounts.is_empty() {
+            self.mollusk
+                .program_cache
+                .refresh_loader_v4_programs(&modified_loader_v4_accounts);
+        }
+    }
+
+    fn consume_mollusk_result(&self, result: &InstructionResult) {
+        if result.program_result.is_ok() {
+            // Only store resulting accounts if the result was success.
+            self.store_resulting_accounts(&result.resulting_accounts);
+        }
     }
 
     fn consume_transaction_result(&self, result: &TransactionResult) {
         if result.program_result.is_ok() {
-            let mut store = self.account_store.borrow_mut();
-            for (pubkey, account) in result.resulting_accounts.iter() {
-                store.store_account(*pubkey, account.clone());
+            self.store_resulting_accounts(&result.resulting_accounts);
+        }
+    }
+
+    fn load_accounts_for_transaction(
+        &self,
+        instructions: &[Instruction],
+        payer: &Pubkey,
+    ) -> Vec<(Pubkey, Account)> {
+        let mut accounts = self.load_accounts_for_instructions(instructions.iter());
+        if !accounts.iter().any(|(key, _)| key == payer) {
+            let store = self.account_store.borrow();
+            let account = store
+                .get_account(payer)
+                .unwrap_or_else(|| store.default_account(payer));
+            accounts.push((*payer, account));
+        }
+        accounts
+    }
+
+    fn load_accounts_for_versioned_message(
+        &self,
+        message: &VersionedMessage,
+    ) -> Vec<(Pubkey, Account)> {
+        // Load the lookup tables first, so their addresses can be resolved.
+        let lookup_table_accounts: Vec<_> = {
+            let store = self.account_store.borrow();
+            message
+                .address_table_lookups()
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|lookup| {
+                    store
+                        .get_account(&lookup.account_key)
+                        .map(|account| (lookup.account_key, account))
+                })
+                .collect()
+        };
+
+        let Ok(sanitized_message) = crate::address_lookup_table::sanitize_versioned_message(
+            message,
+            &lookup_table_accounts,
+            &self.mollusk.sysvars,
+        ) else {
+            // Mollusk will reject the message with the appropriate error.
+            return lookup_table_accounts;
+        };
+
+        let instructions = crate::compile_accounts::decompile_instructions(&sanitized_message);
+        let mut accounts =
+            self.load_accounts_for_transaction(&instructions, sanitized_message.fee_payer());
+        lookup_table_accounts
+            .into_iter()
+            .for_each(|(pubkey, account)| {
+                if !accounts.iter().any(|(key, _)| key == &pubkey) {
+                    accounts.push((pubkey, account));
+                }
+            });
+        accounts
+    }
+
+    fn consume_fee_paying_transaction_result(&self, result: &TransactionResult, payer: &Pubkey) {
+        if result.program_result.is_ok() {
+            self.consume_transaction_result(result);
+        } else if result.fee != TransactionFee::default() {
+            // The fee is charged even if the transaction fails.
+            if let Some(account) = result.get_account(payer) {
+                self.account_store
+                    .borrow_mut()
+                    .store_account(*payer, account.clone());
             }
         }
     }
@@ -1820,4 +3004,124 @@ impl<AS: AccountStore> MolluskContext<AS> {
         self.consume_transaction_result(&result);
         result
     }
+    /// Process multiple instructions as a fee-paying transaction using the
+    /// minified Solana Virtual Machine (SVM) environment.
+    ///
+    /// The fee payer is loaded from the account store. If the transaction
+    /// fails after the fee was charged, only the fee payer's debited balance
+    /// is persisted.
+    ///
+    /// See `Mollusk::process_transaction` for details on how fees are
+    /// charged.
+    pub fn process_transaction(
+        &self,
+        instructions: &[Instruction],
+        payer: &Pubkey,
+        signers: &[Pubkey],
+    ) -> TransactionResult {
+        let accounts = self.load_accounts_for_transaction(instructions, payer);
+        let result = self
+            .mollusk
+            .process_transaction(instructions, &accounts, payer, signers);
+        self.consume_fee_paying_transaction_result(&result, payer);
+        result
+    }
+
+    /// Process multiple instructions as a fee-paying transaction using the
+    /// minified Solana Virtual Machine (SVM) environment, then perform checks
+    /// on the result.
+    pub fn process_and_validate_transaction(
+        &self,
+        instructions: &[Instruction],
+        payer: &Pubkey,
+        signers: &[Pubkey],
+        checks: &[Check],
+    ) -> TransactionResult {
+        let accounts = self.load_accounts_for_transaction(instructions, payer);
+        let result = self.mollusk.process_and_validate_transaction(
+            instructions,
+            &accounts,
+            payer,
+            signers,
+            checks,
+        );
+        self.consume_fee_paying_transaction_result(&result, payer);
+        result
+    }
+
+    /// Process a versioned transaction message using the minified Solana
+    /// Virtual Machine (SVM) environment.
+    ///
+    /// Address lookup tables and all accounts referenced by the message,
+    /// including those loaded from lookup tables, are loaded from the account
+    /// store.
+    ///
+    /// See `Mollusk::process_versioned_message` for more details.
+    pub fn process_versioned_message(&self, message: &VersionedMessage) -> TransactionResult {
+        let accounts = self.load_accounts_for_versioned_message(message);
+        let result = self.mollusk.process_versioned_message(message, &accounts);
+        self.consume_transaction_result(&result);
+        result
+    }
+
+    /// Process a versioned transaction message using the minified Solana
+    /// Virtual Machine (SVM) environment, then perform checks on the result.
+    pub fn process_and_validate_versioned_message(
+        &self,
+        message: &VersionedMessage,
+        checks: &[Check],
+    ) -> TransactionResult {
+        let accounts = self.load_accounts_for_versioned_message(message);
+        let result = self
+            .mollusk
+            .process_and_validate_versioned_message(message, &accounts, checks);
+        self.consume_transaction_result(&result);
+        result
+    }
+}
+
+/// A point-in-time copy of a `MolluskContext`'s state, created with
+/// `MolluskContext::snapshot`.
+///
+/// Captures the account store, sysvars, epoch stake, and program cache.
+#[derive(Clone)]
+pub struct MolluskContextSnapshot<AS> {
+    account_store: AS,
+    sysvars: Sysvars,
+    epoch_stake: EpochStake,
+    program_cache: ProgramCacheSnapshot,
+}
+
+impl<AS: AccountStore + Clone> MolluskContext<AS> {
+    /// Capture the current account store, sysvars, epoch stake, and program
+    /// cache.
+    ///
+    /// The snapshot can be restored any number of times with `restore`, which
+    /// makes it possible to branch many scenarios from a single setup.
+    pub fn snapshot(&self) -> MolluskContextSnapshot<AS> {
+        MolluskContextSnapshot {
+            account_store: self.account_store.borrow().clone(),
+            sysvars: self.mollusk.sysvars.clone(),
+            epoch_stake: self.mollusk.epoch_stake.clone(),
+            program_cache: self.mollusk.program_cache.snapshot(),
+        }
+    }
+
+    /// Restore the context to a previously captured snapshot, discarding all
+    /// changes made since.
+    pub fn restore(&mut self, snapshot: &MolluskContextSnapshot<AS>) {
+        *self.account_store.borrow_mut() = snapshot.account_store.clone();
+        self.mollusk.sysvars = snapshot.sysvars.clone();
+        self.mollusk.epoch_stake = snapshot.epoch_stake.clone();
+        self.mollusk.program_cache.restore(&snapshot.program_cache);
+    }
+
+    /// Run the provided closure against the context, then roll back all
+    /// changes it made. Returns the closure's result.
+    pub fn with_rollback<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let snapshot = self.snapshot();
+        let result = f(self);
+        self.restore(&snapshot);
+        result
+    }
 }
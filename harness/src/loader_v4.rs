@@ -0,0 +1,587 @@
+//! Loader v4 builtin program.
+//!
+//! It handles the program management instructions and the invocation of
+//! programs owned by Loader v4, mirroring Agave's behavior.
+//!
+//! The processor is ported from `programs/loader-v4/src/lib.rs` of
+//! `solana-loader-v4-program` 4.0.0 (Agave commit `2a165e7a90af`), the latest
+//! published release. That crate pins `solana-program-runtime = "=4.0.0"`, so
+//! it can't be used alongside the 4.2.0 runtime Mollusk depends on (Agave
+//! commit `ac82b5d438b0`). When re-syncing, diff against that file; the port
+//! only departs from it where the 4.2.0 runtime requires:
+//!
+//! * The state header is read and written by value with `get_state` and
+//!   `set_state`, instead of transmuting the account data.
+//! * Sysvars are read through `environment_config.sysvar_cache()`, and compute
+//!   units are consumed through `compute_meter`.
+//! * Instructions are deserialized with `bincode` directly, with the same
+//!   limits as `solana_bincode::limited_deserialize`.
+//! * Program execution passes the cache entry to `execute`, and doesn't record
+//!   the executor lookup time.
+//! * Deployment calls `deploy_program` directly instead of the
+//!   `deploy_program!` macro, and rejects a clock slot that doesn't match the
+//!   program cache's slot.
+//!
+//! Once a release compatible with the pinned runtime is published, this module
+//! should be replaced by a dependency on it.
+
+use {
+    bincode::Options,
+    solana_instruction::error::InstructionError,
+    solana_loader_v3_interface::state::UpgradeableLoaderState,
+    solana_loader_v4_interface::{
+        instruction::LoaderV4Instruction,
+        state::{LoaderV4State, LoaderV4Status},
+        DEPLOYMENT_COOLDOWN_IN_SLOTS,
+    },
+    solana_program_runtime::{
+        deploy::deploy_program,
+        invoke_context::InvokeContext,
+        program_cache_entry::{ProgramCacheEntry, ProgramCacheEntryOwner, ProgramCacheEntryType},
+        program_metrics::LoadProgramMetrics,
+        solana_sbpf::declare_builtin_function,
+        vm::execute,
+    },
+    solana_pubkey::Pubkey,
+    solana_sdk_ids::{bpf_loader, bpf_loader_deprecated, bpf_loader_upgradeable, loader_v4},
+    solana_svm_log_collector::{ic_logger_msg, LogCollector},
+    solana_transaction_context::{
+        instruction::InstructionContext, instruction_accounts::BorrowedInstructionAccount,
+    },
+    std::{cell::RefCell, rc::Rc, sync::Arc},
+};
+
+const DEFAULT_COMPUTE_UNITS: u64 = 2_000;
+
+// The maximum size of a serialized instruction, in bytes.
+const PACKET_DATA_SIZE: u64 = 1232;
+
+const STATUS_OFFSET: usize = 40;
+
+/// Read the `LoaderV4State` header of a program account's data.
+pub(crate) fn get_state(data: &[u8]) -> Result<LoaderV4State, InstructionError> {
+    let header = data
+        .get(0..LoaderV4State::program_data_offset())
+        .ok_or(InstructionError::AccountDataTooSmall)?;
+    let read_u64 = |bytes: &[u8]| {
+        bytes
+            .try_into()
+            .map(u64::from_le_bytes)
+            .map_err(|_| InstructionError::InvalidAccountData)
+    };
+    let status = match read_u64(&header[STATUS_OFFSET..])? {
+        0 => LoaderV4Status::Retracted,
+        1 => LoaderV4Status::Deployed,
+        2 => LoaderV4Status::Finalized,
+        _ => return Err(InstructionError::InvalidAccountData),
+    };
+    Ok(LoaderV4State {
+        slot: read_u64(&header[0..8])?,
+        authority_address_or_next_version: Pubkey::try_from(&header[8..STATUS_OFFSET])
+            .map_err(|_| InstructionError::InvalidAccountData)?,
+        status,
+    })
+}
+
+/// Write the `LoaderV4State` header of a program account's data.
+pub(crate) fn set_state(data: &mut [u8], state: &LoaderV4State) -> Result<(), InstructionError> {
+    let header = data
+        .get_mut(0..LoaderV4State::program_data_offset())
+        .ok_or(InstructionError::AccountDataTooSmall)?;
+    header[0..8].copy_from_slice(&state.slot.to_le_bytes());
+    header[8..STATUS_OFFSET].copy_from_slice(state.authority_address_or_next_version.as_ref());
+    header[STATUS_OFFSET..].copy_from_slice(&(state.status as u64).to_le_bytes());
+    Ok(())
+}
+
+fn check_program_account(
+    log_collector: &Option<Rc<RefCell<LogCollector>>>,
+    instruction_context: &InstructionContext,
+    program: &BorrowedInstructionAccount,
+    authority_address: &Pubkey,
+) -> Result<LoaderV4State, InstructionError> {
+    if !loader_v4::check_id(program.get_owner()) {
+        ic_logger_msg!(log_collector, "Program not owned by loader");
+        return Err(InstructionError::InvalidAccountOwner);
+    }
+    let state = get_state(program.get_data())?;
+    if !program.is_writable() {
+        ic_logger_msg!(log_collector, "Program is not writeable");
+        return Err(InstructionError::InvalidArgument);
+    }
+    if !instruction_context.is_instruction_account_signer(1)? {
+        ic_logger_msg!(log_collector, "Authority did not sign");
+        return Err(InstructionError::MissingRequiredSignature);
+    }
+    if state.authority_address_or_next_version != *authority_address {
+        ic_logger_msg!(log_collector, "Incorrect authority provided");
+        return Err(InstructionError::IncorrectAuthority);
+    }
+    if matches!(state.status, LoaderV4Status::Finalized) {
+        ic_logger_msg!(log_collector, "Program is finalized");
+        return Err(InstructionError::Immutable);
+    }
+    Ok(state)
+}
+
+fn process_instruction_write(
+    invoke_context: &mut InvokeContext,
+    offset: u32,
+    bytes: Vec<u8>,
+) -> Result<(), InstructionError> {
+    let log_collector = invoke_context.get_log_collector();
+    let transaction_context = &invoke_context.transaction_context;
+    let instruction_context = transaction_context.get_current_instruction_context()?;
+    let mut program = instruction_context.try_borrow_instruction_account(0)?;
+    let authority_address = instruction_context.get_key_of_instruction_account(1)?;
+    let state = check_program_account(
+        &log_collector,
+        &instruction_context,
+        &program,
+        authority_address,
+    )?;
+    if !matches!(state.status, LoaderV4Status::Retracted) {
+        ic_logger_msg!(log_collector, "Program is not retracted");
+        return Err(InstructionError::InvalidArgument);
+    }
+    let destination_offset = (offset as usize).saturating_add(LoaderV4State::program_data_offset());
+    program
+        .get_data_mut()?
+        .get_mut(destination_offset..destination_offset.saturating_add(bytes.len()))
+        .ok_or_else(|| {
+            ic_logger_msg!(log_collector, "Write out of bounds");
+            InstructionError::AccountDataTooSmall
+        })?
+        .copy_from_slice(&bytes);
+    Ok(())
+}
+
+fn process_instruction_copy(
+    invoke_context: &mut InvokeContext,
+    destination_offset: u32,
+    source_offset: u32,
+    length: u32,
+) -> Result<(), InstructionError> {
+    let log_collector = invoke_context.get_log_collector();
+    let transaction_context = &invoke_context.transaction_context;
+    let instruction_context = transaction_context.get_current_instruction_context()?;
+    let mut program = instruction_context.try_borrow_instruction_account(0)?;
+    let authority_address = instruction_context.get_key_of_instruction_account(1)?;
+    let source_program = instruction_context.try_borrow_instruction_account(2)?;
+    let state = check_program_account(
+        &log_collector,
+        &instruction_context,
+        &program,
+        authority_address,
+    )?;
+    if !matches!(state.status, LoaderV4Status::Retracted) {
+        ic_logger_msg!(log_collector, "Program is not retracted");
+        return Err(InstructionError::InvalidArgument);
+    }
+    let source_owner = source_program.get_owner();
+    let source_offset =
+        (source_offset as usize).saturating_add(if loader_v4::check_id(source_owner) {
+            LoaderV4State::program_data_offset()
+        } else if bpf_loader_upgradeable::check_id(source_owner) {
+            UpgradeableLoaderState::size_of_programdata_metadata()
+        } else if bpf_loader_deprecated::check_id(source_owner)
+            || bpf_loader::check_id(source_owner)
+        {
+            0
+        } else {
+            ic_logger_msg!(log_collector, "Source is not a program");
+            return Err(InstructionError::InvalidArgument);
+        });
+    let data = source_program
+        .get_data()
+        .get(source_offset..source_offset.saturating_add(length as usize))
+        .ok_or_else(|| {
+            ic_logger_msg!(log_collector, "Read out of bounds");
+            InstructionError::AccountDataTooSmall
+        })?;
+    let destination_offset =
+        (destination_offset as usize).saturating_add(LoaderV4State::program_data_offset());
+    program
+        .get_data_mut()?
+        .get_mut(destination_offset..destination_offset.saturating_add(length as usize))
+        .ok_or_else(|| {
+            ic_logger_msg!(log_collector, "Write out of bounds");
+            InstructionError::AccountDataTooSmall
+        })?
+        .copy_from_slice(data);
+    Ok(())
+}
+
+fn process_instruction_set_program_length(
+    invoke_context: &mut InvokeContext,
+    new_size: u32,
+) -> Result<(), InstructionError> {
+    let log_collector = invoke_context.get_log_collector();
+    let transaction_context = &invoke_context.transaction_context;
+    let instruction_context = transaction_context.get_current_instruction_context()?;
+    let mut program = instruction_context.try_borrow_instruction_account(0)?;
+    let authority_address = instruction_context.get_key_of_instruction_account(1)?;
+    let is_initialization = program.get_data().len() < LoaderV4State::program_data_offset();
+    if is_initialization {
+        if !loader_v4::check_id(program.get_owner()) {
+            ic_logger_msg!(log_collector, "Program not owned by loader");
+            return Err(InstructionError::InvalidAccountOwner);
+        }
+        if !program.is_writable() {
+            ic_logger_msg!(log_collector, "Program is not writeable");
+            return Err(InstructionError::InvalidArgument);
+        }
+        if !instruction_context.is_instruction_account_signer(1)? {
+            ic_logger_msg!(log_collector, "Authority did not sign");
+            return Err(InstructionError::MissingRequiredSignature);
+        }
+    } else {
+        let state = check_program_account(
+            &log_collector,
+            &instruction_context,
+            &program,
+            authority_address,
+        )?;
+        if !matches!(state.status, LoaderV4Status::Retracted) {
+            ic_logger_msg!(log_collector, "Program is not retracted");
+            return Err(InstructionError::InvalidArgument);
+        }
+    }
+    let required_lamports = if new_size == 0 {
+        0
+    } else {
+        let rent = invoke_context
+            .environment_config
+            .sysvar_cache()
+            .get_rent()?;
+        rent.minimum_balance(LoaderV4State::program_data_offset().saturating_add(new_size as usize))
+            .max(1)
+    };
+    match program.get_lamports().cmp(&required_lamports) {
+        std::cmp::Ordering::Less => {
+            ic_logger_msg!(
+                log_collector,
+                "Insufficient lamports, {} are required",
+                required_lamports
+            );
+            return Err(InstructionError::InsufficientFunds);
+        }
+        std::cmp::Ordering::Greater => {
+            let recipient = instruction_context.try_borrow_instruction_account(2).ok();
+            if let Some(mut recipient) = recipient {
+                if !instruction_context.is_instruction_account_writable(2)? {
+                    ic_logger_msg!(log_collector, "Recipient is not writeable");
+                    return Err(InstructionError::InvalidArgument);
+                }
+                let lamports_to_receive = program.get_lamports().saturating_sub(required_lamports);
+                program.checked_sub_lamports(lamports_to_receive)?;
+                recipient.checked_add_lamports(lamports_to_receive)?;
+            } else if new_size == 0 {
+                ic_logger_msg!(
+                    log_collector,
+                    "Closing a program requires a recipient account"
+                );
+                return Err(InstructionError::InvalidArgument);
+            }
+        }
+        std::cmp::Ordering::Equal => {}
+    }
+    if new_size == 0 {
+        program.set_data_length(0)?;
+    } else {
+        program.set_data_length(
+            LoaderV4State::program_data_offset().saturating_add(new_size as usize),
+        )?;
+        if is_initialization {
+            program.set_executable(true)?;
+            set_state(
+                program.get_data_mut()?,
+                &LoaderV4State {
+                    slot: 0,
+                    authority_address_or_next_version: *authority_address,
+                    status: LoaderV4Status::Retracted,
+                },
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn process_instruction_deploy(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+    let log_collector = invoke_context.get_log_collector();
+    let transaction_context = &invoke_context.transaction_context;
+    let instruction_context = transaction_context.get_current_instruction_context()?;
+    let mut program = instruction_context.try_borrow_instruction_account(0)?;
+    let authority_address = instruction_context.get_key_of_instruction_account(1)?;
+    let state = check_program_account(
+        &log_collector,
+        &instruction_context,
+        &program,
+        authority_address,
+    )?;
+    let current_slot = invoke_context
+        .environment_config
+        .sysvar_cache()
+        .get_clock()?
+        .slot;
+
+    // Slot = 0 indicates that the program hasn't been deployed yet, so no
+    // need to check for the cooldown slots.
+    if state.slot != 0 && state.slot.saturating_add(DEPLOYMENT_COOLDOWN_IN_SLOTS) > current_slot {
+        ic_logger_msg!(
+            log_collector,
+            "Program was deployed recently, cooldown still in effect"
+        );
+        return Err(InstructionError::InvalidArgument);
+    }
+    if !matches!(state.status, LoaderV4Status::Retracted) {
+        ic_logger_msg!(log_collector, "Destination program is not retracted");
+        return Err(InstructionError::InvalidArgument);
+    }
+
+    let programdata = program
+        .get_data()
+        .get(LoaderV4State::program_data_offset()..)
+        .ok_or(InstructionError::AccountDataTooSmall)?;
+    // The clock can be set independently of the program cache, such as by
+    // writing to the sysvars directly instead of warping.
+    if current_slot != invoke_context.program_cache_for_tx_batch.slot() {
+        ic_logger_msg!(
+            log_collector,
+            "Clock slot does not match the program cache's slot"
+        );
+        return Err(InstructionError::InvalidArgument);
+    }
+    // The `deploy_program!` macro expects the caller to share the program
+    // runtime's `metrics` feature, so call into the deployment directly.
+    deploy_program(
+        invoke_context.get_log_collector(),
+        &mut LoadProgramMetrics::default(),
+        invoke_context.program_cache_for_tx_batch,
+        invoke_context
+            .get_program_runtime_environment_for_deployment()
+            .clone(),
+        invoke_context
+            .get_feature_set()
+            .disable_sbpf_v0_v1_v2_deployment,
+        program.get_key(),
+        &loader_v4::id(),
+        program.get_data().len(),
+        programdata,
+        current_slot,
+    )?;
+
+    set_state(
+        program.get_data_mut()?,
+        &LoaderV4State {
+            slot: current_slot,
+            status: LoaderV4Status::Deployed,
+            ..state
+        },
+    )
+}
+
+fn process_instruction_retract(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+    let log_collector = invoke_context.get_log_collector();
+    let transaction_context = &invoke_context.transaction_context;
+    let instruction_context = transaction_context.get_current_instruction_context()?;
+    let mut program = instruction_context.try_borrow_instruction_account(0)?;
+    let authority_address = instruction_context.get_key_of_instruction_account(1)?;
+    let state = check_program_account(
+        &log_collector,
+        &instruction_context,
+        &program,
+        authority_address,
+    )?;
+    let current_slot = invoke_context
+        .environment_config
+        .sysvar_cache()
+        .get_clock()?
+        .slot;
+    if state.slot.saturating_add(DEPLOYMENT_COOLDOWN_IN_SLOTS) > current_slot {
+        ic_logger_msg!(
+            log_collector,
+            "Program was deployed recently, cooldown still in effect"
+        );
+        return Err(InstructionError::InvalidArgument);
+    }
+    if !matches!(state.status, LoaderV4Status::Deployed) {
+        ic_logger_msg!(log_collector, "Program is not deployed");
+        return Err(InstructionError::InvalidArgument);
+    }
+    set_state(
+        program.get_data_mut()?,
+        &LoaderV4State {
+            status: LoaderV4Status::Retracted,
+            ..state
+        },
+    )?;
+    invoke_context
+        .program_cache_for_tx_batch
+        .store_modified_entry(
+            *program.get_key(),
+            Arc::new(ProgramCacheEntry::new_tombstone(
+                current_slot,
+                ProgramCacheEntryOwner::LoaderV4,
+                ProgramCacheEntryType::Closed,
+            )),
+        );
+    Ok(())
+}
+
+fn process_instruction_transfer_authority(
+    invoke_context: &mut InvokeContext,
+) -> Result<(), InstructionError> {
+    let log_collector = invoke_context.get_log_collector();
+    let transaction_context = &invoke_context.transaction_context;
+    let instruction_context = transaction_context.get_current_instruction_context()?;
+    let mut program = instruction_context.try_borrow_instruction_account(0)?;
+    let authority_address = instruction_context.get_key_of_instruction_account(1)?;
+    let new_authority_address = instruction_context.get_key_of_instruction_account(2)?;
+    let state = check_program_account(
+        &log_collector,
+        &instruction_context,
+        &program,
+        authority_address,
+    )?;
+    if !instruction_context.is_instruction_account_signer(2)? {
+        ic_logger_msg!(log_collector, "New authority did not sign");
+        return Err(InstructionError::MissingRequiredSignature);
+    }
+    if state.authority_address_or_next_version == *new_authority_address {
+        ic_logger_msg!(log_collector, "No change");
+        return Err(InstructionError::InvalidArgument);
+    }
+    set_state(
+        program.get_data_mut()?,
+        &LoaderV4State {
+            authority_address_or_next_version: *new_authority_address,
+            ..state
+        },
+    )
+}
+
+fn process_instruction_finalize(
+    invoke_context: &mut InvokeContext,
+) -> Result<(), InstructionError> {
+    let log_collector = invoke_context.get_log_collector();
+    let transaction_context = &invoke_context.transaction_context;
+    let instruction_context = transaction_context.get_current_instruction_context()?;
+    let program = instruction_context.try_borrow_instruction_account(0)?;
+    let authority_address = instruction_context.get_key_of_instruction_account(1)?;
+    let state = check_program_account(
+        &log_collector,
+        &instruction_context,
+        &program,
+        authority_address,
+    )?;
+    if !matches!(state.status, LoaderV4Status::Deployed) {
+        ic_logger_msg!(log_collector, "Program must be deployed to be finalized");
+        return Err(InstructionError::InvalidArgument);
+    }
+    drop(program);
+    let next_version = instruction_context.try_borrow_instruction_account(2)?;
+    if !loader_v4::check_id(next_version.get_owner()) {
+        ic_logger_msg!(log_collector, "Next version is not owned by loader");
+        return Err(InstructionError::InvalidAccountOwner);
+    }
+    let state_of_next_version = get_state(next_version.get_data())?;
+    if state_of_next_version.authority_address_or_next_version != *authority_address {
+        ic_logger_msg!(log_collector, "Next version has a different authority");
+        return Err(InstructionError::IncorrectAuthority);
+    }
+    if matches!(state_of_next_version.status, LoaderV4Status::Finalized) {
+        ic_logger_msg!(log_collector, "Next version is finalized");
+        return Err(InstructionError::Immutable);
+    }
+    let address_of_next_version = *next_version.get_key();
+    drop(next_version);
+    let mut program = instruction_context.try_borrow_instruction_account(0)?;
+    set_state(
+        program.get_data_mut()?,
+        &LoaderV4State {
+            authority_address_or_next_version: address_of_next_version,
+            status: LoaderV4Status::Finalized,
+            ..state
+        },
+    )
+}
+
+declare_builtin_function!(
+    Entrypoint,
+    fn rust(
+        invoke_context: &mut InvokeContext<'static, 'static>,
+        _arg0: u64,
+        _arg1: u64,
+        _arg2: u64,
+        _arg3: u64,
+        _arg4: u64,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        process_instruction_inner(invoke_context)
+    }
+);
+
+fn process_instruction_inner<'a>(
+    invoke_context: &mut InvokeContext<'a, 'a>,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let log_collector = invoke_context.get_log_collector();
+    let transaction_context = &invoke_context.transaction_context;
+    let instruction_context = transaction_context.get_current_instruction_context()?;
+    let program_id = instruction_context.get_program_key()?;
+    if loader_v4::check_id(program_id) {
+        invoke_context
+            .compute_meter
+            .consume_checked(DEFAULT_COMPUTE_UNITS)?;
+        let instruction = bincode::options()
+            .with_limit(PACKET_DATA_SIZE)
+            .with_fixint_encoding()
+            .allow_trailing_bytes()
+            .deserialize(instruction_context.get_instruction_data())
+            .map_err(|_| InstructionError::InvalidInstructionData)?;
+        match instruction {
+            LoaderV4Instruction::Write { offset, bytes } => {
+                process_instruction_write(invoke_context, offset, bytes)
+            }
+            LoaderV4Instruction::Copy {
+                destination_offset,
+                source_offset,
+                length,
+            } => {
+                process_instruction_copy(invoke_context, destination_offset, source_offset, length)
+            }
+            LoaderV4Instruction::SetProgramLength { new_size } => {
+                process_instruction_set_program_length(invoke_context, new_size)
+            }
+            LoaderV4Instruction::Deploy => process_instruction_deploy(invoke_context),
+            LoaderV4Instruction::Retract => process_instruction_retract(invoke_context),
+            LoaderV4Instruction::TransferAuthority => {
+                process_instruction_transfer_authority(invoke_context)
+            }
+            LoaderV4Instruction::Finalize => process_instruction_finalize(invoke_context),
+        }
+        .map_err(|err| Box::new(err) as Box<dyn std::error::Error>)
+    } else {
+        let executor = invoke_context
+            .program_cache_for_tx_batch
+            .find(program_id)
+            .ok_or_else(|| {
+                ic_logger_msg!(log_collector, "Program is not cached");
+                InstructionError::UnsupportedProgramId
+            })?;
+        match &executor.program {
+            ProgramCacheEntryType::FailedVerification(_)
+            | ProgramCacheEntryType::Closed
+            | ProgramCacheEntryType::DelayVisibility => {
+                ic_logger_msg!(log_collector, "Program is not deployed");
+                Err(Box::new(InstructionError::UnsupportedProgramId) as Box<dyn std::error::Error>)
+            }
+            ProgramCacheEntryType::Loaded(executable) => {
+                execute(executable, invoke_context, &executor)
+            }
+            _ => {
+                Err(Box::new(InstructionError::UnsupportedProgramId) as Box<dyn std::error::Error>)
+            }
+        }
+    }
+    .map(|_| 0)
+}
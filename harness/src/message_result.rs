@@ -1,22 +1,34 @@
 //! The intermediate result of executing a transaction message.
 
+#[cfg(feature = "invocation-tree")]
+use mollusk_svm_result::types::Invocation;
+#[cfg(feature = "syscall-trace")]
+use mollusk_svm_result::types::SyscallTraceEntry;
+#[cfg(feature = "inner-instructions")]
+use {
+    mollusk_svm_result::types::ResolvedInnerInstruction, solana_message::SanitizedMessage,
+    solana_transaction_status_client_types::InnerInstruction,
+};
 use {
     mollusk_svm_result::types::TransactionProgramResult,
     solana_instruction_error::InstructionError, solana_program_error::ProgramError,
     solana_transaction_error::TransactionError,
 };
-#[cfg(feature = "inner-instructions")]
-use {solana_message::SanitizedMessage, solana_transaction_status_client_types::InnerInstruction};
 
 pub(crate) struct MessageResult {
     /// The number of compute units consumed by the transaction.
     pub compute_units_consumed: u64,
+    /// The number of compute units consumed by each processed top-level
+    /// instruction.
+    pub instruction_compute_units: Vec<u64>,
     /// The time taken to execute the transaction, in microseconds.
     pub execution_time: u64,
     /// The raw result of the transaction's execution.
     pub raw_result: Result<(), TransactionError>,
     /// The return data produced by the transaction, if any.
     pub return_data: Vec<u8>,
+    /// The log messages emitted during the transaction execution.
+    pub logs: Vec<String>,
     /// Inner instructions (CPIs) invoked during the transaction execution.
     ///
     /// Each entry represents a cross-program invocation made by the program,
@@ -24,6 +36,10 @@ pub(crate) struct MessageResult {
     /// was called.
     #[cfg(feature = "inner-instructions")]
     pub inner_instructions: Vec<Vec<InnerInstruction>>,
+    /// Inner instructions (CPIs) invoked during the transaction execution,
+    /// resolved to the pubkeys of their program and accounts.
+    #[cfg(feature = "inner-instructions")]
+    pub resolved_inner_instructions: Vec<Vec<ResolvedInnerInstruction>>,
     /// The compiled message used to execute the transaction.
     ///
     /// This can be used to map account indices in inner instructions back to
@@ -33,6 +49,14 @@ pub(crate) struct MessageResult {
     /// fixtures don't contain the compiled message.
     #[cfg(feature = "inner-instructions")]
     pub message: Option<SanitizedMessage>,
+    /// Every syscall invoked during the transaction execution, in the order
+    /// they were invoked.
+    #[cfg(feature = "syscall-trace")]
+    pub syscall_trace: Vec<SyscallTraceEntry>,
+    /// The tree of program invocations made during the transaction
+    /// execution, with one root per processed top-level instruction.
+    #[cfg(feature = "invocation-tree")]
+    pub invocations: Vec<Invocation>,
 }
 
 impl MessageResult {
@@ -56,7 +80,7 @@ impl MessageResult {
                     TransactionProgramResult::UnknownError(index, ix_err.clone())
                 }
             }
-            _ => unreachable!(), // Mollusk only uses `InstructionError` variant.
+            Err(err) => TransactionProgramResult::TransactionError(err.clone()),
         }
     }
 }
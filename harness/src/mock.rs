@@ -0,0 +1,175 @@
+//! Mock programs backed by Rust closures.
+//!
+//! Mock programs stand in for programs whose ELFs aren't available, such as
+//! third-party CPI targets. They're registered as builtins, and dispatch to
+//! the closure registered for the invoked program ID.
+
+use {
+    crate::program::BUILTIN_COMPUTE_UNITS,
+    solana_account::{Account, ReadableAccount},
+    solana_instruction::error::InstructionError,
+    solana_program_error::ProgramError,
+    solana_program_runtime::{
+        invoke_context::InvokeContext, solana_sbpf::declare_builtin_function,
+    },
+    solana_pubkey::Pubkey,
+    solana_svm_log_collector::ic_msg,
+    solana_transaction_context::IndexOfAccount,
+    std::{cell::RefCell, collections::HashMap, rc::Rc},
+};
+
+/// The processor of a mock program.
+pub type MockProcessor = Rc<dyn Fn(MockInvocation) -> Result<(), ProgramError>>;
+
+/// An account provided to a mock program invocation.
+///
+/// Changes to the account are written back once the mock returns, subject to
+/// the runtime's usual account modification rules.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MockAccount {
+    pub key: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+    pub account: Account,
+}
+
+/// A single invocation of a mock program.
+pub struct MockInvocation<'a> {
+    /// The ID of the invoked program.
+    pub program_id: &'a Pubkey,
+    /// The instruction data.
+    pub instruction_data: &'a [u8],
+    /// The instruction accounts, in order.
+    pub accounts: &'a mut [MockAccount],
+    return_data: &'a mut Option<Vec<u8>>,
+    logs: &'a mut Vec<String>,
+}
+
+impl MockInvocation<'_> {
+    /// Set the return data of the invocation.
+    pub fn set_return_data(&mut self, data: &[u8]) {
+        *self.return_data = Some(data.to_vec());
+    }
+
+    /// Log a message to the program logs.
+    pub fn log(&mut self, message: &str) {
+        self.logs.push(message.to_string());
+    }
+}
+
+thread_local! {
+    // The mock processors of the `Mollusk` instance currently executing on
+    // this thread. Builtins are plain function pointers, so the processors
+    // are made available to the entrypoint for the duration of execution.
+    static ACTIVE_MOCKS: RefCell<Rc<RefCell<HashMap<Pubkey, MockProcessor>>>> =
+        RefCell::default();
+}
+
+/// Makes a set of mock processors available to the entrypoint until dropped.
+pub(crate) struct ActiveMocksGuard {
+    previous: Rc<RefCell<HashMap<Pubkey, MockProcessor>>>,
+}
+
+impl ActiveMocksGuard {
+    pub(crate) fn new(mocks: Rc<RefCell<HashMap<Pubkey, MockProcessor>>>) -> Self {
+        let previous = ACTIVE_MOCKS.with(|active| active.replace(mocks));
+        Self { previous }
+    }
+}
+
+impl Drop for ActiveMocksGuard {
+    fn drop(&mut self) {
+        let previous = std::mem::take(&mut self.previous);
+        ACTIVE_MOCKS.with(|active| active.replace(previous));
+    }
+}
+
+declare_builtin_function!(
+    Entrypoint,
+    fn rust(
+        invoke_context: &mut InvokeContext<'static, 'static>,
+        _arg0: u64,
+        _arg1: u64,
+        _arg2: u64,
+        _arg3: u64,
+        _arg4: u64,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        invoke_context
+            .compute_meter
+            .consume_checked(BUILTIN_COMPUTE_UNITS)?;
+        process_instruction(invoke_context)
+            .map(|_| 0)
+            .map_err(|err| Box::new(err) as Box<dyn std::error::Error>)
+    }
+);
+
+fn process_instruction(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+    let transaction_context = &invoke_context.transaction_context;
+    let instruction_context = transaction_context.get_current_instruction_context()?;
+    let program_id = *instruction_context.get_program_key()?;
+
+    let processor = ACTIVE_MOCKS
+        .with(|active| active.borrow().borrow().get(&program_id).cloned())
+        .ok_or(InstructionError::UnsupportedProgramId)?;
+
+    let instruction_data = instruction_context.get_instruction_data().to_vec();
+    let mut accounts = (0..instruction_context.get_number_of_instruction_accounts())
+        .map(|index| {
+            let index_in_transaction =
+                instruction_context.get_index_of_instruction_account_in_transaction(index)?;
+            let account = transaction_context
+                .accounts()
+                .try_borrow(index_in_transaction)?;
+            Ok(MockAccount {
+                key: *instruction_context.get_key_of_instruction_account(index)?,
+                is_signer: instruction_context.is_instruction_account_signer(index)?,
+                is_writable: instruction_context.is_instruction_account_writable(index)?,
+                account: Account {
+                    lamports: account.lamports(),
+                    data: account.data().to_vec(),
+                    owner: *account.owner(),
+                    executable: account.executable(),
+                    rent_epoch: account.rent_epoch(),
+                },
+            })
+        })
+        .collect::<Result<Vec<_>, InstructionError>>()?;
+    let mut return_data = None;
+    let mut logs = Vec::new();
+
+    let result = processor(MockInvocation {
+        program_id: &program_id,
+        instruction_data: &instruction_data,
+        accounts: &mut accounts,
+        return_data: &mut return_data,
+        logs: &mut logs,
+    });
+
+    for message in logs {
+        ic_msg!(invoke_context, "{}", message);
+    }
+    result.map_err(|err| InstructionError::from(u64::from(err)))?;
+
+    let transaction_context = &invoke_context.transaction_context;
+    let instruction_context = transaction_context.get_current_instruction_context()?;
+    for (index, mock_account) in accounts.iter().enumerate() {
+        let mut account =
+            instruction_context.try_borrow_instruction_account(index as IndexOfAccount)?;
+        if account.get_lamports() != mock_account.account.lamports {
+            account.set_lamports(mock_account.account.lamports)?;
+        }
+        if account.get_data() != mock_account.account.data.as_slice() {
+            account.set_data_from_slice(&mock_account.account.data)?;
+        }
+        if account.get_owner() != &mock_account.account.owner {
+            account.set_owner(mock_account.account.owner.as_ref())?;
+        }
+    }
+    if let Some(return_data) = return_data {
+        invoke_context
+            .transaction_context
+            .set_return_data(program_id, return_data)?;
+    }
+
+    Ok(())
+}
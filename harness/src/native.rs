@@ -0,0 +1,428 @@
+//! Native (host-compiled) program execution.
+//!
+//! Native programs run a program's Rust `process_instruction` directly on the
+//! host, inside the same `InvokeContext` as every other program. This makes it
+//! possible to set breakpoints, use `dbg!`, or run sanitizers on program logic.
+//!
+//! Accounts are serialized into the same input buffer an SBF program would
+//! receive, so `AccountInfo` behaves as it does on-chain, including `resize`.
+//! Changes are deserialized back into the transaction context once the
+//! program returns, subject to the runtime's usual account modification rules.
+//!
+//! Off-chain, the Solana SDK's syscall wrappers (`invoke_signed`, `msg!`,
+//! `set_return_data`, etc.) are no-ops. Programs reach the runtime through
+//! the syscall stubs in `solana_sysvar::program_stubs` instead, which Mollusk
+//! installs on first use. The stubs support logging, CPI, return data,
+//! compute unit queries, epoch stake, and stack height.
+//!
+//! Compute units are approximated: each invocation consumes a fixed base cost,
+//! and stub calls consume what their syscalls would. The program's own
+//! instructions aren't metered.
+
+use {
+    crate::program::BUILTIN_COMPUTE_UNITS,
+    solana_account::ReadableAccount,
+    solana_account_info::AccountInfo,
+    solana_instruction::{error::InstructionError, Instruction},
+    solana_program_entrypoint::{deserialize, ProcessInstruction},
+    solana_program_error::{ProgramError, ProgramResult},
+    solana_program_runtime::{
+        invoke_context::InvokeContext,
+        serialization::{deserialize_parameters, serialize_parameters},
+        solana_sbpf::{declare_builtin_function, vm::ContextObject},
+        stable_log,
+    },
+    solana_pubkey::Pubkey,
+    solana_svm_log_collector::ic_msg,
+    solana_sysvar::program_stubs::{self, SyscallStubs},
+    std::{cell::RefCell, collections::HashMap, rc::Rc, sync::Once},
+};
+
+thread_local! {
+    // The native programs of the `Mollusk` instance currently executing on
+    // this thread. Builtins are plain function pointers, so the entrypoints
+    // are made available to the builtin for the duration of execution.
+    static ACTIVE_NATIVE_PROGRAMS: RefCell<Rc<RefCell<HashMap<Pubkey, ProcessInstruction>>>> =
+        RefCell::default();
+
+    // The stack of native program invocations currently executing on this
+    // thread, innermost last. Used by the syscall stubs to reach the runtime.
+    static INVOCATIONS: RefCell<Vec<Invocation>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Makes a set of native programs available to the builtin until dropped.
+pub(crate) struct ActiveNativeProgramsGuard {
+    previous: Rc<RefCell<HashMap<Pubkey, ProcessInstruction>>>,
+}
+
+impl ActiveNativeProgramsGuard {
+    pub(crate) fn new(native_programs: Rc<RefCell<HashMap<Pubkey, ProcessInstruction>>>) -> Self {
+        let previous = ACTIVE_NATIVE_PROGRAMS.with(|active| active.replace(native_programs));
+        Self { previous }
+    }
+}
+
+impl Drop for ActiveNativeProgramsGuard {
+    fn drop(&mut self) {
+        let previous = std::mem::take(&mut self.previous);
+        ACTIVE_NATIVE_PROGRAMS.with(|active| active.replace(previous));
+    }
+}
+
+struct Invocation {
+    invoke_context: *mut InvokeContext<'static, 'static>,
+    // The first runtime error raised by a stub. On-chain, these abort the
+    // program, so it takes precedence over whatever the program returns.
+    error: Option<InstructionError>,
+}
+
+// Pops the invocation when dropped, even if the program panics.
+struct InvocationGuard;
+
+impl InvocationGuard {
+    fn push(invoke_context: &mut InvokeContext<'static, 'static>) -> Self {
+        INVOCATIONS.with(|invocations| {
+            invocations.borrow_mut().push(Invocation {
+                invoke_context: invoke_context as *mut _,
+                error: None,
+            })
+        });
+        Self
+    }
+
+    fn take_error(&self) -> Option<InstructionError> {
+        INVOCATIONS.with(|invocations| {
+            invocations
+                .borrow_mut()
+                .last_mut()
+                .and_then(|invocation| invocation.error.take())
+        })
+    }
+}
+
+impl Drop for InvocationGuard {
+    fn drop(&mut self) {
+        INVOCATIONS.with(|invocations| invocations.borrow_mut().pop());
+    }
+}
+
+// Run `f` against the innermost native invocation on this thread, if any.
+fn with_invoke_context<R>(
+    f: impl FnOnce(&mut InvokeContext<'static, 'static>) -> Result<R, InstructionError>,
+) -> Option<Result<R, InstructionError>> {
+    let invoke_context = INVOCATIONS.with(|invocations| {
+        invocations
+            .borrow()
+            .last()
+            .map(|invocation| invocation.invoke_context)
+    })?;
+    // SAFETY: The pointer is pushed by the builtin entrypoint, which holds the
+    // unique reference to the invoke context while the native program runs,
+    // and is popped before the entrypoint returns.
+    let result = f(unsafe { &mut *invoke_context });
+    if let Err(err) = &result {
+        INVOCATIONS.with(|invocations| {
+            if let Some(invocation) = invocations.borrow_mut().last_mut() {
+                invocation.error.get_or_insert_with(|| err.clone());
+            }
+        });
+    }
+    Some(result)
+}
+
+fn consume(invoke_context: &InvokeContext, amount: u64) -> Result<(), InstructionError> {
+    invoke_context
+        .compute_meter
+        .consume_checked(amount)
+        .map_err(|_| InstructionError::ComputationalBudgetExceeded)
+}
+
+// The program sees a `ProgramError`, but the recorded `InstructionError` is
+// what the invocation ultimately fails with.
+fn to_program_error(err: InstructionError) -> ProgramError {
+    ProgramError::try_from(err).unwrap_or(ProgramError::InvalidArgument)
+}
+
+struct MolluskSyscallStubs;
+
+impl SyscallStubs for MolluskSyscallStubs {
+    fn sol_log(&self, message: &str) {
+        let logged = with_invoke_context(|invoke_context| {
+            let cost = invoke_context.get_execution_cost().syscall_base_cost;
+            consume(invoke_context, cost.max(message.len() as u64))?;
+            stable_log::program_log(&invoke_context.get_log_collector(), message);
+            Ok(())
+        });
+        if logged.is_none() {
+            println!("{message}");
+        }
+    }
+
+    fn sol_log_compute_units(&self) {
+        with_invoke_context(|invoke_context| {
+            consume(
+                invoke_context,
+                invoke_context.get_execution_cost().syscall_base_cost,
+            )?;
+            ic_msg!(
+                invoke_context,
+                "Program consumption: {} units remaining",
+                invoke_context.get_remaining(),
+            );
+            Ok(())
+        });
+    }
+
+    fn sol_remaining_compute_units(&self) -> u64 {
+        with_invoke_context(|invoke_context| {
+            consume(
+                invoke_context,
+                invoke_context.get_execution_cost().syscall_base_cost,
+            )?;
+            Ok(invoke_context.get_remaining())
+        })
+        .and_then(Result::ok)
+        .unwrap_or_default()
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        with_invoke_context(|invoke_context| {
+            consume(
+                invoke_context,
+                invoke_context.get_execution_cost().invoke_units,
+            )?;
+            sync_caller_accounts(invoke_context, account_infos)?;
+            invoke_context.native_invoke_signed(instruction.clone(), signers_seeds)?;
+            sync_account_infos(invoke_context, account_infos)
+        })
+        .unwrap_or(Err(InstructionError::UnsupportedProgramId))
+        .map_err(to_program_error)
+    }
+
+    fn sol_get_epoch_stake(&self, vote_address: *const u8) -> u64 {
+        with_invoke_context(|invoke_context| {
+            consume(
+                invoke_context,
+                invoke_context.get_execution_cost().syscall_base_cost,
+            )?;
+            if vote_address.is_null() {
+                Ok(invoke_context.get_epoch_stake())
+            } else {
+                // SAFETY: The caller provides a pointer to a vote address.
+                let vote_address = unsafe { &*(vote_address as *const Pubkey) };
+                Ok(invoke_context.get_epoch_stake_for_vote_account(vote_address))
+            }
+        })
+        .and_then(Result::ok)
+        .unwrap_or_default()
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        with_invoke_context(|invoke_context| {
+            consume(
+                invoke_context,
+                invoke_context.get_execution_cost().syscall_base_cost,
+            )?;
+            let (program_id, data) = invoke_context.transaction_context.get_return_data();
+            Ok((!data.is_empty()).then(|| (*program_id, data.to_vec())))
+        })
+        .and_then(Result::ok)
+        .flatten()
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        with_invoke_context(|invoke_context| {
+            let execution_cost = invoke_context.get_execution_cost();
+            let cost = (data.len() as u64)
+                .checked_div(execution_cost.cpi_bytes_per_unit)
+                .unwrap_or(u64::MAX)
+                .saturating_add(execution_cost.syscall_base_cost);
+            consume(invoke_context, cost)?;
+            let program_id = *invoke_context
+                .transaction_context
+                .get_current_instruction_context()?
+                .get_program_key()?;
+            invoke_context
+                .transaction_context
+                .set_return_data(program_id, data.to_vec())
+        });
+    }
+
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        let logged = with_invoke_context(|invoke_context| {
+            let cost = fields.iter().fold(
+                invoke_context.get_execution_cost().syscall_base_cost,
+                |cost, field| cost.saturating_add(field.len() as u64),
+            );
+            consume(invoke_context, cost)?;
+            stable_log::program_data(&invoke_context.get_log_collector(), fields);
+            Ok(())
+        });
+        if logged.is_none() {
+            println!("data: {fields:?}");
+        }
+    }
+
+    fn sol_get_stack_height(&self) -> u64 {
+        with_invoke_context(|invoke_context| {
+            consume(
+                invoke_context,
+                invoke_context.get_execution_cost().syscall_base_cost,
+            )?;
+            Ok(invoke_context.get_stack_height() as u64)
+        })
+        .and_then(Result::ok)
+        .unwrap_or_default()
+    }
+}
+
+/// Install Mollusk's syscall stubs, replacing any previously installed.
+///
+/// Stubs are process-wide, but dispatch to the native program invocation
+/// executing on the calling thread. Outside of one, they fall back to the
+/// default behavior.
+pub(crate) fn install_syscall_stubs() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        program_stubs::set_syscall_stubs(Box::new(MolluskSyscallStubs));
+    });
+}
+
+// Before a CPI, write the caller's changes to its `AccountInfo`s back to the
+// transaction context, so the callee observes them.
+fn sync_caller_accounts(
+    invoke_context: &InvokeContext,
+    account_infos: &[AccountInfo],
+) -> Result<(), InstructionError> {
+    let transaction_context = &invoke_context.transaction_context;
+    let instruction_context = transaction_context.get_current_instruction_context()?;
+    for account_info in account_infos {
+        let Some(index) = transaction_context
+            .find_index_of_account(account_info.key)
+            .and_then(|index| {
+                instruction_context
+                    .get_index_of_account_in_instruction(index)
+                    .ok()
+            })
+        else {
+            continue;
+        };
+        let mut account = instruction_context.try_borrow_instruction_account(index)?;
+        let lamports = account_info.lamports();
+        if account.get_lamports() != lamports {
+            account.set_lamports(lamports)?;
+        }
+        let data = account_info
+            .try_borrow_data()
+            .map_err(|_| InstructionError::AccountBorrowFailed)?;
+        if account.get_data() != *data {
+            account.set_data_from_slice(&data)?;
+        }
+        if account.get_owner() != account_info.owner {
+            account.set_owner(account_info.owner.as_ref())?;
+        }
+    }
+    Ok(())
+}
+
+// After a CPI, write the callee's changes back to the caller's
+// `AccountInfo`s.
+fn sync_account_infos(
+    invoke_context: &InvokeContext,
+    account_infos: &[AccountInfo],
+) -> Result<(), InstructionError> {
+    let transaction_context = &invoke_context.transaction_context;
+    for account_info in account_infos {
+        let Some(index) = transaction_context.find_index_of_account(account_info.key) else {
+            continue;
+        };
+        let account = transaction_context.accounts().try_borrow(index)?;
+        **account_info
+            .try_borrow_mut_lamports()
+            .map_err(|_| InstructionError::AccountBorrowFailed)? = account.lamports();
+        if account_info.owner != account.owner() {
+            account_info.assign(account.owner());
+        }
+        if account_info.data_len() != account.data().len() {
+            account_info
+                .resize(account.data().len())
+                .map_err(|_| InstructionError::InvalidRealloc)?;
+        }
+        account_info
+            .try_borrow_mut_data()
+            .map_err(|_| InstructionError::AccountBorrowFailed)?
+            .copy_from_slice(account.data());
+    }
+    Ok(())
+}
+
+declare_builtin_function!(
+    Entrypoint,
+    fn rust(
+        invoke_context: &mut InvokeContext<'static, 'static>,
+        _arg0: u64,
+        _arg1: u64,
+        _arg2: u64,
+        _arg3: u64,
+        _arg4: u64,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        invoke_context
+            .compute_meter
+            .consume_checked(BUILTIN_COMPUTE_UNITS)?;
+        process_instruction(invoke_context)
+            .map(|_| 0)
+            .map_err(|err| Box::new(err) as Box<dyn std::error::Error>)
+    }
+);
+
+fn process_instruction(
+    invoke_context: &mut InvokeContext<'static, 'static>,
+) -> Result<(), InstructionError> {
+    let instruction_context = invoke_context
+        .transaction_context
+        .get_current_instruction_context()?;
+    let program_id = *instruction_context.get_program_key()?;
+
+    let entrypoint = ACTIVE_NATIVE_PROGRAMS
+        .with(|active| active.borrow().borrow().get(&program_id).copied())
+        .ok_or(InstructionError::UnsupportedProgramId)?;
+
+    let (mut parameter_bytes, _regions, accounts_metadata, _instruction_data_offset) =
+        serialize_parameters(
+            &instruction_context,
+            /* virtual_address_space_adjustments */ false,
+            /* account_data_direct_mapping */ false,
+            /* direct_account_pointers_in_program_input */ false,
+        )?;
+
+    let (result, stub_error) = {
+        let invocation = InvocationGuard::push(invoke_context);
+        // SAFETY: The buffer was serialized by the runtime in the format the
+        // program entrypoint expects, and outlives the deserialized accounts.
+        let (program_id, account_infos, instruction_data) =
+            unsafe { deserialize(parameter_bytes.as_slice_mut().as_mut_ptr()) };
+        let result = entrypoint(program_id, &account_infos, instruction_data);
+        (result, invocation.take_error())
+    };
+
+    if let Some(err) = stub_error {
+        return Err(err);
+    }
+    result.map_err(|err| InstructionError::from(u64::from(err)))?;
+
+    let instruction_context = invoke_context
+        .transaction_context
+        .get_current_instruction_context()?;
+    deserialize_parameters(
+        &instruction_context,
+        /* virtual_address_space_adjustments */ false,
+        /* account_data_direct_mapping */ false,
+        parameter_bytes.as_slice(),
+        &accounts_metadata,
+    )
+}
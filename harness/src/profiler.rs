@@ -0,0 +1,548 @@
+//! Function-level compute unit profiling from register traces.
+//!
+//! The profiler walks the register trace of every program invocation,
+//! following call and return instructions to maintain a call stack, and
+//! attributes each executed instruction to the stack it was executed in.
+//! Functions are named from the program's DWARF debug info when present,
+//! falling back to the ELF symbol table, then to `function_{pc}`.
+//!
+//! Invocations made through CPI are nested under the call site of the
+//! invoking program, so a profile covers a top-level instruction and all of
+//! its inner instructions.
+//!
+//! Compute units are counted as one per executed SBF instruction. The
+//! additional compute units charged by syscalls are not included.
+
+use {
+    crate::{InvocationInspectCallback, Mollusk},
+    addr2line::gimli::{EndianSlice, LittleEndian},
+    object::{Object, ObjectSection, ObjectSymbol, SymbolKind},
+    solana_program_runtime::{
+        invoke_context::{Executable, InvokeContext, RegisterTrace},
+        solana_sbpf::ebpf,
+    },
+    solana_pubkey::Pubkey,
+    solana_transaction_context::{
+        instruction::InstructionContext, instruction_accounts::InstructionAccount,
+    },
+    std::{
+        cell::{Cell, RefCell},
+        collections::{BTreeMap, HashMap},
+        fs::File,
+        io::Write,
+        rc::Rc,
+    },
+};
+
+const DEFAULT_PATH: &str = "target/sbf/profile";
+
+/// Syscalls which invoke another program.
+const INVOKE_SYSCALLS: &[&str] = &["sol_invoke_signed_c", "sol_invoke_signed_rust"];
+
+/// The compute units consumed by a top-level instruction, broken down by call
+/// stack.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Profile {
+    /// The program invoked by the instruction.
+    pub program_id: Pubkey,
+    /// The compute units consumed in each distinct call stack. Stacks are
+    /// listed from the outermost frame, the invoked program, to the innermost.
+    pub stacks: BTreeMap<Vec<String>, u64>,
+}
+
+/// The compute units consumed by a single function.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FunctionComputeUnits {
+    /// The name of the function.
+    pub name: String,
+    /// The compute units consumed by the function itself.
+    pub self_compute_units: u64,
+    /// The compute units consumed by the function and everything it called.
+    pub total_compute_units: u64,
+}
+
+impl Profile {
+    /// The total compute units consumed across all stacks.
+    pub fn total_compute_units(&self) -> u64 {
+        self.stacks.values().sum()
+    }
+
+    /// Aggregate the profile by function, sorted by total compute units in
+    /// descending order.
+    pub fn functions(&self) -> Vec<FunctionComputeUnits> {
+        let mut functions: HashMap<&str, FunctionComputeUnits> = HashMap::new();
+        for (stack, compute_units) in &self.stacks {
+            let Some(innermost) = stack.last() else {
+                continue;
+            };
+            for (depth, name) in stack.iter().enumerate() {
+                // Recursive functions are only counted once per stack.
+                if stack[..depth].contains(name) {
+                    continue;
+                }
+                let function = functions
+                    .entry(name)
+                    .or_insert_with(|| FunctionComputeUnits {
+                        name: name.clone(),
+                        ..Default::default()
+                    });
+                function.total_compute_units += compute_units;
+            }
+            functions
+                .get_mut(innermost.as_str())
+                .unwrap()
+                .self_compute_units += compute_units;
+        }
+        let mut functions = functions.into_values().collect::<Vec<_>>();
+        functions.sort_by(|a, b| {
+            b.total_compute_units
+                .cmp(&a.total_compute_units)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        functions
+    }
+
+    /// Write the profile in the folded stack format, one stack per line.
+    pub fn write_folded<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        for line in self.folded_lines() {
+            writeln!(writer, "{line}")?;
+        }
+        Ok(())
+    }
+
+    /// Write the profile as a flamegraph SVG.
+    pub fn write_flamegraph<W: Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut options = inferno::flamegraph::Options::default();
+        options.title = format!("Compute units: {}", self.program_id);
+        options.count_name = "CU".to_string();
+        let lines = self.folded_lines();
+        inferno::flamegraph::from_lines(&mut options, lines.iter().map(String::as_str), writer)?;
+        Ok(())
+    }
+
+    fn folded_lines(&self) -> Vec<String> {
+        self.stacks
+            .iter()
+            .map(|(stack, compute_units)| {
+                // Semicolons separate frames in the folded format.
+                let frames = stack
+                    .iter()
+                    .map(|frame| frame.replace(';', ":"))
+                    .collect::<Vec<_>>();
+                format!("{} {compute_units}", frames.join(";"))
+            })
+            .collect()
+    }
+}
+
+/// An invocation inspect callback which profiles every instruction, writing
+/// folded stacks and a flamegraph for each.
+///
+/// Requires register tracing to be enabled, for example with
+/// `Mollusk::new_debuggable`.
+pub struct ProfilingCallback {
+    /// The directory to write the `.folded` and `.svg` files of each profile
+    /// to, or `None` to skip writing them.
+    pub output_dir: Option<String>,
+    /// The profiles of every instruction processed so far.
+    pub profiles: Rc<RefCell<Vec<Profile>>>,
+    // The index in the instruction trace of the top-level instruction being
+    // processed.
+    top_level_index: Cell<usize>,
+}
+
+impl Default for ProfilingCallback {
+    fn default() -> Self {
+        Self {
+            // User can override default path with `SBF_PROFILE_DIR` environment variable.
+            output_dir: Some(std::env::var("SBF_PROFILE_DIR").unwrap_or(DEFAULT_PATH.to_string())),
+            profiles: Rc::default(),
+            top_level_index: Cell::new(0),
+        }
+    }
+}
+
+impl ProfilingCallback {
+    /// Create a profiling callback writing to the provided directory, or
+    /// skipping writing if `None`.
+    pub fn new(output_dir: Option<String>) -> Self {
+        Self {
+            output_dir,
+            ..Default::default()
+        }
+    }
+
+    fn write_profile(&self, profile: &Profile) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(output_dir) = &self.output_dir else {
+            return Ok(());
+        };
+        let output_dir = std::env::current_dir()?.join(output_dir);
+        std::fs::create_dir_all(&output_dir)?;
+
+        let base_fname = output_dir.join(format!(
+            "{:04}-{}",
+            self.profiles.borrow().len(),
+            profile.program_id
+        ));
+        profile.write_folded(&mut File::create(base_fname.with_extension("folded"))?)?;
+        profile.write_flamegraph(&mut File::create(base_fname.with_extension("svg"))?)?;
+        Ok(())
+    }
+}
+
+impl InvocationInspectCallback for ProfilingCallback {
+    fn before_invocation(
+        &self,
+        _: &Mollusk,
+        _: &Pubkey,
+        _: &[u8],
+        _: &[InstructionAccount],
+        invoke_context: &mut InvokeContext,
+        _register_tracing_enabled: bool,
+    ) {
+        if let Ok(instruction_context) = invoke_context
+            .transaction_context
+            .get_next_instruction_context()
+        {
+            self.top_level_index
+                .set(instruction_context.get_index_in_trace());
+        }
+    }
+
+    fn after_invocation(
+        &self,
+        mollusk: &Mollusk,
+        invoke_context: &InvokeContext,
+        register_tracing_enabled: bool,
+    ) {
+        if !register_tracing_enabled {
+            return;
+        }
+        let top_level_index = self.top_level_index.get();
+        let transaction_context = &invoke_context.transaction_context;
+
+        // Register traces are kept for the whole transaction, so only the
+        // ones of the current top-level instruction are profiled.
+        let traces = RefCell::new(HashMap::new());
+        invoke_context.iterate_vm_traces(
+            &|instruction_context: InstructionContext,
+              executable: &Executable,
+              register_trace: RegisterTrace| {
+                let mut index = instruction_context.get_index_in_trace();
+                loop {
+                    let Ok(context) =
+                        transaction_context.get_instruction_context_at_index_in_trace(index)
+                    else {
+                        return;
+                    };
+                    if context.get_stack_height() <= 1 {
+                        break;
+                    }
+                    index = context.get_index_of_caller();
+                }
+                if index != top_level_index {
+                    return;
+                }
+                let Ok(program_id) = instruction_context.get_program_key() else {
+                    return;
+                };
+                let elf = mollusk.program_cache.get_program_elf_bytes(program_id);
+                let trace = profile_register_trace(executable, elf.as_deref(), register_trace);
+                traces
+                    .borrow_mut()
+                    .insert(instruction_context.get_index_in_trace(), trace);
+            },
+        );
+        let traces = traces.into_inner();
+        if traces.is_empty() {
+            return;
+        }
+
+        // The inner instructions invoked by each instruction, in order.
+        let mut inner_instructions: HashMap<usize, Vec<(usize, Pubkey)>> = HashMap::new();
+        for index in 0..transaction_context.get_instruction_trace_length() {
+            let Ok(instruction_context) =
+                transaction_context.get_instruction_context_at_index_in_trace(index)
+            else {
+                continue;
+            };
+            if instruction_context.get_stack_height() > 1 {
+                if let Ok(program_id) = instruction_context.get_program_key() {
+                    inner_instructions
+                        .entry(instruction_context.get_index_of_caller())
+                        .or_default()
+                        .push((index, *program_id));
+                }
+            }
+        }
+
+        let Ok(program_id) = transaction_context
+            .get_instruction_context_at_index_in_trace(top_level_index)
+            .and_then(|instruction_context| instruction_context.get_program_key().copied())
+        else {
+            return;
+        };
+        let mut profile = Profile {
+            program_id,
+            stacks: BTreeMap::new(),
+        };
+        nest_trace_profiles(
+            &mut profile.stacks,
+            &traces,
+            &inner_instructions,
+            top_level_index,
+            vec![program_id.to_string()],
+        );
+
+        if let Err(e) = self.write_profile(&profile) {
+            eprintln!("Error writing the profile: {e}");
+        }
+        self.profiles.borrow_mut().push(profile);
+    }
+}
+
+// The profile of a single program invocation.
+struct TraceProfile {
+    stacks: HashMap<Vec<String>, u64>,
+    // The call stack at each invoke syscall, in order.
+    invoke_sites: Vec<Vec<String>>,
+}
+
+// Add the stacks of an invocation to the profile, prefixed with the stack it
+// was invoked from, followed by those of its inner instructions.
+fn nest_trace_profiles(
+    stacks: &mut BTreeMap<Vec<String>, u64>,
+    traces: &HashMap<usize, TraceProfile>,
+    inner_instructions: &HashMap<usize, Vec<(usize, Pubkey)>>,
+    index: usize,
+    prefix: Vec<String>,
+) {
+    let trace = traces.get(&index);
+    if let Some(trace) = trace {
+        for (stack, compute_units) in &trace.stacks {
+            let mut stack_with_prefix = prefix.clone();
+            stack_with_prefix.extend_from_slice(stack);
+            *stacks.entry(stack_with_prefix).or_default() += compute_units;
+        }
+    }
+    let Some(invoked) = inner_instructions.get(&index) else {
+        return;
+    };
+    // Each inner instruction was invoked by the matching invoke syscall.
+    for (position, (inner_index, program_id)) in invoked.iter().enumerate() {
+        let mut inner_prefix = prefix.clone();
+        if let Some(invoke_site) = trace.and_then(|trace| trace.invoke_sites.get(position)) {
+            inner_prefix.extend_from_slice(invoke_site);
+        }
+        inner_prefix.push(program_id.to_string());
+        nest_trace_profiles(
+            stacks,
+            traces,
+            inner_instructions,
+            *inner_index,
+            inner_prefix,
+        );
+    }
+}
+
+// Walk a register trace, attributing each executed instruction to the call
+// stack it was executed in.
+fn profile_register_trace(
+    executable: &Executable,
+    elf: Option<&[u8]>,
+    register_trace: RegisterTrace,
+) -> TraceProfile {
+    let mut symbolizer = Symbolizer::new(executable, elf);
+    let (_, program) = executable.get_text_bytes();
+    let sbpf_version = executable.get_sbpf_version();
+    let syscalls = executable.get_loader().get_function_registry();
+
+    // The program counters of the call sites of each caller, and the number
+    // of instructions executed at each program counter in each call stack.
+    let mut call_sites: Vec<u64> = vec![];
+    let mut counts: HashMap<(Vec<u64>, u64), u64> = HashMap::new();
+    let mut syscall_counts: HashMap<(Vec<u64>, u64, String), u64> = HashMap::new();
+    let mut invoke_sites: Vec<(Vec<u64>, u64, String)> = vec![];
+
+    for (position, registers) in register_trace.iter().enumerate() {
+        // The program counter is stored in r11.
+        let pc = registers[11];
+        let insn = ebpf::get_insn_unchecked(program, pc as usize);
+        let syscall = (insn.opc == ebpf::CALL_IMM
+            && (!sbpf_version.static_syscalls() || insn.src == 0))
+            .then(|| syscalls.lookup_by_key(insn.imm as u32))
+            .flatten()
+            .map(|(name, _)| String::from_utf8_lossy(name).into_owned());
+
+        if let Some(name) = syscall {
+            if INVOKE_SYSCALLS.contains(&name.as_str()) {
+                invoke_sites.push((call_sites.clone(), pc, name.clone()));
+            }
+            *syscall_counts
+                .entry((call_sites.clone(), pc, name))
+                .or_default() += 1;
+            continue;
+        }
+        *counts.entry((call_sites.clone(), pc)).or_default() += 1;
+
+        match insn.opc {
+            ebpf::CALL_IMM | ebpf::CALL_REG => {
+                // Calls which don't move on to the next instruction enter a
+                // new function.
+                let next_pc = register_trace
+                    .get(position + 1)
+                    .map(|registers| registers[11]);
+                if next_pc.is_some_and(|next_pc| next_pc != pc + 1) {
+                    call_sites.push(pc);
+                }
+            }
+            ebpf::EXIT => {
+                call_sites.pop();
+            }
+            _ => (),
+        }
+    }
+
+    let mut stack_of = |call_sites: &[u64], pc: u64| {
+        let mut stack = vec![];
+        for call_site in call_sites {
+            stack.extend_from_slice(&symbolizer.frames(*call_site));
+        }
+        stack.extend_from_slice(&symbolizer.frames(pc));
+        stack
+    };
+
+    let mut stacks = HashMap::new();
+    for ((call_sites, pc), count) in counts {
+        *stacks.entry(stack_of(&call_sites, pc)).or_default() += count;
+    }
+    for ((call_sites, pc, name), count) in syscall_counts {
+        let mut stack = stack_of(&call_sites, pc);
+        stack.push(name);
+        *stacks.entry(stack).or_default() += count;
+    }
+    let invoke_sites = invoke_sites
+        .into_iter()
+        .map(|(call_sites, pc, name)| {
+            let mut stack = stack_of(&call_sites, pc);
+            stack.push(name);
+            stack
+        })
+        .collect();
+
+    TraceProfile {
+        stacks,
+        invoke_sites,
+    }
+}
+
+// Resolves program counters to the names of the functions they're in.
+struct Symbolizer<'a> {
+    // The ELF address of the first instruction.
+    text_address: u64,
+    // DWARF debug info, if present.
+    dwarf: Option<addr2line::Context<EndianSlice<'a, LittleEndian>>>,
+    // Function symbols, keyed by ELF address, with their size.
+    symbols: BTreeMap<u64, (u64, String)>,
+    // Functions known to the executable, keyed by program counter.
+    functions: BTreeMap<u64, String>,
+    cache: HashMap<u64, Rc<[String]>>,
+}
+
+impl<'a> Symbolizer<'a> {
+    fn new(executable: &Executable, elf: Option<&'a [u8]>) -> Self {
+        let mut symbolizer = Self {
+            text_address: 0,
+            dwarf: None,
+            symbols: BTreeMap::new(),
+            functions: executable
+                .get_function_registry()
+                .iter()
+                .map(|(_key, (name, pc))| (pc as u64, String::from_utf8_lossy(name).into_owned()))
+                .collect(),
+            cache: HashMap::new(),
+        };
+
+        let Some(object) = elf.and_then(|elf| object::File::parse(elf).ok()) else {
+            return symbolizer;
+        };
+        let Some(text_section) = object.section_by_name(".text") else {
+            return symbolizer;
+        };
+        symbolizer.text_address = text_section.address();
+        symbolizer.symbols = object
+            .symbols()
+            .chain(object.dynamic_symbols())
+            .filter(|symbol| symbol.kind() == SymbolKind::Text)
+            .filter_map(|symbol| {
+                let name = symbol.name().ok()?;
+                Some((symbol.address(), (symbol.size(), name.to_string())))
+            })
+            .collect();
+        if object.section_by_name(".debug_info").is_some() {
+            let dwarf = addr2line::gimli::Dwarf::load(|id| {
+                let data = object
+                    .section_by_name(id.name())
+                    .and_then(|section| section.data().ok())
+                    .unwrap_or_default();
+                Ok::<_, addr2line::gimli::Error>(EndianSlice::new(data, LittleEndian))
+            });
+            symbolizer.dwarf = dwarf
+                .ok()
+                .and_then(|dwarf| addr2line::Context::from_dwarf(dwarf).ok());
+        }
+        symbolizer
+    }
+
+    // The frames at a program counter, from the outermost to the innermost.
+    // More than one frame is returned for inlined functions.
+    fn frames(&mut self, pc: u64) -> Rc<[String]> {
+        if let Some(frames) = self.cache.get(&pc) {
+            return Rc::clone(frames);
+        }
+        let frames: Rc<[String]> = match self.dwarf_frames(pc) {
+            Some(frames) if !frames.is_empty() => frames.into(),
+            _ => Rc::new([self.symbol_name(pc)]),
+        };
+        self.cache.insert(pc, Rc::clone(&frames));
+        frames
+    }
+
+    fn dwarf_frames(&self, pc: u64) -> Option<Vec<String>> {
+        let dwarf = self.dwarf.as_ref()?;
+        let address = self.text_address + pc * ebpf::INSN_SIZE as u64;
+        let mut frames = dwarf.find_frames(address).skip_all_loads().ok()?;
+        let mut names = vec![];
+        while let Ok(Some(frame)) = frames.next() {
+            if let Some(name) = frame
+                .function
+                .as_ref()
+                .and_then(|name| name.raw_name().ok())
+            {
+                names.push(demangle(&name));
+            }
+        }
+        names.reverse();
+        Some(names)
+    }
+
+    fn symbol_name(&self, pc: u64) -> String {
+        let address = self.text_address + pc * ebpf::INSN_SIZE as u64;
+        if let Some((start, (size, name))) = self.symbols.range(..=address).next_back() {
+            if *size == 0 || address < start + size {
+                return demangle(name);
+            }
+        }
+        match self.functions.range(..=pc).next_back() {
+            Some((_, name)) if !name.is_empty() => demangle(name),
+            _ => format!("function_{pc}"),
+        }
+    }
+}
+
+fn demangle(name: &str) -> String {
+    format!("{:#}", rustc_demangle::demangle(name))
+}
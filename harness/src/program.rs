@@ -1,13 +1,19 @@
 //! Module for working with Solana programs.
 
 use {
+    crate::{
+        mock::MockProcessor,
+        syscall_intercept::{SyscallInterceptError, SyscallInterceptor},
+    },
     solana_account::Account,
     solana_compute_budget::compute_budget::ComputeBudget,
     solana_loader_v3_interface::state::UpgradeableLoaderState,
+    solana_loader_v4_interface::state::{LoaderV4State, LoaderV4Status},
+    solana_program_entrypoint::ProcessInstruction,
     solana_program_runtime::{
         invoke_context::{BuiltinFunctionRegisterer, InvokeContext},
         loaded_programs::{ProgramCacheForTxBatch, ProgramRuntimeEnvironment},
-        program_cache_entry::ProgramCacheEntry,
+        program_cache_entry::{ProgramCacheEntry, ProgramCacheEntryOwner, ProgramCacheEntryType},
         program_metrics::LoadProgramMetrics,
         solana_sbpf::{
             elf::ElfError,
@@ -20,7 +26,7 @@ use {
     solana_syscalls::create_program_runtime_environment,
     std::{
         cell::{RefCell, RefMut},
-        collections::HashMap,
+        collections::{HashMap, HashSet},
         rc::Rc,
         sync::Arc,
     },
@@ -30,10 +36,15 @@ use {
 pub mod loader_keys {
     pub use solana_sdk_ids::{
         bpf_loader::ID as LOADER_V2, bpf_loader_deprecated::ID as LOADER_V1,
-        bpf_loader_upgradeable::ID as LOADER_V3, native_loader::ID as NATIVE_LOADER,
+        bpf_loader_upgradeable::ID as LOADER_V3, loader_v4::ID as LOADER_V4,
+        native_loader::ID as NATIVE_LOADER,
     };
 }
 
+// Builtins must consume compute units, so the builtins Mollusk provides, such
+// as mock and native programs, charge the same as the System program.
+pub(crate) const BUILTIN_COMPUTE_UNITS: u64 = 150;
+
 #[cfg(feature = "precompiles")]
 pub mod precompile_keys {
     use solana_pubkey::Pubkey;
@@ -59,6 +70,7 @@ pub mod precompile_keys {
     }
 }
 
+#[derive(Clone)]
 pub struct CacheEntry {
     pub loader_key: Pubkey,
     pub elf_bytes: Option<Vec<u8>>,
@@ -79,6 +91,38 @@ pub struct ProgramCache {
     // The function registry (syscalls) to use for verifying and loading
     // program ELFs.
     program_runtime_environment: ProgramRuntimeEnvironment,
+    // The processors of mock programs, keyed by program ID.
+    mocks: Rc<RefCell<HashMap<Pubkey, MockProcessor>>>,
+    // The entrypoints of native programs, keyed by program ID.
+    native_programs: Rc<RefCell<HashMap<Pubkey, ProcessInstruction>>>,
+    // The interceptors of intercepted syscalls, keyed by syscall name.
+    syscall_interceptors: Rc<RefCell<HashMap<String, SyscallInterceptor>>>,
+}
+
+/// A point-in-time copy of a `ProgramCache`, which can be restored with
+/// `ProgramCache::restore`.
+#[derive(Clone)]
+pub struct ProgramCacheSnapshot {
+    cache: ProgramCacheForTxBatch,
+    entries_cache: HashMap<Pubkey, CacheEntry>,
+    program_runtime_environment: ProgramRuntimeEnvironment,
+    mocks: HashMap<Pubkey, MockProcessor>,
+    native_programs: HashMap<Pubkey, ProcessInstruction>,
+    syscall_interceptors: HashMap<String, SyscallInterceptor>,
+}
+
+fn clone_environment(
+    environment: &ProgramRuntimeEnvironment,
+) -> BuiltinProgram<InvokeContext<'static, 'static>> {
+    let config = environment.get_config().clone();
+    let mut loader = BuiltinProgram::new_loader(config);
+
+    for (_key, (name, value)) in environment.get_function_registry().iter() {
+        let name = std::str::from_utf8(name).unwrap();
+        loader.register_function(name, value).unwrap();
+    }
+
+    loader
 }
 
 impl ProgramCache {
@@ -97,6 +141,9 @@ impl ProgramCache {
                 /* debugging_features */ enable_register_tracing,
             )
             .unwrap(),
+            mocks: Rc::new(RefCell::new(HashMap::new())),
+            native_programs: Rc::new(RefCell::new(HashMap::new())),
+            syscall_interceptors: Rc::new(RefCell::new(HashMap::new())),
         };
         BUILTINS.iter().for_each(|builtin| {
             let program_id = builtin.program_id;
@@ -106,6 +153,29 @@ impl ProgramCache {
         me
     }
 
+    /// Capture the current cache entries and program runtime environment.
+    pub fn snapshot(&self) -> ProgramCacheSnapshot {
+        ProgramCacheSnapshot {
+            cache: self.cache.borrow().clone(),
+            entries_cache: self.entries_cache.borrow().clone(),
+            program_runtime_environment: self.program_runtime_environment.clone(),
+            mocks: self.mocks.borrow().clone(),
+            native_programs: self.native_programs.borrow().clone(),
+            syscall_interceptors: self.syscall_interceptors.borrow().clone(),
+        }
+    }
+
+    /// Restore the cache to a previously captured snapshot, discarding any
+    /// programs added since.
+    pub fn restore(&mut self, snapshot: &ProgramCacheSnapshot) {
+        *self.cache.borrow_mut() = snapshot.cache.clone();
+        *self.entries_cache.borrow_mut() = snapshot.entries_cache.clone();
+        self.program_runtime_environment = snapshot.program_runtime_environment.clone();
+        *self.mocks.borrow_mut() = snapshot.mocks.clone();
+        *self.native_programs.borrow_mut() = snapshot.native_programs.clone();
+        *self.syscall_interceptors.borrow_mut() = snapshot.syscall_interceptors.clone();
+    }
+
     pub(crate) fn cache(&self) -> RefMut<'_, ProgramCacheForTxBatch> {
         self.cache.borrow_mut()
     }
@@ -133,20 +203,40 @@ impl ProgramCache {
         self.replenish(program_id, entry, None);
     }
 
-    fn clone_program_runtime_environment(&self) -> BuiltinProgram<InvokeContext<'static, 'static>> {
-        let config = self.program_runtime_environment.get_config().clone();
-        let mut loader = BuiltinProgram::new_loader(config);
+    /// Add a mock program to the cache, backed by the provided processor.
+    pub fn add_mock_program(&mut self, program_id: &Pubkey, processor: MockProcessor) {
+        self.mocks.borrow_mut().insert(*program_id, processor);
+        self.add_builtin(Builtin {
+            program_id: *program_id,
+            name: "mock_program",
+            register_fn: crate::mock::Entrypoint::register,
+        });
+    }
 
-        for (_key, (name, value)) in self
-            .program_runtime_environment
-            .get_function_registry()
-            .iter()
-        {
-            let name = std::str::from_utf8(name).unwrap();
-            loader.register_function(name, value).unwrap();
-        }
+    pub(crate) fn mocks(&self) -> Rc<RefCell<HashMap<Pubkey, MockProcessor>>> {
+        Rc::clone(&self.mocks)
+    }
+
+    /// Add a native program to the cache, executed by calling the provided
+    /// entrypoint on the host.
+    pub fn add_native_program(&mut self, program_id: &Pubkey, entrypoint: ProcessInstruction) {
+        crate::native::install_syscall_stubs();
+        self.native_programs
+            .borrow_mut()
+            .insert(*program_id, entrypoint);
+        self.add_builtin(Builtin {
+            program_id: *program_id,
+            name: "native_program",
+            register_fn: crate::native::Entrypoint::register,
+        });
+    }
 
-        loader
+    pub(crate) fn native_programs(&self) -> Rc<RefCell<HashMap<Pubkey, ProcessInstruction>>> {
+        Rc::clone(&self.native_programs)
+    }
+
+    fn clone_program_runtime_environment(&self) -> BuiltinProgram<InvokeContext<'static, 'static>> {
+        clone_environment(&self.program_runtime_environment)
     }
 
     /// Register an additional builtin function (ie. a custom syscall) into
@@ -165,27 +255,240 @@ impl ProgramCache {
         Ok(())
     }
 
+    /// Intercept a syscall, such as `sol_log_`, with the provided
+    /// interceptor, replacing any previous interceptor of the syscall.
+    ///
+    /// Programs already in the cache are reloaded against the updated
+    /// environment.
+    ///
+    /// Returns an error if no syscall with the provided name is registered,
+    /// or if `MAX_WRAPPED_SYSCALLS` distinct syscalls have already been
+    /// intercepted in the process.
+    pub fn intercept_syscall(
+        &mut self,
+        name: &str,
+        interceptor: SyscallInterceptor,
+    ) -> Result<(), SyscallInterceptError> {
+        let (_, (original, _)) = self
+            .program_runtime_environment
+            .get_function_registry()
+            .lookup_by_name(name.as_bytes())
+            .ok_or_else(|| SyscallInterceptError::NotRegistered(name.to_string()))?;
+        // Reserve the wrapper slot up front, so running out of slots is
+        // reported here rather than when programs are loaded.
+        crate::syscall_intercept::slot_for(name, original)?;
+        let previous = self
+            .syscall_interceptors
+            .borrow_mut()
+            .insert(name.to_string(), interceptor);
+        if previous.is_some() {
+            return Ok(());
+        }
+
+        // Executables are bound to the environment they were loaded with.
+        let programs = self
+            .entries_cache
+            .borrow()
+            .iter()
+            .filter_map(|(program_id, entry)| {
+                entry
+                    .elf_bytes
+                    .clone()
+                    .map(|elf| (*program_id, entry.loader_key, elf))
+            })
+            .collect::<Vec<_>>();
+        for (program_id, loader_key, elf) in programs {
+            self.add_program(&program_id, &loader_key, &elf);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn syscall_interceptors(&self) -> Rc<RefCell<HashMap<String, SyscallInterceptor>>> {
+        Rc::clone(&self.syscall_interceptors)
+    }
+
+    /// Wrap the intercepted syscalls of a program runtime environment, such
+    /// as the one programs are deployed with. With the `syscall-trace`
+    /// feature, all syscalls are wrapped, so they can be traced, except once
+    /// the wrapper slots run out.
+    pub(crate) fn wrap_environment(
+        &self,
+        environment: &ProgramRuntimeEnvironment,
+    ) -> ProgramRuntimeEnvironment {
+        #[cfg(feature = "syscall-trace")]
+        let names = environment
+            .get_function_registry()
+            .iter()
+            .map(|(_key, (name, _value))| String::from_utf8_lossy(name).into_owned())
+            .collect::<Vec<_>>();
+        #[cfg(not(feature = "syscall-trace"))]
+        let names = self
+            .syscall_interceptors
+            .borrow()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if names.is_empty() {
+            return environment.clone();
+        }
+        let mut loader = clone_environment(environment);
+        for name in names {
+            crate::syscall_intercept::wrap_syscall(&mut loader, &name);
+        }
+        ProgramRuntimeEnvironment::from(loader)
+    }
+
+    fn new_program_entry(
+        &self,
+        loader_key: &Pubkey,
+        elf: &[u8],
+    ) -> Result<Arc<ProgramCacheEntry>, Box<dyn std::error::Error>> {
+        let environment = self.wrap_environment(&self.program_runtime_environment);
+        ProgramCacheEntry::new(
+            loader_key,
+            environment,
+            0,
+            0,
+            elf,
+            elf.len(),
+            &mut LoadProgramMetrics::default(),
+        )
+        .map(Arc::new)
+    }
+
     /// Add a program to the cache.
     pub fn add_program(&mut self, program_id: &Pubkey, loader_key: &Pubkey, elf: &[u8]) {
-        let environment = ProgramRuntimeEnvironment::from(self.clone_program_runtime_environment());
-        self.replenish(
+        let entry = self.new_program_entry(loader_key, elf).unwrap();
+        self.replenish(*program_id, entry, Some(elf));
+    }
+
+    // Replace a program with a closed tombstone, so it can no longer be
+    // invoked.
+    fn evict(&self, program_id: &Pubkey, owner: ProgramCacheEntryOwner) {
+        self.entries_cache.borrow_mut().remove(program_id);
+        self.cache.borrow_mut().replenish(
             *program_id,
-            Arc::new(
-                ProgramCacheEntry::new(
-                    loader_key,
-                    environment,
-                    0,
-                    0,
-                    elf,
-                    elf.len(),
-                    &mut LoadProgramMetrics::default(),
-                )
-                .unwrap(),
-            ),
-            Some(elf),
+            Arc::new(ProgramCacheEntry::new_tombstone(
+                0,
+                owner,
+                ProgramCacheEntryType::Closed,
+            )),
         );
     }
 
+    /// Refresh the cache entries of BPF Loader v3 (Upgradeable) programs
+    /// whose program or program data accounts were modified, such as by an
+    /// in-harness deploy, upgrade, extend, or close.
+    ///
+    /// Programs are reloaded from their program data accounts, which are
+    /// looked up in `modified_accounts` first, then with `get_account`.
+    /// Programs whose program data accounts were closed are evicted.
+    pub(crate) fn refresh_loader_v3_programs(
+        &self,
+        modified_accounts: &[(Pubkey, Account)],
+        get_account: impl Fn(&Pubkey) -> Option<Account>,
+    ) {
+        let loader_v3_accounts = modified_accounts
+            .iter()
+            .filter(|(_, account)| account.owner == loader_keys::LOADER_V3)
+            .map(|(key, account)| (key, bincode::deserialize(&account.data).ok()))
+            .collect::<Vec<_>>();
+
+        let mut program_ids = loader_v3_accounts
+            .iter()
+            .filter_map(|(key, state)| {
+                matches!(state, Some(UpgradeableLoaderState::Program { .. })).then_some(**key)
+            })
+            .collect::<HashSet<_>>();
+
+        // Program data accounts don't reference their program, so match them
+        // against the known programs' derived program data addresses.
+        let programdata_keys = loader_v3_accounts
+            .iter()
+            .filter(|(_, state)| {
+                !matches!(
+                    state,
+                    Some(UpgradeableLoaderState::Program { .. })
+                        | Some(UpgradeableLoaderState::Buffer { .. })
+                )
+            })
+            .map(|(key, _)| **key)
+            .collect::<HashSet<_>>();
+        if !programdata_keys.is_empty() {
+            let candidates = self
+                .entries_cache
+                .borrow()
+                .iter()
+                .filter(|(_, entry)| entry.loader_key == loader_keys::LOADER_V3)
+                .map(|(program_id, _)| *program_id)
+                .collect::<Vec<_>>();
+            program_ids.extend(candidates.into_iter().filter(|program_id| {
+                programdata_keys.contains(&get_programdata_address(program_id))
+            }));
+        }
+
+        for program_id in program_ids {
+            let programdata_address = get_programdata_address(&program_id);
+            let Some(programdata) = modified_accounts
+                .iter()
+                .find(|(key, _)| key == &programdata_address)
+                .map(|(_, account)| account.clone())
+                .or_else(|| get_account(&programdata_address))
+            else {
+                continue;
+            };
+
+            let is_deployed = programdata.lamports > 0
+                && programdata.owner == loader_keys::LOADER_V3
+                && matches!(
+                    bincode::deserialize(&programdata.data),
+                    Ok(UpgradeableLoaderState::ProgramData { .. })
+                );
+            if !is_deployed {
+                self.evict(&program_id, ProgramCacheEntryOwner::LoaderV3);
+                continue;
+            }
+
+            let elf = &programdata.data[UpgradeableLoaderState::size_of_programdata_metadata()..];
+            match self.new_program_entry(&loader_keys::LOADER_V3, elf) {
+                Ok(entry) => self.replenish(program_id, entry, Some(elf)),
+                Err(_) => self.evict(&program_id, ProgramCacheEntryOwner::LoaderV3),
+            }
+        }
+    }
+
+    /// Refresh the cache entries of Loader v4 programs whose accounts were
+    /// modified, such as by an in-harness deploy, retract, or close.
+    ///
+    /// Deployed and finalized programs are reloaded from their accounts.
+    /// Retracted or closed programs are evicted.
+    pub(crate) fn refresh_loader_v4_programs(&self, modified_accounts: &[(Pubkey, Account)]) {
+        for (program_id, account) in modified_accounts {
+            let is_deployed = account.lamports > 0
+                && account.owner == loader_keys::LOADER_V4
+                && crate::loader_v4::get_state(&account.data)
+                    .is_ok_and(|state| !matches!(state.status, LoaderV4Status::Retracted));
+            if !is_deployed {
+                let is_cached = self
+                    .entries_cache
+                    .borrow()
+                    .get(program_id)
+                    .is_some_and(|entry| entry.loader_key == loader_keys::LOADER_V4);
+                if is_cached {
+                    self.evict(program_id, ProgramCacheEntryOwner::LoaderV4);
+                }
+                continue;
+            }
+
+            let elf = &account.data[LoaderV4State::program_data_offset()..];
+            match self.new_program_entry(&loader_keys::LOADER_V4, elf) {
+                Ok(entry) => self.replenish(*program_id, entry, Some(elf)),
+                Err(_) => self.evict(program_id, ProgramCacheEntryOwner::LoaderV4),
+            }
+        }
+    }
+
     /// Load a program from the cache.
     pub fn load_program(&self, program_id: &Pubkey) -> Option<Arc<ProgramCacheEntry>> {
         self.cache.borrow().find(program_id)
@@ -207,6 +510,12 @@ impl ProgramCache {
                 loader_keys::LOADER_V3 => {
                     (*program_id, create_program_account_loader_v3(program_id))
                 }
+                loader_keys::LOADER_V4 => (
+                    *program_id,
+                    create_program_account_loader_v4(
+                        cache_entry.elf_bytes.as_deref().unwrap_or_default(),
+                    ),
+                ),
                 _ => panic!("Invalid loader key: {}", cache_entry.loader_key),
             })
             .collect()
@@ -225,6 +534,9 @@ impl ProgramCache {
                 loader_keys::LOADER_V1 => create_program_account_loader_v1(&[]),
                 loader_keys::LOADER_V2 => create_program_account_loader_v2(&[]),
                 loader_keys::LOADER_V3 => create_program_account_loader_v3(pubkey),
+                loader_keys::LOADER_V4 => create_program_account_loader_v4(
+                    cache_entry.elf_bytes.as_deref().unwrap_or_default(),
+                ),
                 _ => panic!("Invalid loader key: {}", cache_entry.loader_key),
             })
     }
@@ -274,6 +586,11 @@ static BUILTINS: &[Builtin] = &[
         name: "solana_bpf_loader_upgradeable_program",
         register_fn: solana_bpf_loader_program::Entrypoint::register,
     },
+    Builtin {
+        program_id: loader_keys::LOADER_V4,
+        name: "loader_v4",
+        register_fn: crate::loader_v4::Entrypoint::register,
+    },
     #[cfg(feature = "all-builtins")]
     Builtin {
         program_id: solana_sdk_ids::zk_elgamal_proof_program::id(),
@@ -326,6 +643,11 @@ pub fn keyed_account_for_bpf_loader_v3_program() -> (Pubkey, Account) {
     create_keyed_account_for_builtin_program(&BUILTINS[2].program_id, BUILTINS[2].name)
 }
 
+/// Get the key and account for the Loader v4 program.
+pub fn keyed_account_for_loader_v4_program() -> (Pubkey, Account) {
+    create_keyed_account_for_builtin_program(&BUILTINS[4].program_id, BUILTINS[4].name)
+}
+
 /* ... */
 
 /// Create a BPF Loader 1 (deprecated) program account.
@@ -352,10 +674,13 @@ pub fn create_program_account_loader_v2(elf: &[u8]) -> Account {
     }
 }
 
+fn get_programdata_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[program_id.as_ref()], &loader_keys::LOADER_V3).0
+}
+
 /// Create a BPF Loader v3 (Upgradeable) program account.
 pub fn create_program_account_loader_v3(program_id: &Pubkey) -> Account {
-    let programdata_address =
-        Pubkey::find_program_address(&[program_id.as_ref()], &loader_keys::LOADER_V3).0;
+    let programdata_address = get_programdata_address(program_id);
     let data = bincode::serialize(&UpgradeableLoaderState::Program {
         programdata_address,
     })
@@ -410,3 +735,44 @@ pub fn create_program_account_pair_loader_v3(
         create_program_data_account_loader_v3(elf),
     )
 }
+
+/// Create a deployed Loader v4 program account, with no authority.
+pub fn create_program_account_loader_v4(elf: &[u8]) -> Account {
+    create_program_account_loader_v4_with_status(elf, &Pubkey::default(), LoaderV4Status::Deployed)
+}
+
+/// Create a Loader v4 program account with the provided authority and
+/// deployment status.
+///
+/// For finalized programs, `authority_address_or_next_version` is the address
+/// of the program's next version.
+pub fn create_program_account_loader_v4_with_status(
+    elf: &[u8],
+    authority_address_or_next_version: &Pubkey,
+    status: LoaderV4Status,
+) -> Account {
+    let data = {
+        let elf_offset = LoaderV4State::program_data_offset();
+        let data_len = elf_offset + elf.len();
+        let mut data = vec![0; data_len];
+        crate::loader_v4::set_state(
+            &mut data,
+            &LoaderV4State {
+                slot: 0,
+                authority_address_or_next_version: *authority_address_or_next_version,
+                status,
+            },
+        )
+        .unwrap();
+        data[elf_offset..].copy_from_slice(elf);
+        data
+    };
+    let lamports = Rent::default().minimum_balance(data.len());
+    Account {
+        lamports,
+        data,
+        owner: loader_keys::LOADER_V4,
+        executable: true,
+        ..Default::default()
+    }
+}
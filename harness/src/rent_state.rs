@@ -0,0 +1,210 @@
+//! Account rent states and the transitions allowed between them, mirroring
+//! the runtime's post-execution rent checks.
+
+use {
+    solana_account::ReadableAccount,
+    solana_message::SanitizedMessage,
+    solana_pubkey::Pubkey,
+    solana_rent::Rent,
+    solana_transaction_context::{transaction::TransactionContext, IndexOfAccount},
+    solana_transaction_error::TransactionError,
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum RentState {
+    /// `lamports == 0`
+    Uninitialized,
+    /// `0 < lamports < rent-exempt minimum`
+    RentPaying { lamports: u64, data_size: usize },
+    /// `lamports >= rent-exempt minimum`
+    RentExempt,
+}
+
+impl RentState {
+    pub(crate) fn from_balance(lamports: u64, data_size: usize, rent: &Rent) -> Self {
+        if lamports == 0 {
+            Self::Uninitialized
+        } else if rent.is_exempt(lamports, data_size) {
+            Self::RentExempt
+        } else {
+            Self::RentPaying {
+                lamports,
+                data_size,
+            }
+        }
+    }
+
+    /// Accounts may always become uninitialized or rent-exempt. An account
+    /// may only remain rent-paying if it was already rent-paying, was not
+    /// resized, and was not credited.
+    pub(crate) fn transition_allowed(&self, post: &Self) -> bool {
+        match (self, post) {
+            (_, Self::Uninitialized | Self::RentExempt) => true,
+            (
+                Self::RentPaying {
+                    lamports: pre_lamports,
+                    data_size: pre_data_size,
+                },
+                Self::RentPaying {
+                    lamports: post_lamports,
+                    data_size: post_data_size,
+                },
+            ) => post_data_size == pre_data_size && post_lamports <= pre_lamports,
+            _ => false,
+        }
+    }
+}
+
+fn pre_exec_rent_state(
+    lamports: u64,
+    data_size: usize,
+    rent: &Rent,
+    relax_post_exec_min_balance_check: bool,
+) -> RentState {
+    match RentState::from_balance(lamports, data_size, rent) {
+        RentState::RentPaying { .. } if relax_post_exec_min_balance_check => RentState::RentExempt,
+        state => state,
+    }
+}
+
+fn post_exec_rent_state(
+    lamports: u64,
+    data_size: usize,
+    rent: &Rent,
+    pre_lamports: u64,
+    relax_rent_exempt_criteria: bool,
+) -> RentState {
+    match RentState::from_balance(lamports, data_size, rent) {
+        RentState::RentPaying { .. } if relax_rent_exempt_criteria && lamports >= pre_lamports => {
+            RentState::RentExempt
+        }
+        state => state,
+    }
+}
+
+fn check_transition(
+    pre_state: &RentState,
+    post_state: &RentState,
+    account_index: u8,
+) -> Result<(), TransactionError> {
+    if pre_state.transition_allowed(post_state) {
+        Ok(())
+    } else {
+        Err(TransactionError::InsufficientFundsForRent { account_index })
+    }
+}
+
+/// Check the rent state transition of an account whose owner and data size
+/// did not change, such as a fee payer being debited.
+///
+/// When `relax_post_exec_min_balance_check` is active (SIMD-0392), accounts
+/// are no longer considered rent-paying before execution, and a rent-exempt
+/// account may drop below the minimum balance as long as it wasn't debited.
+pub(crate) fn check_static_account_transition(
+    pre_lamports: u64,
+    post_lamports: u64,
+    data_size: usize,
+    rent: &Rent,
+    account_index: u8,
+    relax_post_exec_min_balance_check: bool,
+) -> Result<(), TransactionError> {
+    let pre_state = pre_exec_rent_state(
+        pre_lamports,
+        data_size,
+        rent,
+        relax_post_exec_min_balance_check,
+    );
+    let post_state = post_exec_rent_state(
+        post_lamports,
+        data_size,
+        rent,
+        pre_lamports,
+        relax_post_exec_min_balance_check && pre_state == RentState::RentExempt,
+    );
+    check_transition(&pre_state, &post_state, account_index)
+}
+
+/// The pre-execution state of a writable transaction account.
+pub(crate) struct WritableAccountStateInfo {
+    rent_state: RentState,
+    lamports: u64,
+    data_size: usize,
+    owner: Pubkey,
+}
+
+/// Capture the pre-execution rent state of every writable account in the
+/// transaction. Readonly accounts are `None`.
+pub(crate) fn pre_exec_state_infos(
+    transaction_context: &TransactionContext,
+    message: &SanitizedMessage,
+    rent: &Rent,
+    relax_post_exec_min_balance_check: bool,
+) -> Vec<Option<WritableAccountStateInfo>> {
+    (0..message.account_keys().len())
+        .map(|index| {
+            if !message.is_writable(index) {
+                return None;
+            }
+            let account = transaction_context
+                .accounts()
+                .try_borrow(index as IndexOfAccount)
+                .ok()?;
+            let lamports = account.lamports();
+            let data_size = account.data().len();
+            Some(WritableAccountStateInfo {
+                rent_state: pre_exec_rent_state(
+                    lamports,
+                    data_size,
+                    rent,
+                    relax_post_exec_min_balance_check,
+                ),
+                lamports,
+                data_size,
+                owner: *account.owner(),
+            })
+        })
+        .collect()
+}
+
+/// Verify the rent state transitions of all writable accounts after
+/// execution, returning the first violation as the runtime would.
+///
+/// Under SIMD-0392, a rent-exempt account may drop below the minimum balance
+/// if it was not debited, shrunk, or reassigned. The incinerator is exempt
+/// from these checks.
+pub(crate) fn verify_post_exec_state_transitions(
+    pre_state_infos: &[Option<WritableAccountStateInfo>],
+    transaction_context: &TransactionContext,
+    rent: &Rent,
+    relax_post_exec_min_balance_check: bool,
+) -> Result<(), TransactionError> {
+    for (index, pre) in pre_state_infos.iter().enumerate() {
+        let Some(pre) = pre else {
+            continue;
+        };
+        let index = index as IndexOfAccount;
+        let Ok(key) = transaction_context.get_key_of_account_at_index(index) else {
+            continue;
+        };
+        if solana_sdk_ids::incinerator::check_id(key) {
+            continue;
+        }
+        let Ok(account) = transaction_context.accounts().try_borrow(index) else {
+            continue;
+        };
+        let data_size = account.data().len();
+        let relax_rent_exempt_criteria = relax_post_exec_min_balance_check
+            && pre.data_size >= data_size
+            && pre.rent_state == RentState::RentExempt
+            && pre.owner == *account.owner();
+        let post_state = post_exec_rent_state(
+            account.lamports(),
+            data_size,
+            rent,
+            pre.lamports,
+            relax_rent_exempt_criteria,
+        );
+        check_transition(&pre.rent_state, &post_state, index as u8)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,351 @@
+//! Interception of syscalls made by SBF programs.
+//!
+//! Intercepted syscalls are replaced in the program runtime environment with
+//! a wrapper, which asks the registered interceptor what to do with each call:
+//! pass it through to the original syscall, return a value in its place, or
+//! abort the program with an error.
+//!
+//! Builtin functions are plain function pointers, so each intercepted syscall
+//! name is assigned one of a fixed number of wrapper slots, and interceptors
+//! are made available to the wrappers for the duration of execution. Slots are
+//! never released, so at most `MAX_WRAPPED_SYSCALLS` distinct syscalls can be
+//! intercepted in a single process.
+//!
+//! With the `syscall-trace` feature, every syscall is wrapped, and the
+//! wrappers also record each call in a trace.
+
+#[cfg(feature = "syscall-trace")]
+use mollusk_svm_result::types::SyscallTraceEntry;
+use {
+    solana_instruction::error::InstructionError,
+    solana_program_runtime::{
+        invoke_context::{BuiltinFunctionRegisterer, InvokeContext},
+        solana_sbpf::{
+            error::{EbpfError, ProgramResult},
+            program::{BuiltinFunction, BuiltinFunctionDefinition, BuiltinProgram},
+            vm::{ContextObject, EncryptedHostAddressToEbpfVm},
+        },
+    },
+    std::{cell::RefCell, collections::HashMap, rc::Rc, sync::RwLock},
+};
+
+type Context = InvokeContext<'static, 'static>;
+
+/// A syscall call observed by an interceptor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SyscallCall<'a> {
+    /// The name of the syscall, such as `sol_log_`.
+    pub name: &'a str,
+    /// The number of previous calls to the syscall while processing the
+    /// current instruction or transaction, starting from zero.
+    pub count: u64,
+    /// The raw arguments passed to the syscall. Pointers are VM addresses.
+    pub args: [u64; 5],
+}
+
+/// What an interceptor does with a syscall call.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SyscallAction {
+    /// Execute the original syscall.
+    PassThrough,
+    /// Skip the original syscall, returning the provided value to the program.
+    Return(u64),
+    /// Skip the original syscall, aborting the program with the provided
+    /// error.
+    Error(InstructionError),
+}
+
+/// An interceptor of a syscall.
+pub type SyscallInterceptor = Rc<dyn Fn(&SyscallCall) -> SyscallAction>;
+
+/// The maximum number of distinct syscalls that can be wrapped in a single
+/// process.
+pub const MAX_WRAPPED_SYSCALLS: usize = 128;
+
+/// The error returned when a syscall can't be intercepted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SyscallInterceptError {
+    /// No syscall with the provided name is registered.
+    NotRegistered(String),
+    /// Every wrapper slot is already in use.
+    SlotsExhausted,
+}
+
+impl std::fmt::Display for SyscallInterceptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotRegistered(name) => {
+                write!(f, "Syscall to intercept is not registered: {name}")
+            }
+            Self::SlotsExhausted => write!(
+                f,
+                "At most {MAX_WRAPPED_SYSCALLS} distinct syscalls can be intercepted"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SyscallInterceptError {}
+
+// The syscall name and original syscall assigned to each wrapper slot. Slots
+// are shared by every `Mollusk` instance in the process.
+#[allow(clippy::type_complexity)]
+static SLOTS: RwLock<Vec<(String, BuiltinFunction<Context>)>> = RwLock::new(Vec::new());
+
+/// Assign a wrapper slot to a syscall, reusing its slot if it already has one.
+pub(crate) fn slot_for(
+    name: &str,
+    original: BuiltinFunction<Context>,
+) -> Result<usize, SyscallInterceptError> {
+    let find = |slots: &[(String, BuiltinFunction<Context>)]| {
+        slots
+            .iter()
+            .position(|(n, f)| n == name && *f as usize == original as usize)
+    };
+    if let Some(slot) = find(&SLOTS.read().unwrap()) {
+        return Ok(slot);
+    }
+    let mut slots = SLOTS.write().unwrap();
+    if let Some(slot) = find(&slots) {
+        return Ok(slot);
+    }
+    if slots.len() == MAX_WRAPPED_SYSCALLS {
+        return Err(SyscallInterceptError::SlotsExhausted);
+    }
+    slots.push((name.to_string(), original));
+    Ok(slots.len() - 1)
+}
+
+struct Wrapper<const SLOT: usize>;
+
+impl<const SLOT: usize> BuiltinFunctionDefinition<Context> for Wrapper<SLOT> {
+    type Error = Box<dyn std::error::Error>;
+
+    fn rust(
+        _invoke_context: &mut Context,
+        _arg_a: u64,
+        _arg_b: u64,
+        _arg_c: u64,
+        _arg_d: u64,
+        _arg_e: u64,
+    ) -> Result<u64, Self::Error> {
+        // Wrappers dispatch from the VM interface, since passing through
+        // requires calling the original syscall's VM interface.
+        Err("intercepted syscalls are dispatched through `vm`".into())
+    }
+
+    fn vm(vm: EncryptedHostAddressToEbpfVm<Context>, a: u64, b: u64, c: u64, d: u64, e: u64) {
+        dispatch(SLOT, vm, [a, b, c, d, e]);
+    }
+}
+
+macro_rules! wrapper_registerers {
+    ($($slot:literal)*) => {
+        [$(Wrapper::<$slot>::register as BuiltinFunctionRegisterer),*]
+    };
+}
+
+const WRAPPER_REGISTERERS: [BuiltinFunctionRegisterer; MAX_WRAPPED_SYSCALLS] = wrapper_registerers!(
+    0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31
+    32 33 34 35 36 37 38 39 40 41 42 43 44 45 46 47 48 49 50 51 52 53 54 55 56 57 58 59 60
+    61 62 63 64 65 66 67 68 69 70 71 72 73 74 75 76 77 78 79 80 81 82 83 84 85 86 87 88 89
+    90 91 92 93 94 95 96 97 98 99 100 101 102 103 104 105 106 107 108 109 110 111 112 113
+    114 115 116 117 118 119 120 121 122 123 124 125 126 127
+);
+
+/// Replace a syscall in the loader with its wrapper.
+///
+/// Returns `false`, leaving the loader unchanged, if the loader has no syscall
+/// with the provided name or no wrapper slot is left for it.
+pub(crate) fn wrap_syscall(loader: &mut BuiltinProgram<Context>, name: &str) -> bool {
+    let Some((_, (original, _))) = loader
+        .get_function_registry()
+        .lookup_by_name(name.as_bytes())
+    else {
+        return false;
+    };
+    let Ok(slot) = slot_for(name, original) else {
+        return false;
+    };
+    loader.unregister_function(name);
+    WRAPPER_REGISTERERS[slot](loader, name).unwrap();
+    true
+}
+
+struct ActiveSyscallInterceptors {
+    interceptors: Rc<RefCell<HashMap<String, SyscallInterceptor>>>,
+    counts: HashMap<String, u64>,
+    #[cfg(feature = "syscall-trace")]
+    trace: Vec<SyscallTraceEntry>,
+}
+
+thread_local! {
+    // The syscall interceptors of the `Mollusk` instance currently executing
+    // on this thread, and the number of calls to each intercepted syscall.
+    static ACTIVE_SYSCALL_INTERCEPTORS: RefCell<Option<ActiveSyscallInterceptors>> =
+        const { RefCell::new(None) };
+}
+
+/// Makes a set of syscall interceptors available to the wrappers until
+/// dropped. Call counts start from zero.
+pub(crate) struct ActiveSyscallInterceptorsGuard {
+    previous: Option<ActiveSyscallInterceptors>,
+}
+
+impl ActiveSyscallInterceptorsGuard {
+    pub(crate) fn new(interceptors: Rc<RefCell<HashMap<String, SyscallInterceptor>>>) -> Self {
+        let previous = ACTIVE_SYSCALL_INTERCEPTORS.with(|active| {
+            active.replace(Some(ActiveSyscallInterceptors {
+                interceptors,
+                counts: HashMap::new(),
+                #[cfg(feature = "syscall-trace")]
+                trace: Vec::new(),
+            }))
+        });
+        Self { previous }
+    }
+
+    /// Take the syscalls traced since the guard was created.
+    #[cfg(feature = "syscall-trace")]
+    pub(crate) fn take_trace(&self) -> Vec<SyscallTraceEntry> {
+        ACTIVE_SYSCALL_INTERCEPTORS.with(|active| {
+            active
+                .borrow_mut()
+                .as_mut()
+                .map(|active| std::mem::take(&mut active.trace))
+                .unwrap_or_default()
+        })
+    }
+}
+
+impl Drop for ActiveSyscallInterceptorsGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        ACTIVE_SYSCALL_INTERCEPTORS.with(|active| active.replace(previous));
+    }
+}
+
+fn dispatch(slot: usize, vm: EncryptedHostAddressToEbpfVm<Context>, args: [u64; 5]) {
+    let (name, original) = SLOTS.read().unwrap()[slot].clone();
+
+    #[cfg(feature = "syscall-trace")]
+    // SAFETY: The copy is only used while the wrapper isn't using the
+    // original.
+    let mut traced_vm = unsafe { std::ptr::read(&vm) };
+    #[cfg(feature = "syscall-trace")]
+    let trace_index = start_trace(&mut traced_vm, &name, args);
+
+    let interception = ACTIVE_SYSCALL_INTERCEPTORS.with(|active| {
+        let mut active = active.borrow_mut();
+        let active = active.as_mut()?;
+        let interceptor = active.interceptors.borrow().get(&name).cloned()?;
+        let count = active.counts.entry(name.clone()).or_default();
+        let call_count = *count;
+        *count += 1;
+        Some((interceptor, call_count))
+    });
+    let action = match interception {
+        Some((interceptor, count)) => interceptor(&SyscallCall {
+            name: &name,
+            count,
+            args,
+        }),
+        None => SyscallAction::PassThrough,
+    };
+    match action {
+        SyscallAction::PassThrough => {
+            let [a, b, c, d, e] = args;
+            original(vm, a, b, c, d, e);
+        }
+        SyscallAction::Return(value) => set_program_result(vm, ProgramResult::Ok(value)),
+        SyscallAction::Error(err) => set_program_result(
+            vm,
+            ProgramResult::Err(EbpfError::SyscallError(Box::new(err))),
+        ),
+    }
+
+    #[cfg(feature = "syscall-trace")]
+    finish_trace(traced_vm, trace_index);
+}
+
+// Record a syscall call, returning its index in the trace, along with the
+// compute units remaining before the call.
+#[cfg(feature = "syscall-trace")]
+fn start_trace(
+    vm: &mut EncryptedHostAddressToEbpfVm<Context>,
+    name: &str,
+    args: [u64; 5],
+) -> Option<(usize, u64)> {
+    // SAFETY: The VM address was provided to the wrapper by the VM, and is
+    // the only one in use.
+    let (program_id, stack_height, remaining) = unsafe {
+        vm.with_vm(|vm| {
+            // Instructions executed since the last syscall have yet to be
+            // charged.
+            let pending_compute_units = vm.previous_instruction_meter - vm.due_insn_count;
+            let invoke_context = vm.context();
+            let program_id = invoke_context
+                .transaction_context
+                .get_current_instruction_context()
+                .ok()
+                .and_then(|instruction_context| instruction_context.get_program_key().ok())
+                .copied()
+                .unwrap_or_default();
+            let remaining = invoke_context
+                .get_remaining()
+                .saturating_sub(pending_compute_units);
+            (
+                program_id,
+                invoke_context.get_stack_height() as u32,
+                remaining,
+            )
+        })
+    };
+    ACTIVE_SYSCALL_INTERCEPTORS.with(|active| {
+        let mut active = active.borrow_mut();
+        let active = active.as_mut()?;
+        active.trace.push(SyscallTraceEntry {
+            name: name.to_string(),
+            program_id,
+            stack_height,
+            args,
+            compute_units_consumed: 0,
+        });
+        Some((active.trace.len() - 1, remaining))
+    })
+}
+
+#[cfg(feature = "syscall-trace")]
+fn finish_trace(mut vm: EncryptedHostAddressToEbpfVm<Context>, trace_index: Option<(usize, u64)>) {
+    let Some((index, remaining_before)) = trace_index else {
+        return;
+    };
+    // SAFETY: The syscall has returned, so the VM address is the only one in
+    // use.
+    let remaining = unsafe { vm.with_vm(|vm| vm.context().get_remaining()) };
+    ACTIVE_SYSCALL_INTERCEPTORS.with(|active| {
+        if let Some(active) = active.borrow_mut().as_mut() {
+            active.trace[index].compute_units_consumed = remaining_before.saturating_sub(remaining);
+        }
+    });
+}
+
+// Mirrors `BuiltinFunctionDefinition::vm`, settling the instruction meter
+// around the result of a skipped syscall.
+fn set_program_result(mut vm: EncryptedHostAddressToEbpfVm<Context>, result: ProgramResult) {
+    // SAFETY: The VM address was provided to the wrapper by the VM, and is
+    // the only one in use.
+    unsafe {
+        vm.with_vm(|vm| {
+            let enable_instruction_meter = vm.loader.get_config().enable_instruction_meter;
+            if enable_instruction_meter {
+                let used_compute_units = vm.previous_instruction_meter - vm.due_insn_count;
+                vm.context().consume(used_compute_units);
+            }
+            vm.program_result = result;
+            if enable_instruction_meter {
+                vm.previous_instruction_meter = vm.context().get_remaining();
+            }
+        })
+    }
+}
@@ -32,6 +32,22 @@ pub struct Sysvars {
     pub recent_blockhashes: RecentBlockhashes,
 }
 
+// `SlotHashes` doesn't implement `Clone`.
+impl Clone for Sysvars {
+    fn clone(&self) -> Self {
+        Self {
+            clock: self.clock.clone(),
+            epoch_rewards: self.epoch_rewards.clone(),
+            epoch_schedule: self.epoch_schedule.clone(),
+            last_restart_slot: self.last_restart_slot.clone(),
+            rent: self.rent.clone(),
+            slot_hashes: SlotHashes::new(self.slot_hashes.slot_hashes()),
+            stake_history: self.stake_history.clone(),
+            recent_blockhashes: self.recent_blockhashes.clone(),
+        }
+    }
+}
+
 impl Default for Sysvars {
     fn default() -> Self {
         let clock = Clock::default();
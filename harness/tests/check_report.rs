@@ -0,0 +1,151 @@
+use {
+    mollusk_svm::{
+        result::{Check, Compare},
+        Mollusk,
+    },
+    solana_account::Account,
+    solana_instruction::Instruction,
+    solana_pubkey::Pubkey,
+};
+
+fn transfer() -> (Instruction, Vec<(Pubkey, Account)>) {
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    (
+        solana_system_interface::instruction::transfer(&sender, &recipient, 42_000),
+        vec![
+            (
+                sender,
+                Account::new(100_000_000, 0, &solana_sdk_ids::system_program::id()),
+            ),
+            (
+                recipient,
+                Account::new(100_000_000, 0, &solana_sdk_ids::system_program::id()),
+            ),
+        ],
+    )
+}
+
+#[test]
+fn test_check_report() {
+    let mollusk = Mollusk::default();
+    let (instruction, accounts) = transfer();
+    let sender = accounts[0].0;
+    let recipient = accounts[1].0;
+    let missing = Pubkey::new_unique();
+
+    let result = mollusk.process_instruction(&instruction, &accounts);
+
+    // Passing checks produce an empty report.
+    let report = result.check_report(
+        &[
+            Check::success(),
+            Check::account(&recipient).lamports(100_042_000).build(),
+        ],
+        &mollusk,
+    );
+    assert!(report.is_pass());
+    assert!(report.into_result().is_ok());
+
+    // Every failed check is reported, not just the first.
+    let owner = Pubkey::new_unique();
+    let report = result.check_report(
+        &[
+            Check::compute_units(1),
+            Check::success(),
+            Check::account(&sender)
+                .lamports(100_000_000)
+                .owner(&owner)
+                .build(),
+            Check::account(&missing).build(),
+            Check::logs_contain("goodbye"),
+        ],
+        &mollusk,
+    );
+    assert!(!report.is_pass());
+
+    let failures = report
+        .failures
+        .iter()
+        .map(|failure| (failure.check.as_str(), failure.pubkey))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        failures,
+        vec![
+            ("compute_units", None),
+            ("account_lamports", Some(sender)),
+            ("account_owner", Some(sender)),
+            ("account", Some(missing)),
+            ("logs_contain", None),
+        ],
+    );
+
+    let lamports = &report.failures[1];
+    assert_eq!(lamports.expected, "100000000");
+    assert_eq!(lamports.actual, "99958000");
+    assert_eq!(
+        lamports.message,
+        format!(
+            "CHECK FAILED: account_lamports ({sender})\n  Expected: `100000000`,\n Got: `99958000`"
+        ),
+    );
+
+    let rendered = report.to_string();
+    assert!(rendered.starts_with("5 check(s) failed"));
+    for failure in &report.failures {
+        assert!(rendered.contains(&failure.message));
+    }
+
+    // The report converts into an error.
+    let error: Box<dyn std::error::Error> = report.clone().into_result().unwrap_err().into();
+    assert_eq!(error.to_string(), report.to_string());
+}
+
+#[test]
+fn test_compare_report() {
+    let mollusk = Mollusk::default();
+    let (instruction, accounts) = transfer();
+    let sender = accounts[0].0;
+    let recipient = accounts[1].0;
+
+    let result_a = mollusk.process_instruction(&instruction, &accounts);
+    let result_b = mollusk.process_instruction(&instruction, &accounts);
+    assert!(result_a
+        .compare_report(&result_b, &Compare::everything())
+        .is_pass());
+
+    let instruction = solana_system_interface::instruction::transfer(&sender, &recipient, 1);
+    let result_c = mollusk.process_instruction(&instruction, &accounts);
+
+    // Both accounts' lamports differ.
+    let report = result_a.compare_report(&result_c, &Compare::everything());
+    let failures = report
+        .failures
+        .iter()
+        .map(|failure| (failure.check.as_str(), failure.pubkey))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        failures,
+        vec![
+            ("resulting_account_lamports", Some(sender)),
+            ("resulting_account_lamports", Some(recipient)),
+        ],
+    );
+}
+
+#[test]
+#[should_panic(expected = "CHECK FAILED: compute_units")]
+fn test_run_checks_panics_on_first_failure() {
+    let mollusk = Mollusk::default();
+    let (instruction, accounts) = transfer();
+    let sender = accounts[0].0;
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[
+            Check::compute_units(1),
+            Check::account(&sender).lamports(0).build(),
+        ],
+    );
+}
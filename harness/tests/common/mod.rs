@@ -2,7 +2,57 @@
 
 #![allow(dead_code)]
 
-use solana_svm_feature_set::SVMFeatureSet;
+use {
+    mollusk_svm::{file::read_file, program::loader_keys, Mollusk},
+    solana_account::Account,
+    solana_instruction::{AccountMeta, Instruction},
+    solana_program_option::COption,
+    solana_program_pack::Pack,
+    solana_pubkey::Pubkey,
+    solana_svm_feature_set::SVMFeatureSet,
+    spl_token_interface::state::Mint,
+};
+
+const MEMO_ELF_PATH: &str = "../programs/memo/src/elf/memo.so";
+
+/// A `Mollusk` with the SPL Memo program loaded under a new program ID.
+pub fn memo_mollusk() -> (Mollusk, Pubkey) {
+    let program_id = Pubkey::new_unique();
+    let mut mollusk = Mollusk::default();
+    mollusk.add_program_with_loader_and_elf(
+        &program_id,
+        &loader_keys::LOADER_V2,
+        &read_file(MEMO_ELF_PATH),
+    );
+    (mollusk, program_id)
+}
+
+/// A signed memo, which SPL Memo logs the signer and the memo for.
+pub fn signed_memo(program_id: &Pubkey, memo: &[u8]) -> (Instruction, Vec<(Pubkey, Account)>) {
+    let signer = Pubkey::new_unique();
+    (
+        Instruction::new_with_bytes(
+            *program_id,
+            memo,
+            vec![AccountMeta::new_readonly(signer, true)],
+        ),
+        vec![(signer, Account::default())],
+    )
+}
+
+/// An initialized SPL Token mint with no freeze authority.
+pub fn mint_data() -> Vec<u8> {
+    let mint = Mint {
+        mint_authority: COption::Some(Pubkey::new_unique()),
+        supply: 0,
+        decimals: 9,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    };
+    let mut data = vec![0; Mint::LEN];
+    Mint::pack(mint, &mut data).unwrap();
+    data
+}
 
 /// Field-by-field equality check for `SVMFeatureSet`, since the upstream
 /// type does not derive `PartialEq`.
@@ -0,0 +1,134 @@
+use {
+    mollusk_svm::{
+        result::{Check, Compare},
+        Mollusk,
+    },
+    solana_account::Account,
+    solana_pubkey::Pubkey,
+};
+
+#[test]
+fn test_compute_units_tolerance_checks() {
+    let mollusk = Mollusk::default();
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let instruction = solana_system_interface::instruction::transfer(&sender, &recipient, 42_000);
+    let accounts = [
+        (
+            sender,
+            Account::new(100_000_000, 0, &solana_sdk_ids::system_program::id()),
+        ),
+        (
+            recipient,
+            Account::new(100_000_000, 0, &solana_sdk_ids::system_program::id()),
+        ),
+    ];
+
+    let result = mollusk.process_instruction(&instruction, &accounts);
+    assert_eq!(result.compute_units_consumed, 150);
+
+    let report = result.check_report(
+        &[
+            Check::compute_units_at_most(150),
+            Check::compute_units_at_most(1_000),
+            Check::compute_units_within(150, 0.0),
+            Check::compute_units_within(148, 2.0),
+            Check::compute_units_within(153, 2.0),
+        ],
+        &mollusk,
+    );
+    assert!(report.is_pass(), "{report}");
+
+    let report = result.check_report(
+        &[
+            Check::compute_units_at_most(149),
+            Check::compute_units_within(140, 5.0),
+            Check::compute_units_within(160, 5.0),
+        ],
+        &mollusk,
+    );
+    let failures = report
+        .failures
+        .iter()
+        .map(|failure| (failure.check.as_str(), failure.expected.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        failures,
+        vec![
+            ("compute_units_at_most", "at most 149"),
+            ("compute_units_within", "140 ± 7 (5%)"),
+            ("compute_units_within", "160 ± 8 (5%)"),
+        ],
+    );
+    assert!(report
+        .failures
+        .iter()
+        .all(|failure| failure.actual == "150"));
+}
+
+#[test]
+fn test_compare_compute_units_within() {
+    let mollusk = Mollusk::default();
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let instruction = solana_system_interface::instruction::transfer(&sender, &recipient, 42_000);
+    let accounts = [
+        (
+            sender,
+            Account::new(100_000_000, 0, &solana_sdk_ids::system_program::id()),
+        ),
+        (
+            recipient,
+            Account::new(100_000_000, 0, &solana_sdk_ids::system_program::id()),
+        ),
+    ];
+
+    let result_a = mollusk.process_instruction(&instruction, &accounts);
+    let mut result_b = result_a.clone();
+    result_b.compute_units_consumed = 153;
+
+    // 153 is within 2% of 150, but not exactly equal.
+    assert!(!result_a
+        .compare_report(&result_b, &[Compare::ComputeUnits])
+        .is_pass());
+    assert!(result_a
+        .compare_report(
+            &result_b,
+            &[Compare::ComputeUnitsWithin {
+                absolute: 0,
+                percent: 2.0,
+            }],
+        )
+        .is_pass());
+
+    // The greater of the absolute and relative tolerances applies.
+    assert!(result_a
+        .compare_report(
+            &result_b,
+            &[Compare::ComputeUnitsWithin {
+                absolute: 3,
+                percent: 0.0,
+            }],
+        )
+        .is_pass());
+
+    result_b.compute_units_consumed = 154;
+    let report = result_a.compare_report(
+        &result_b,
+        &[Compare::ComputeUnitsWithin {
+            absolute: 3,
+            percent: 2.0,
+        }],
+    );
+    assert_eq!(report.failures.len(), 1);
+    assert_eq!(report.failures[0].check, "compute_units_consumed");
+    assert_eq!(report.failures[0].expected, "150 ± 3");
+    assert_eq!(report.failures[0].actual, "154");
+
+    assert!(Compare::ComputeUnitsWithin {
+        absolute: 0,
+        percent: 2.0,
+    }
+    .is_compute_units());
+    assert!(!Compare::ProgramResult.is_compute_units());
+}
@@ -0,0 +1,109 @@
+use {
+    mollusk_svm::{program::Builtin, result::Check, Mollusk},
+    solana_account::Account,
+    solana_program_runtime::solana_sbpf::program::BuiltinFunctionDefinition,
+    solana_pubkey::Pubkey,
+    std::collections::HashMap,
+};
+
+fn system_account_with_lamports(lamports: u64) -> Account {
+    Account::new(lamports, 0, &solana_sdk_ids::system_program::id())
+}
+
+#[test]
+fn test_snapshot_and_restore() {
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+
+    let base_lamports = 100_000_000u64;
+    let transfer_amount = 42_000u64;
+
+    let mut account_store = HashMap::new();
+    account_store.insert(sender, system_account_with_lamports(base_lamports));
+    account_store.insert(recipient, system_account_with_lamports(base_lamports));
+
+    let mut context = Mollusk::default().with_context(account_store);
+
+    let transfer =
+        solana_system_interface::instruction::transfer(&sender, &recipient, transfer_amount);
+
+    // Set up some state to branch from.
+    context.process_and_validate_instruction(&transfer, &[Check::success()]);
+    let snapshot = context.snapshot();
+
+    // Branch from the snapshot multiple times.
+    for _ in 0..3 {
+        context.process_and_validate_instruction(&transfer, &[Check::success()]);
+        context.process_and_validate_instruction(&transfer, &[Check::success()]);
+        context.mollusk.warp_to_slot(1_000);
+        context.mollusk.epoch_stake.insert(Pubkey::new_unique(), 1);
+
+        context.restore(&snapshot);
+
+        let store = context.account_store.borrow();
+        assert_eq!(
+            store.get(&sender).unwrap().lamports,
+            base_lamports - transfer_amount,
+        );
+        assert_eq!(
+            store.get(&recipient).unwrap().lamports,
+            base_lamports + transfer_amount,
+        );
+        assert_eq!(context.mollusk.sysvars.clock.slot, 0);
+        assert!(context.mollusk.epoch_stake.is_empty());
+    }
+}
+
+#[test]
+fn test_with_rollback() {
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let builtin_id = Pubkey::new_unique();
+
+    let base_lamports = 100_000_000u64;
+    let transfer_amount = 42_000u64;
+
+    let mut account_store = HashMap::new();
+    account_store.insert(sender, system_account_with_lamports(base_lamports));
+    account_store.insert(recipient, system_account_with_lamports(base_lamports));
+
+    let mut context = Mollusk::default().with_context(account_store);
+
+    let recipient_lamports = context.with_rollback(|context| {
+        context.process_and_validate_instruction(
+            &solana_system_interface::instruction::transfer(&sender, &recipient, transfer_amount),
+            &[Check::success()],
+        );
+        context.mollusk.warp_to_slot(1_000);
+        context.mollusk.program_cache.add_builtin(Builtin {
+            program_id: builtin_id,
+            name: "mock_builtin",
+            register_fn: solana_system_program::system_processor::Entrypoint::register,
+        });
+        assert!(context
+            .mollusk
+            .program_cache
+            .load_program(&builtin_id)
+            .is_some());
+
+        context
+            .account_store
+            .borrow()
+            .get(&recipient)
+            .unwrap()
+            .lamports
+    });
+
+    assert_eq!(recipient_lamports, base_lamports + transfer_amount);
+
+    // All changes made in the closure were rolled back.
+    let store = context.account_store.borrow();
+    assert_eq!(store.get(&sender).unwrap().lamports, base_lamports);
+    assert_eq!(store.get(&recipient).unwrap().lamports, base_lamports);
+    assert_eq!(context.mollusk.sysvars.clock.slot, 0);
+    assert!(context
+        .mollusk
+        .program_cache
+        .load_program(&builtin_id)
+        .is_none());
+}
@@ -0,0 +1,212 @@
+#![cfg(feature = "coverage")]
+
+mod common;
+
+use {
+    common::mint_data,
+    mollusk_svm::{
+        coverage::{CoverageCallback, CoverageReport},
+        result::Check,
+        Mollusk,
+    },
+    solana_account::Account,
+    solana_instruction::{AccountMeta, Instruction},
+    solana_pubkey::Pubkey,
+    std::path::{Path, PathBuf},
+};
+
+// Program names are resolved relative to the working directory.
+const MEMO_PROGRAM_NAME: &str = "../programs/memo/src/elf/memo";
+const TOKEN_PROGRAM_NAME: &str = "../programs/token/src/elf/token";
+const ASSOCIATED_TOKEN_PROGRAM_NAME: &str = "../programs/token/src/elf/associated_token";
+const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    solana_pubkey::pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+const TOKEN_PROGRAM_ID: Pubkey =
+    solana_pubkey::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+fn coverage_dir(name: &str) -> PathBuf {
+    let coverage_dir =
+        std::env::temp_dir().join(format!("mollusk-coverage-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&coverage_dir);
+    coverage_dir
+}
+
+fn covered_mollusk(program_id: &Pubkey, program_name: &str, coverage_dir: &Path) -> Mollusk {
+    let mut mollusk = Mollusk::new_debuggable(program_id, program_name, true);
+    mollusk.invocation_inspect_callback =
+        Box::new(CoverageCallback::new(coverage_dir.to_str().unwrap()));
+    mollusk
+}
+
+fn memo(program_id: &Pubkey, signed: bool) -> (Instruction, Vec<(Pubkey, Account)>) {
+    let signer = Pubkey::new_unique();
+    (
+        Instruction::new_with_bytes(
+            *program_id,
+            b"hello",
+            vec![AccountMeta::new_readonly(signer, signed)],
+        ),
+        vec![(signer, Account::default())],
+    )
+}
+
+#[test]
+fn test_coverage() {
+    let coverage_dir = coverage_dir("memo");
+    let program_id = Pubkey::new_unique();
+    let mollusk = covered_mollusk(&program_id, MEMO_PROGRAM_NAME, &coverage_dir);
+
+    let (instruction, accounts) = memo(&program_id, true);
+    mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    let report = CoverageReport::load(&coverage_dir).unwrap();
+    assert_eq!(report.programs.len(), 1);
+    let coverage = &report.programs[0];
+    assert_eq!(coverage.program_id, program_id);
+    assert!(coverage.executed_instructions > 0);
+    assert!(coverage.executed_instructions < coverage.instructions);
+
+    // The program has no debug info, so there are no source lines.
+    assert!(coverage.lines.is_empty());
+
+    // Executing the same path again covers nothing new.
+    let executed_instructions = coverage.executed_instructions;
+    mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+    let report = CoverageReport::load(&coverage_dir).unwrap();
+    assert_eq!(report.programs.len(), 1);
+    assert_eq!(
+        report.programs[0].executed_instructions,
+        executed_instructions
+    );
+
+    // Failing on a missing signature takes a different path.
+    let (instruction, accounts) = memo(&program_id, false);
+    mollusk.process_instruction(&instruction, &accounts);
+    let report = CoverageReport::load(&coverage_dir).unwrap();
+    assert!(report.programs[0].executed_instructions > executed_instructions);
+
+    std::fs::remove_dir_all(coverage_dir).unwrap();
+}
+
+#[test]
+fn test_coverage_merges_instances() {
+    let coverage_dir = coverage_dir("instances");
+
+    // Separate instances, such as separate tests, record into the same
+    // directory and are merged by ELF.
+    let program_id = Pubkey::new_unique();
+    let (instruction, accounts) = memo(&program_id, true);
+    covered_mollusk(&program_id, MEMO_PROGRAM_NAME, &coverage_dir)
+        .process_instruction(&instruction, &accounts);
+    let single = CoverageReport::load(&coverage_dir).unwrap();
+
+    let (instruction, accounts) = memo(&program_id, false);
+    covered_mollusk(&program_id, MEMO_PROGRAM_NAME, &coverage_dir)
+        .process_instruction(&instruction, &accounts);
+    let merged = CoverageReport::load(&coverage_dir).unwrap();
+
+    assert_eq!(merged.programs.len(), 1);
+    assert_eq!(merged.programs[0].elf_hash, single.programs[0].elf_hash);
+    assert!(merged.programs[0].executed_instructions > single.programs[0].executed_instructions);
+
+    std::fs::remove_dir_all(coverage_dir).unwrap();
+}
+
+#[test]
+fn test_coverage_cpi() {
+    let coverage_dir = coverage_dir("cpi");
+    let payer = Pubkey::new_unique();
+    let wallet = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let associated_token_address = Pubkey::find_program_address(
+        &[wallet.as_ref(), TOKEN_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0;
+
+    let mut mollusk = covered_mollusk(
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+        ASSOCIATED_TOKEN_PROGRAM_NAME,
+        &coverage_dir,
+    );
+    mollusk.add_program(&TOKEN_PROGRAM_ID, TOKEN_PROGRAM_NAME);
+
+    let instruction = Instruction::new_with_bytes(
+        ASSOCIATED_TOKEN_PROGRAM_ID,
+        &[],
+        vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(associated_token_address, false),
+            AccountMeta::new_readonly(wallet, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(solana_sdk_ids::system_program::id(), false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ],
+    );
+    let accounts = [
+        (
+            payer,
+            Account::new(10_000_000_000, 0, &solana_sdk_ids::system_program::id()),
+        ),
+        (associated_token_address, Account::default()),
+        (wallet, Account::default()),
+        (mint, {
+            let mut account = Account::new(1_000_000_000, 0, &TOKEN_PROGRAM_ID);
+            account.data = mint_data();
+            account
+        }),
+        mollusk_svm::program::keyed_account_for_system_program(),
+        (
+            TOKEN_PROGRAM_ID,
+            mollusk_svm::program::create_program_account_loader_v3(&TOKEN_PROGRAM_ID),
+        ),
+    ];
+
+    mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    // Programs invoked through CPI are covered too.
+    let report = CoverageReport::load(&coverage_dir).unwrap();
+    let mut program_ids = report
+        .programs
+        .iter()
+        .map(|coverage| coverage.program_id)
+        .collect::<Vec<_>>();
+    program_ids.sort();
+    let mut expected = vec![ASSOCIATED_TOKEN_PROGRAM_ID, TOKEN_PROGRAM_ID];
+    expected.sort();
+    assert_eq!(program_ids, expected);
+    assert!(report
+        .programs
+        .iter()
+        .all(|coverage| coverage.executed_instructions > 0));
+
+    std::fs::remove_dir_all(coverage_dir).unwrap();
+}
+
+#[test]
+fn test_coverage_reports() {
+    let coverage_dir = coverage_dir("reports");
+    let program_id = Pubkey::new_unique();
+    let mollusk = covered_mollusk(&program_id, MEMO_PROGRAM_NAME, &coverage_dir);
+
+    let (instruction, accounts) = memo(&program_id, true);
+    mollusk.process_instruction(&instruction, &accounts);
+
+    let output_dir = coverage_dir.join("reports");
+    CoverageReport::load(&coverage_dir)
+        .unwrap()
+        .write_reports(&output_dir)
+        .unwrap();
+
+    let base_fname = output_dir.join(program_id.to_string());
+    let lcov = std::fs::read_to_string(base_fname.with_extension("lcov")).unwrap();
+    assert_eq!(lcov, format!("TN:{program_id}\n"));
+
+    let cobertura = std::fs::read_to_string(base_fname.with_extension("cobertura.xml")).unwrap();
+    assert!(cobertura.starts_with("<?xml"));
+    assert!(cobertura.contains(r#"lines-covered="0" lines-valid="0""#));
+    assert!(cobertura.contains(&format!(r#"<package name="{program_id}""#)));
+    assert!(cobertura.trim_end().ends_with("</coverage>"));
+
+    std::fs::remove_dir_all(coverage_dir).unwrap();
+}
@@ -0,0 +1,159 @@
+use {
+    mollusk_svm::{
+        result::{
+            diff::{DataDiff, DataLayout},
+            Check, Compare,
+        },
+        Mollusk,
+    },
+    solana_account::Account,
+    solana_pubkey::Pubkey,
+};
+
+// A token account's data: mint, owner, then amount.
+fn token_account_data(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Vec<u8> {
+    let mut data = vec![];
+    data.extend_from_slice(mint.as_ref());
+    data.extend_from_slice(owner.as_ref());
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.extend_from_slice(&[0; 93]);
+    data
+}
+
+fn token_account_layout() -> DataLayout {
+    DataLayout::new()
+        .pubkey("mint", 0)
+        .pubkey("owner", 32)
+        .u64("amount", 64)
+}
+
+#[test]
+fn test_data_diff() {
+    let mint = Pubkey::new_from_array([1; 32]);
+    let owner = Pubkey::new_from_array([2; 32]);
+    let expected = token_account_data(&mint, &owner, 100);
+    let actual = token_account_data(&mint, &owner, 90);
+
+    assert_eq!(
+        DataDiff::new(&expected, &actual).to_string(),
+        "\
+- 00000030  02 02 02 02 02 02 02 02  02 02 02 02 02 02 02 02
++ 00000030  02 02 02 02 02 02 02 02  02 02 02 02 02 02 02 02
+- 00000040  64 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
++ 00000040  5a 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+            ^^
+- 00000050  00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
++ 00000050  00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+",
+    );
+
+    // With a layout, changed fields are named and decoded.
+    let layout = token_account_layout();
+    let diff = DataDiff::new(&expected, &actual)
+        .layout(&layout)
+        .to_string();
+    assert!(diff.starts_with("field 'amount' @ 64..72: 100 -> 90\n- 00000030"));
+
+    assert!(DataDiff::new(&expected, &expected).is_empty());
+}
+
+#[test]
+fn test_data_diff_length_change() {
+    let expected = vec![0; 40];
+    let mut actual = vec![0; 34];
+    actual[1] = 7;
+
+    let layout = DataLayout::new().u8("flag", 1).u64("tail", 32);
+    assert_eq!(
+        DataDiff::new(&expected, &actual)
+            .layout(&layout)
+            .to_string(),
+        "\
+length: 40 -> 34 (-6)
+field 'flag' @ 1..2: 0 -> 7
+field 'tail' @ 32..40: 0 -> <missing>
+- 00000000  00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
++ 00000000  00 07 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+               ^^
+- 00000010  00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
++ 00000010  00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+- 00000020  00 00 00 00 00 00 00 00
++ 00000020  00 00 -- -- -- -- -- --
+                  ^^ ^^ ^^ ^^ ^^ ^^
+",
+    );
+}
+
+#[test]
+fn test_data_diff_separate_ranges() {
+    let expected = vec![0; 128];
+    let mut actual = expected.clone();
+    actual[0] = 1;
+    actual[127] = 1;
+
+    // Unchanged rows between the changed ranges are elided.
+    let diff = DataDiff::new(&expected, &actual).to_string();
+    let rows = diff.lines().collect::<Vec<_>>();
+    assert_eq!(rows.len(), 5 + 1 + 5);
+    assert!(rows[0].starts_with("- 00000000"));
+    assert_eq!(rows[5], "...");
+    assert!(rows[6].starts_with("- 00000060"));
+}
+
+#[test]
+fn test_account_data_check_failure() {
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let key = Pubkey::new_unique();
+
+    let mollusk = Mollusk::default();
+    let instruction = solana_instruction::Instruction::new_with_bytes(
+        solana_sdk_ids::system_program::id(),
+        &[],
+        vec![],
+    );
+    let mut result = mollusk.process_instruction(&instruction, &[]);
+    result.resulting_accounts = vec![(
+        key,
+        Account {
+            data: token_account_data(&mint, &owner, 90),
+            ..Account::default()
+        },
+    )];
+
+    let expected = token_account_data(&mint, &owner, 100);
+    let layout = token_account_layout();
+    let report = result.check_report(
+        &[
+            Check::account(&key)
+                .data(&expected)
+                .data_layout(&layout)
+                .build(),
+            Check::account(&key)
+                .data_slice(64, &100u64.to_le_bytes())
+                .data_layout(&layout)
+                .build(),
+        ],
+        &mollusk,
+    );
+
+    assert_eq!(report.failures.len(), 2);
+    for failure in &report.failures {
+        assert!(failure
+            .message
+            .contains("field 'amount' @ 64..72: 100 -> 90\n"));
+        assert!(failure.message.contains("+ 00000040  5a 00"));
+    }
+    assert!(report.failures[0]
+        .message
+        .starts_with(&format!("CHECK FAILED: account_data ({key})\n")));
+
+    // Comparisons render a diff too.
+    let mut other = result.clone();
+    other.resulting_accounts[0].1.data = expected;
+    let report = result.compare_report(&other, &[Compare::all_resulting_accounts()]);
+    assert_eq!(report.failures.len(), 1);
+    assert!(report.failures[0]
+        .message
+        .contains("- 00000040  5a 00 00 00 00 00 00 00"));
+}
@@ -0,0 +1,270 @@
+#![cfg(all(feature = "borsh", feature = "bytemuck", feature = "program-pack"))]
+
+use {
+    borsh::{BorshDeserialize, BorshSerialize},
+    bytemuck::{Pod as PodType, Zeroable},
+    mollusk_svm::{
+        result::{
+            decode::{Borsh, Pack, Pod},
+            Check, Config as CheckConfig, InstructionResult,
+        },
+        Mollusk,
+    },
+    solana_account::Account,
+    solana_program_option::COption,
+    solana_program_pack::Pack as _,
+    solana_pubkey::Pubkey,
+    spl_token_interface::state::{Account as TokenAccount, AccountState},
+    std::cell::Cell,
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, PodType, Zeroable)]
+struct Counter {
+    authority: [u8; 32],
+    count: u64,
+    bump: u8,
+    _padding: [u8; 7],
+}
+
+#[derive(Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+struct Config {
+    admin: [u8; 32],
+    fee_bps: u16,
+    paused: bool,
+    labels: Vec<String>,
+}
+
+// Run a no-op instruction, then set the resulting accounts to check against.
+fn result_with_accounts(mollusk: &Mollusk, accounts: Vec<(Pubkey, Account)>) -> InstructionResult {
+    let instruction = solana_instruction::Instruction::new_with_bytes(
+        solana_sdk_ids::system_program::id(),
+        &[],
+        vec![],
+    );
+    let mut result = mollusk.process_instruction(&instruction, &[]);
+    result.resulting_accounts = accounts;
+    result
+}
+
+fn account_with_data(data: Vec<u8>) -> Account {
+    Account {
+        data,
+        ..Account::default()
+    }
+}
+
+#[test]
+fn test_decoded_checks_pass() {
+    let mollusk = Mollusk::default();
+
+    let counter_key = Pubkey::new_unique();
+    let counter = Counter {
+        authority: [7; 32],
+        count: 42,
+        bump: 254,
+        _padding: [0; 7],
+    };
+
+    let config_key = Pubkey::new_unique();
+    let admin = Pubkey::new_unique().to_bytes();
+    let config = Config {
+        admin,
+        fee_bps: 25,
+        paused: false,
+        labels: vec!["alpha".to_string()],
+    };
+    // Borsh accounts may be allocated with extra space.
+    let mut config_data = borsh::to_vec(&config).unwrap();
+    config_data.extend_from_slice(&[0; 16]);
+
+    let token_key = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let token = TokenAccount {
+        mint,
+        owner,
+        amount: 1_000,
+        delegate: COption::None,
+        state: AccountState::Initialized,
+        is_native: COption::None,
+        delegated_amount: 0,
+        close_authority: COption::None,
+    };
+    let mut token_data = vec![0; TokenAccount::LEN];
+    TokenAccount::pack(token, &mut token_data).unwrap();
+
+    let result = result_with_accounts(
+        &mollusk,
+        vec![
+            (
+                counter_key,
+                account_with_data(bytemuck::bytes_of(&counter).to_vec()),
+            ),
+            (config_key, account_with_data(config_data)),
+            (token_key, account_with_data(token_data)),
+        ],
+    );
+
+    let report = result.check_report(
+        &[
+            Check::account(&counter_key)
+                .decoded(Pod, |counter: &Counter| counter.count == 42)
+                .decoded_eq(Pod, counter)
+                .build(),
+            Check::account(&config_key)
+                .decoded(Borsh, |config: &Config| config.admin == admin)
+                .decoded_eq(Borsh, config)
+                .build(),
+            Check::account(&token_key)
+                .decoded(Pack, |token: &TokenAccount| {
+                    token.owner == owner && token.amount == 1_000
+                })
+                .decoded_eq(Pack, token)
+                .build(),
+            // Any closure returning a `Result` is a decoder.
+            Check::account(&counter_key)
+                .decoded(|data: &[u8]| Ok(data[40]), |bump: &u8| *bump == 254)
+                .build(),
+        ],
+        &mollusk,
+    );
+    assert!(report.is_pass(), "{report}");
+}
+
+#[test]
+fn test_decoded_checks_fail() {
+    let mollusk = Mollusk::default();
+
+    let key = Pubkey::new_unique();
+    let admin = Pubkey::new_unique().to_bytes();
+    let config = Config {
+        admin,
+        fee_bps: 25,
+        paused: true,
+        labels: vec!["alpha".to_string(), "beta".to_string()],
+    };
+    let result = result_with_accounts(
+        &mollusk,
+        vec![(key, account_with_data(borsh::to_vec(&config).unwrap()))],
+    );
+
+    let expected = Config {
+        admin,
+        fee_bps: 25,
+        paused: false,
+        labels: vec!["alpha".to_string(), "beta".to_string()],
+    };
+    let report = result.check_report(
+        &[Check::account(&key)
+            .decoded(Borsh, |config: &Config| !config.paused)
+            .decoded_eq(Borsh, expected)
+            .decoded(Pod, |_: &Counter| true)
+            .build()],
+        &mollusk,
+    );
+
+    let failures = report
+        .failures
+        .iter()
+        .map(|failure| (failure.check.as_str(), failure.pubkey))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        failures,
+        vec![
+            ("account_decoded", Some(key)),
+            ("account_decoded", Some(key)),
+            ("account_decoded", Some(key)),
+        ],
+    );
+
+    // A failed predicate renders the decoded value.
+    let predicate = &report.failures[0].message;
+    assert!(predicate.starts_with(&format!(
+        "CHECK FAILED: account_decoded ({key})\nDecoded `decoded_checks::Config` does not satisfy \
+         the predicate:\nConfig {{\n"
+    )));
+    assert!(predicate.contains("    paused: true,\n"));
+
+    // A failed equality check renders a diff of the changed fields.
+    assert_eq!(
+        report.failures[1].message,
+        format!(
+            "\
+CHECK FAILED: account_decoded ({key})
+Decoded `decoded_checks::Config` mismatch:
+      fee_bps: 25,
+-     paused: false,
++     paused: true,
+      labels: ["
+        ),
+    );
+
+    // Data that cannot be decoded is reported.
+    assert!(report.failures[2]
+        .message
+        .contains("Failed to decode account data as `decoded_checks::Counter`"));
+}
+
+#[test]
+fn test_run_checks_stops_at_first_failure() {
+    let mollusk = Mollusk::default();
+    let key = Pubkey::new_unique();
+    let result = result_with_accounts(
+        &mollusk,
+        vec![(key, account_with_data(vec![0; size_of::<Counter>()]))],
+    );
+
+    let decoded = Cell::new(false);
+    let checks = [
+        Check::compute_units(u64::MAX),
+        Check::account(&key)
+            .decoded(Pod, |_: &Counter| {
+                decoded.set(true);
+                true
+            })
+            .build(),
+    ];
+
+    // Checks after the first failure aren't run.
+    let config = CheckConfig {
+        panic: false,
+        verbose: false,
+    };
+    assert!(!result.run_checks(&checks, &config, &mollusk));
+    assert!(!decoded.get());
+
+    // A report runs every check.
+    assert_eq!(result.check_report(&checks, &mollusk).failures.len(), 1);
+    assert!(decoded.get());
+}
+
+#[test]
+fn test_run_checks_continues_after_missing_account() {
+    let mollusk = Mollusk::default();
+    let key = Pubkey::new_unique();
+    let result = result_with_accounts(
+        &mollusk,
+        vec![(key, account_with_data(vec![0; size_of::<Counter>()]))],
+    );
+
+    let missing = Pubkey::new_unique();
+    let decoded = Cell::new(false);
+    let checks = [
+        Check::account(&missing).build(),
+        Check::account(&key)
+            .decoded(Pod, |_: &Counter| {
+                decoded.set(true);
+                true
+            })
+            .build(),
+    ];
+
+    // A missing account fails the checks, but later checks are still run.
+    let config = CheckConfig {
+        panic: false,
+        verbose: false,
+    };
+    assert!(!result.run_checks(&checks, &config, &mollusk));
+    assert!(decoded.get());
+}
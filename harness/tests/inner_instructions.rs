@@ -0,0 +1,310 @@
+#![cfg(feature = "inner-instructions")]
+
+use {
+    mollusk_svm::{
+        file::read_file,
+        program::{
+            create_program_account_loader_v2, keyed_account_for_system_program, loader_keys,
+        },
+        result::{types::ResolvedInnerInstruction, Check, Config},
+        Mollusk,
+    },
+    solana_account::Account,
+    solana_instruction::{AccountMeta, Instruction},
+    solana_pubkey::Pubkey,
+};
+
+const TOKEN_ELF_PATH: &str = "../programs/token/src/elf/token.so";
+const ASSOCIATED_TOKEN_ELF_PATH: &str = "../programs/token/src/elf/associated_token.so";
+const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    solana_pubkey::pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+const TOKEN_PROGRAM_ID: Pubkey =
+    solana_pubkey::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+// Set up the creation of an associated token account, which CPIs into the
+// token and system programs.
+fn create_associated_token_account() -> (Mollusk, Instruction, Vec<(Pubkey, Account)>) {
+    let payer = Pubkey::new_unique();
+    let wallet = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let associated_token_address = Pubkey::find_program_address(
+        &[wallet.as_ref(), TOKEN_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0;
+
+    let token_elf = read_file(TOKEN_ELF_PATH);
+
+    let mut mollusk = Mollusk::default();
+    mollusk.add_program_with_loader_and_elf(
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+        &loader_keys::LOADER_V2,
+        &read_file(ASSOCIATED_TOKEN_ELF_PATH),
+    );
+    mollusk.add_program_with_loader_and_elf(&TOKEN_PROGRAM_ID, &loader_keys::LOADER_V2, &token_elf);
+
+    // An initialized SPL Token mint with no freeze authority.
+    let mut mint_data = vec![1, 0, 0, 0];
+    mint_data.extend_from_slice(Pubkey::new_unique().as_ref());
+    mint_data.extend_from_slice(&0u64.to_le_bytes());
+    mint_data.extend_from_slice(&[9, 1]);
+    mint_data.extend_from_slice(&[0; 36]);
+
+    let instruction = Instruction::new_with_bytes(
+        ASSOCIATED_TOKEN_PROGRAM_ID,
+        &[],
+        vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(associated_token_address, false),
+            AccountMeta::new_readonly(wallet, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(solana_sdk_ids::system_program::id(), false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ],
+    );
+    let accounts = vec![
+        (
+            payer,
+            Account::new(10_000_000_000, 0, &solana_sdk_ids::system_program::id()),
+        ),
+        (associated_token_address, Account::default()),
+        (wallet, Account::default()),
+        (mint, {
+            let mut account = Account::new(1_000_000_000, 0, &TOKEN_PROGRAM_ID);
+            account.data = mint_data;
+            account
+        }),
+        keyed_account_for_system_program(),
+        (
+            TOKEN_PROGRAM_ID,
+            create_program_account_loader_v2(&token_elf),
+        ),
+    ];
+
+    (mollusk, instruction, accounts)
+}
+
+#[test]
+fn test_resolved_inner_instructions() {
+    let (mollusk, instruction, accounts) = create_associated_token_account();
+    let payer = instruction.accounts[0].pubkey;
+    let associated_token_address = instruction.accounts[1].pubkey;
+    let wallet = instruction.accounts[2].pubkey;
+    let mint = instruction.accounts[3].pubkey;
+
+    let result =
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    let resolved = &result.resolved_inner_instructions;
+    assert_eq!(resolved.len(), result.inner_instructions.len());
+    assert_eq!(
+        resolved
+            .iter()
+            .map(|inner| inner.instruction.program_id)
+            .collect::<Vec<_>>(),
+        vec![
+            TOKEN_PROGRAM_ID,
+            solana_sdk_ids::system_program::id(),
+            TOKEN_PROGRAM_ID,
+            TOKEN_PROGRAM_ID,
+        ],
+    );
+    assert!(resolved.iter().all(|inner| inner.stack_height == 2));
+
+    // The associated token account is created by the system program, signed
+    // for by the associated token program, even though it isn't a signer of
+    // the transaction.
+    let create_account = &resolved[1].instruction;
+    assert_eq!(
+        create_account.accounts,
+        vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(associated_token_address, true),
+        ],
+    );
+    let message = result.message.as_ref().unwrap();
+    let index = message
+        .account_keys()
+        .iter()
+        .position(|key| *key == associated_token_address)
+        .unwrap();
+    assert!(!message.is_signer(index));
+
+    // `InitializeAccount3`.
+    assert_eq!(
+        resolved[3],
+        ResolvedInnerInstruction {
+            instruction: Instruction {
+                program_id: TOKEN_PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new(associated_token_address, false),
+                    AccountMeta::new_readonly(mint, false),
+                ],
+                data: [&[18], wallet.as_ref()].concat(),
+            },
+            stack_height: 2,
+        },
+    );
+
+    // The resolved instructions match their compiled counterparts.
+    let account_keys = message.account_keys();
+    for (compiled, resolved) in result.inner_instructions.iter().zip(resolved) {
+        assert_eq!(compiled.stack_height, Some(resolved.stack_height));
+        assert_eq!(
+            account_keys[compiled.instruction.program_id_index as usize],
+            resolved.instruction.program_id,
+        );
+        assert_eq!(
+            compiled
+                .instruction
+                .accounts
+                .iter()
+                .map(|index| account_keys[*index as usize])
+                .collect::<Vec<_>>(),
+            resolved
+                .instruction
+                .accounts
+                .iter()
+                .map(|meta| meta.pubkey)
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(compiled.instruction.data, resolved.instruction.data);
+    }
+}
+
+#[test]
+fn test_resolved_inner_instructions_transaction() {
+    let (mollusk, instruction, accounts) = create_associated_token_account();
+    let transfer = solana_system_interface::instruction::transfer(
+        &instruction.accounts[0].pubkey,
+        &instruction.accounts[2].pubkey,
+        42,
+    );
+
+    let result =
+        mollusk.process_transaction_instructions(&[instruction, transfer], &accounts, None);
+
+    // CPIs are grouped under the top-level instruction which made them.
+    assert!(result.raw_result.is_ok());
+    assert_eq!(result.resolved_inner_instructions.len(), 2);
+    assert_eq!(result.resolved_inner_instructions[0].len(), 4);
+    assert!(result.resolved_inner_instructions[1].is_empty());
+}
+
+#[cfg(feature = "fuzz")]
+#[test]
+fn test_resolved_inner_instructions_from_fixture() {
+    use {
+        mollusk_svm::result::InstructionResult,
+        mollusk_svm_fuzz_fixture::{proto::InstrFixture, Fixture},
+    };
+
+    let (mollusk, instruction, accounts) = create_associated_token_account();
+    let result = mollusk.process_instruction(&instruction, &accounts);
+
+    // Fixtures store the resolved inner instructions, even though they don't
+    // store the compiled message.
+    let fixture = mollusk_svm::fuzz::mollusk::build_fixture_from_mollusk_test(
+        &mollusk,
+        &instruction,
+        &accounts,
+        &result,
+    );
+    let fixture = Fixture::from(InstrFixture::from(fixture));
+    let fixture_result = InstructionResult::from(&fixture.output);
+
+    assert!(fixture_result.message.is_none());
+    assert_eq!(
+        fixture_result.resolved_inner_instructions,
+        result.resolved_inner_instructions,
+    );
+}
+
+#[test]
+fn test_cpi_checks() {
+    let (mollusk, instruction, accounts) = create_associated_token_account();
+    let payer = instruction.accounts[0].pubkey;
+    let associated_token_address = instruction.accounts[1].pubkey;
+    let mint = instruction.accounts[3].pubkey;
+    let system_program = solana_sdk_ids::system_program::id();
+
+    let create_account_metas = [
+        AccountMeta::new(payer, true),
+        AccountMeta::new(associated_token_address, true),
+    ];
+    let initialize_account_keys = [associated_token_address, mint];
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[
+            Check::success(),
+            // Exactly one `InitializeAccount3`, at depth 2.
+            Check::cpi(&TOKEN_PROGRAM_ID)
+                .data_prefix(&[18])
+                .account_keys(&initialize_account_keys)
+                .stack_height(2)
+                .times(1)
+                .build(),
+            // The system program creates the account, with the PDA signing.
+            Check::cpi(&system_program)
+                .accounts(&create_account_metas)
+                .build(),
+            Check::cpi(&TOKEN_PROGRAM_ID).times(3).build(),
+            Check::cpis(&[
+                Check::cpi(&TOKEN_PROGRAM_ID).data_prefix(&[21]),
+                Check::cpi(&system_program),
+                Check::cpi(&TOKEN_PROGRAM_ID).data(&[22]),
+                Check::cpi(&TOKEN_PROGRAM_ID).data_prefix(&[18]),
+            ]),
+            Check::cpis_in_order(&[
+                Check::cpi(&system_program),
+                Check::cpi(&TOKEN_PROGRAM_ID).data_prefix(&[18]),
+            ]),
+        ],
+    );
+}
+
+#[test]
+fn test_cpi_checks_fail() {
+    let (mollusk, instruction, accounts) = create_associated_token_account();
+    let payer = instruction.accounts[0].pubkey;
+    let associated_token_address = instruction.accounts[1].pubkey;
+    let system_program = solana_sdk_ids::system_program::id();
+
+    let result = mollusk.process_instruction(&instruction, &accounts);
+
+    let config = Config {
+        panic: false,
+        verbose: false,
+    };
+    let check = |check: Check| result.run_checks(&[check], &config, &mollusk);
+
+    // The PDA signs the CPI, so readonly or unsigned metas don't match.
+    let unsigned_metas = [
+        AccountMeta::new(payer, true),
+        AccountMeta::new(associated_token_address, false),
+    ];
+    assert!(!check(
+        Check::cpi(&system_program)
+            .accounts(&unsigned_metas)
+            .build()
+    ));
+    assert!(!check(Check::cpi(&Pubkey::new_unique()).build()));
+    assert!(!check(Check::cpi(&TOKEN_PROGRAM_ID).times(1).build()));
+    assert!(!check(
+        Check::cpi(&TOKEN_PROGRAM_ID).stack_height(3).build()
+    ));
+    assert!(!check(Check::cpi(&TOKEN_PROGRAM_ID).data(&[18]).build()));
+
+    // Missing a CPI, or out of order.
+    assert!(!check(Check::cpis(&[
+        Check::cpi(&TOKEN_PROGRAM_ID),
+        Check::cpi(&system_program),
+        Check::cpi(&TOKEN_PROGRAM_ID),
+    ])));
+    assert!(!check(Check::cpis_in_order(&[
+        Check::cpi(&TOKEN_PROGRAM_ID).data_prefix(&[18]),
+        Check::cpi(&system_program),
+    ])));
+}
@@ -185,3 +185,41 @@ fn test_mixed() {
         ],
     );
 }
+
+#[test]
+fn test_chain_results() {
+    let mollusk = Mollusk::default();
+
+    let alice = Pubkey::new_unique();
+    let bob = Pubkey::new_unique();
+
+    let instructions = [
+        solana_system_interface::instruction::transfer(&alice, &bob, 1_000),
+        // Alice can't cover this one, so the chain stops here.
+        solana_system_interface::instruction::transfer(&alice, &bob, 100_000_000),
+        solana_system_interface::instruction::transfer(&bob, &alice, 500),
+    ];
+    let accounts = [
+        (alice, system_account_with_lamports(100_000_000)),
+        (bob, system_account_with_lamports(100_000_000)),
+    ];
+
+    let results = mollusk.process_instruction_chain_results(&instructions, &accounts);
+    assert_eq!(results.len(), 2);
+    assert!(results[0].program_result.is_ok());
+    assert!(results[1].program_result.is_err());
+    // Each instruction sees the accounts left by the previous one.
+    assert_eq!(results[0].resulting_accounts[0].1.lamports, 99_999_000);
+
+    // The composite result is made of the same results.
+    let composite = mollusk.process_instruction_chain(&instructions, &accounts);
+    assert_eq!(
+        composite.compute_units_consumed,
+        results
+            .iter()
+            .map(|result| result.compute_units_consumed)
+            .sum::<u64>(),
+    );
+    assert_eq!(composite.program_result, results[1].program_result);
+    assert_eq!(composite.resulting_accounts, results[1].resulting_accounts);
+}
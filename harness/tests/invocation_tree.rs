@@ -0,0 +1,259 @@
+#![cfg(feature = "invocation-tree")]
+
+mod common;
+
+use {
+    common::{memo_mollusk, mint_data},
+    mollusk_svm::{
+        file::read_file,
+        program::{
+            create_program_account_loader_v2, keyed_account_for_system_program, loader_keys,
+        },
+        result::{Check, ProgramResult},
+        Mollusk,
+    },
+    solana_account::Account,
+    solana_instruction::{AccountMeta, Instruction},
+    solana_program_error::ProgramError,
+    solana_pubkey::Pubkey,
+};
+
+const TOKEN_ELF_PATH: &str = "../programs/token/src/elf/token.so";
+const ASSOCIATED_TOKEN_ELF_PATH: &str = "../programs/token/src/elf/associated_token.so";
+const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    solana_pubkey::pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+const TOKEN_PROGRAM_ID: Pubkey =
+    solana_pubkey::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+fn memo(program_id: &Pubkey, signed: bool) -> (Instruction, Vec<(Pubkey, Account)>) {
+    let signer = Pubkey::new_unique();
+    (
+        Instruction::new_with_bytes(
+            *program_id,
+            b"hello",
+            vec![AccountMeta::new_readonly(signer, signed)],
+        ),
+        vec![(signer, Account::default())],
+    )
+}
+
+// Set up the creation of an associated token account, which CPIs into the
+// token and system programs.
+fn create_associated_token_account(
+    mint_data: Vec<u8>,
+) -> (Mollusk, Instruction, Vec<(Pubkey, Account)>) {
+    let payer = Pubkey::new_unique();
+    let wallet = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let associated_token_address = Pubkey::find_program_address(
+        &[wallet.as_ref(), TOKEN_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0;
+
+    let token_elf = read_file(TOKEN_ELF_PATH);
+
+    let mut mollusk = Mollusk::default();
+    mollusk.add_program_with_loader_and_elf(
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+        &loader_keys::LOADER_V2,
+        &read_file(ASSOCIATED_TOKEN_ELF_PATH),
+    );
+    mollusk.add_program_with_loader_and_elf(&TOKEN_PROGRAM_ID, &loader_keys::LOADER_V2, &token_elf);
+
+    let instruction = Instruction::new_with_bytes(
+        ASSOCIATED_TOKEN_PROGRAM_ID,
+        &[],
+        vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(associated_token_address, false),
+            AccountMeta::new_readonly(wallet, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(solana_sdk_ids::system_program::id(), false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ],
+    );
+    let accounts = vec![
+        (
+            payer,
+            Account::new(10_000_000_000, 0, &solana_sdk_ids::system_program::id()),
+        ),
+        (associated_token_address, Account::default()),
+        (wallet, Account::default()),
+        (mint, {
+            let mut account = Account::new(1_000_000_000, 0, &TOKEN_PROGRAM_ID);
+            account.data = mint_data;
+            account
+        }),
+        keyed_account_for_system_program(),
+        (
+            TOKEN_PROGRAM_ID,
+            create_program_account_loader_v2(&token_elf),
+        ),
+    ];
+
+    (mollusk, instruction, accounts)
+}
+
+#[test]
+fn test_invocation_tree() {
+    let (mollusk, program_id) = memo_mollusk();
+    let (instruction, accounts) = memo(&program_id, true);
+
+    let result =
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    assert_eq!(result.invocations.len(), 1);
+    let invocation = &result.invocations[0];
+    assert_eq!(invocation.program_id, program_id);
+    assert_eq!(invocation.stack_height, 1);
+    assert_eq!(invocation.instruction, instruction);
+    assert_eq!(
+        invocation.compute_units_consumed,
+        Some(result.compute_units_consumed)
+    );
+    assert_eq!(invocation.program_result, ProgramResult::Success);
+    assert!(invocation.return_data.is_empty());
+    assert_eq!(
+        invocation.logs,
+        vec![
+            format!("Program log: Signed by {}", accounts[0].0),
+            "Program log: Memo (len 5): \"hello\"".to_string(),
+        ],
+    );
+    assert!(invocation.inner_invocations.is_empty());
+}
+
+#[test]
+fn test_invocation_tree_cpi() {
+    let (mollusk, instruction, accounts) = create_associated_token_account(mint_data());
+
+    let result =
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    let root = &result.invocations[0];
+    assert_eq!(root.program_id, ASSOCIATED_TOKEN_PROGRAM_ID);
+    assert_eq!(
+        root.logs,
+        vec![
+            "Program log: Create".to_string(),
+            "Program log: Initialize the associated token account".to_string(),
+        ],
+    );
+
+    // CPIs are nested under their caller, in the order they were invoked.
+    let inner_program_ids = root
+        .inner_invocations
+        .iter()
+        .map(|invocation| invocation.program_id)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        inner_program_ids,
+        vec![
+            TOKEN_PROGRAM_ID,
+            solana_sdk_ids::system_program::id(),
+            TOKEN_PROGRAM_ID,
+            TOKEN_PROGRAM_ID,
+        ],
+    );
+    for invocation in &root.inner_invocations {
+        assert_eq!(invocation.stack_height, 2);
+        assert_eq!(invocation.program_result, ProgramResult::Success);
+        assert!(invocation.inner_invocations.is_empty());
+    }
+
+    // The token program's `GetAccountDataSize` returns the account size.
+    let get_account_data_size = &root.inner_invocations[0];
+    assert_eq!(get_account_data_size.instruction.data[0], 21);
+    assert_eq!(get_account_data_size.return_data, 165u64.to_le_bytes());
+    let inner_compute_units = get_account_data_size.compute_units_consumed.unwrap();
+    assert!(inner_compute_units > 0);
+    assert!(inner_compute_units < root.compute_units_consumed.unwrap());
+
+    // Builtins don't report their compute units.
+    let create_account = &root.inner_invocations[1];
+    assert_eq!(create_account.compute_units_consumed, None);
+
+    // Instructions are resolved to their accounts' pubkeys.
+    let initialize_account = &root.inner_invocations[3];
+    assert_eq!(initialize_account.instruction.program_id, TOKEN_PROGRAM_ID);
+    assert_eq!(
+        initialize_account.instruction.accounts[0],
+        AccountMeta::new(instruction.accounts[1].pubkey, false),
+    );
+    assert_eq!(
+        initialize_account.instruction.accounts[1],
+        AccountMeta::new_readonly(instruction.accounts[3].pubkey, false),
+    );
+
+    assert_eq!(root.iter().count(), 5);
+}
+
+#[test]
+fn test_invocation_tree_cpi_failure() {
+    // An uninitialized mint fails the first CPI.
+    let (mollusk, instruction, accounts) = create_associated_token_account(vec![0; 82]);
+
+    let result = mollusk.process_instruction(&instruction, &accounts);
+
+    let error = ProgramResult::Failure(ProgramError::Custom(2));
+    assert_eq!(result.program_result, error);
+
+    let root = &result.invocations[0];
+    assert_eq!(root.program_result, error);
+    assert_eq!(root.inner_invocations.len(), 1);
+    let inner = &root.inner_invocations[0];
+    assert_eq!(inner.program_id, TOKEN_PROGRAM_ID);
+    assert_eq!(inner.program_result, error);
+    assert_eq!(
+        inner.logs,
+        vec!["Program log: Error: Invalid Mint".to_string()]
+    );
+
+    assert_eq!(
+        root.to_string(),
+        format!(
+            "{ASSOCIATED_TOKEN_PROGRAM_ID} [1] {} CU failed: Custom program error: 0x2\n  \
+             {TOKEN_PROGRAM_ID} [2] {} CU failed: Custom program error: 0x2\n",
+            root.compute_units_consumed.unwrap(),
+            inner.compute_units_consumed.unwrap(),
+        ),
+    );
+}
+
+#[test]
+fn test_invocation_tree_chain_and_transaction() {
+    let (mollusk, program_id) = memo_mollusk();
+    let (signed, signed_accounts) = memo(&program_id, true);
+    let (unsigned, unsigned_accounts) = memo(&program_id, false);
+    let accounts = [signed_accounts, unsigned_accounts].concat();
+
+    // Chains have a root per instruction.
+    let chain = mollusk.process_instruction_chain(&[signed.clone(), signed.clone()], &accounts);
+    assert_eq!(chain.invocations.len(), 2);
+    assert_eq!(chain.invocations[0], chain.invocations[1]);
+
+    // Transactions stop at the first failed instruction.
+    let transaction = mollusk.process_transaction_instructions(
+        &[signed.clone(), unsigned, signed],
+        &accounts,
+        None,
+    );
+    assert_eq!(transaction.invocations.len(), 2);
+    assert_eq!(
+        transaction.invocations[0].program_result,
+        ProgramResult::Success
+    );
+    assert_eq!(
+        transaction.invocations[1].program_result,
+        ProgramResult::Failure(ProgramError::MissingRequiredSignature),
+    );
+    assert_eq!(
+        transaction
+            .invocations
+            .iter()
+            .map(|invocation| invocation.compute_units_consumed.unwrap())
+            .sum::<u64>(),
+        transaction.compute_units_consumed,
+    );
+}
@@ -0,0 +1,250 @@
+//! Tests for in-harness BPF Loader v3 (Upgradeable) program management.
+
+use {
+    mollusk_svm::{
+        file::read_file,
+        program::{create_program_data_account_loader_v3, loader_keys},
+        result::Check,
+        Mollusk, MolluskContext,
+    },
+    solana_account::Account,
+    solana_instruction::{AccountMeta, Instruction},
+    solana_loader_v3_interface::{
+        instruction as loader_v3_instruction, state::UpgradeableLoaderState,
+    },
+    solana_program_error::ProgramError,
+    solana_pubkey::Pubkey,
+    solana_svm_feature_set::SVMFeatureSet,
+    std::collections::HashMap,
+};
+
+const MEMO_ELF_PATH: &str = "../programs/memo/src/elf/memo.so";
+const TOKEN_ELF_PATH: &str = "../programs/token/src/elf/token.so";
+
+// SPL Token's `InvalidInstruction` error.
+const TOKEN_INVALID_INSTRUCTION: u32 = 12;
+
+struct Setup {
+    context: MolluskContext<HashMap<Pubkey, Account>>,
+    program_id: Pubkey,
+    programdata_address: Pubkey,
+    authority: Pubkey,
+}
+
+fn system_account_with_lamports(lamports: u64) -> Account {
+    Account::new(lamports, 0, &solana_sdk_ids::system_program::id())
+}
+
+// A memo instruction with a non-signer account, which SPL Memo rejects.
+fn memo_instruction(program_id: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        b"hello",
+        vec![AccountMeta::new_readonly(Pubkey::new_unique(), false)],
+    )
+}
+
+fn buffer_account(elf: &[u8], authority: &Pubkey, mollusk: &Mollusk) -> Account {
+    let offset = UpgradeableLoaderState::size_of_buffer_metadata();
+    let mut data = vec![0; offset + elf.len()];
+    bincode::serialize_into(
+        &mut data[..offset],
+        &UpgradeableLoaderState::Buffer {
+            authority_address: Some(*authority),
+        },
+    )
+    .unwrap();
+    data[offset..].copy_from_slice(elf);
+    Account {
+        lamports: mollusk.sysvars.rent.minimum_balance(data.len()),
+        data,
+        owner: loader_keys::LOADER_V3,
+        ..Default::default()
+    }
+}
+
+// Program data accounts are sized to fit ELFs up to `max_elf_len`.
+// Struct update syntax on `Mollusk` doesn't compile with the `register-tracing`
+// feature, which adds a private field.
+#[allow(clippy::field_reassign_with_default)]
+fn setup(elf: &[u8], max_elf_len: usize) -> Setup {
+    let program_id = Pubkey::new_unique();
+    let programdata_address =
+        Pubkey::find_program_address(&[program_id.as_ref()], &loader_keys::LOADER_V3).0;
+    let authority = Pubkey::new_unique();
+
+    let mut mollusk = Mollusk::default();
+    // The SPL ELFs are SBPFv0.
+    mollusk.feature_set = SVMFeatureSet {
+        disable_sbpf_v0_v1_v2_deployment: false,
+        ..SVMFeatureSet::all_enabled()
+    };
+    mollusk.add_program_with_loader_and_elf(&program_id, &loader_keys::LOADER_V3, elf);
+
+    // Programs can't be modified in the slot they were deployed.
+    mollusk.warp_to_slot(1);
+
+    let mut programdata = create_program_data_account_loader_v3(elf);
+    programdata.data.resize(
+        UpgradeableLoaderState::size_of_programdata_metadata() + max_elf_len,
+        0,
+    );
+    programdata.lamports = mollusk.sysvars.rent.minimum_balance(programdata.data.len());
+    bincode::serialize_into(
+        &mut programdata.data[..],
+        &UpgradeableLoaderState::ProgramData {
+            slot: 0,
+            upgrade_authority_address: Some(authority),
+        },
+    )
+    .unwrap();
+
+    let mut account_store = HashMap::new();
+    account_store.insert(programdata_address, programdata);
+    account_store.insert(authority, system_account_with_lamports(10_000_000_000));
+
+    Setup {
+        context: mollusk.with_context(account_store),
+        program_id,
+        programdata_address,
+        authority,
+    }
+}
+
+#[test]
+fn test_upgrade() {
+    let memo_elf = read_file(MEMO_ELF_PATH);
+    let token_elf = read_file(TOKEN_ELF_PATH);
+
+    let Setup {
+        context,
+        program_id,
+        authority,
+        ..
+    } = setup(&memo_elf, token_elf.len());
+
+    context.process_and_validate_instruction(
+        &memo_instruction(&program_id),
+        &[Check::err(ProgramError::MissingRequiredSignature)],
+    );
+
+    let buffer = Pubkey::new_unique();
+    context.account_store.borrow_mut().insert(
+        buffer,
+        buffer_account(&token_elf, &authority, &context.mollusk),
+    );
+
+    context.process_and_validate_instruction(
+        &loader_v3_instruction::upgrade(&program_id, &buffer, &authority, &authority),
+        &[Check::success()],
+    );
+
+    // The upgraded program is executed.
+    context.process_and_validate_instruction(
+        &memo_instruction(&program_id),
+        &[Check::err(ProgramError::Custom(TOKEN_INVALID_INSTRUCTION))],
+    );
+    assert!(context
+        .mollusk
+        .program_cache
+        .get_program_elf_bytes(&program_id)
+        .unwrap()
+        .starts_with(&token_elf));
+}
+
+#[test]
+fn test_failed_upgrade_is_not_applied() {
+    let memo_elf = read_file(MEMO_ELF_PATH);
+    let token_elf = read_file(TOKEN_ELF_PATH);
+
+    let Setup {
+        context,
+        program_id,
+        authority,
+        ..
+    } = setup(&memo_elf, token_elf.len());
+
+    // The buffer's authority doesn't match the upgrade authority.
+    let buffer = Pubkey::new_unique();
+    context.account_store.borrow_mut().insert(
+        buffer,
+        buffer_account(&token_elf, &Pubkey::new_unique(), &context.mollusk),
+    );
+
+    let result = context.process_instruction(&loader_v3_instruction::upgrade(
+        &program_id,
+        &buffer,
+        &authority,
+        &authority,
+    ));
+    assert!(result.program_result.is_err());
+
+    context.process_and_validate_instruction(
+        &memo_instruction(&program_id),
+        &[Check::err(ProgramError::MissingRequiredSignature)],
+    );
+}
+
+#[test]
+fn test_extend() {
+    let memo_elf = read_file(MEMO_ELF_PATH);
+
+    let Setup {
+        context,
+        program_id,
+        authority,
+        ..
+    } = setup(&memo_elf, memo_elf.len());
+
+    let additional_bytes = 10 * 1024;
+
+    context.process_and_validate_instruction(
+        &loader_v3_instruction::extend_program(&program_id, Some(&authority), additional_bytes),
+        &[Check::success()],
+    );
+
+    assert_eq!(
+        context
+            .mollusk
+            .program_cache
+            .get_program_elf_bytes(&program_id)
+            .unwrap()
+            .len(),
+        memo_elf.len() + additional_bytes as usize,
+    );
+    context.process_and_validate_instruction(
+        &memo_instruction(&program_id),
+        &[Check::err(ProgramError::MissingRequiredSignature)],
+    );
+}
+
+#[test]
+fn test_close() {
+    let memo_elf = read_file(MEMO_ELF_PATH);
+
+    let Setup {
+        context,
+        program_id,
+        programdata_address,
+        authority,
+    } = setup(&memo_elf, memo_elf.len());
+
+    context.process_and_validate_instruction(
+        &loader_v3_instruction::close_any(
+            &programdata_address,
+            &authority,
+            Some(&authority),
+            Some(&program_id),
+        ),
+        &[Check::success()],
+    );
+
+    // The closed program is evicted.
+    assert!(context
+        .mollusk
+        .program_cache
+        .get_program_elf_bytes(&program_id)
+        .is_none());
+    let result = context.process_instruction(&memo_instruction(&program_id));
+    assert!(result.program_result.is_err());
+}
@@ -0,0 +1,224 @@
+//! Tests for Loader v4 program support.
+
+use {
+    mollusk_svm::{
+        file::read_file,
+        program::{
+            create_program_account_loader_v4, create_program_account_loader_v4_with_status,
+            loader_keys,
+        },
+        result::Check,
+        Mollusk, MolluskContext,
+    },
+    solana_account::Account,
+    solana_instruction::{AccountMeta, Instruction},
+    solana_loader_v4_interface::{instruction as loader_v4_instruction, state::LoaderV4Status},
+    solana_program_error::ProgramError,
+    solana_pubkey::Pubkey,
+    solana_svm_feature_set::SVMFeatureSet,
+    std::collections::HashMap,
+};
+
+const MEMO_ELF_PATH: &str = "../programs/memo/src/elf/memo.so";
+
+fn system_account_with_lamports(lamports: u64) -> Account {
+    Account::new(lamports, 0, &solana_sdk_ids::system_program::id())
+}
+
+// A memo instruction with a non-signer account, which SPL Memo rejects.
+fn memo_instruction(program_id: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        b"hello",
+        vec![AccountMeta::new_readonly(Pubkey::new_unique(), false)],
+    )
+}
+
+// Struct update syntax on `Mollusk` doesn't compile with the `register-tracing`
+// feature, which adds a private field.
+#[allow(clippy::field_reassign_with_default)]
+fn mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::default();
+    // The SPL ELFs are SBPFv0.
+    mollusk.feature_set = SVMFeatureSet {
+        disable_sbpf_v0_v1_v2_deployment: false,
+        ..SVMFeatureSet::all_enabled()
+    };
+    mollusk
+}
+
+// A context with a retracted memo program, owned by `authority`.
+fn setup_retracted(elf: &[u8]) -> (MolluskContext<HashMap<Pubkey, Account>>, Pubkey, Pubkey) {
+    let program_id = Pubkey::new_unique();
+    let authority = Pubkey::new_unique();
+
+    let mut account_store = HashMap::new();
+    account_store.insert(
+        program_id,
+        create_program_account_loader_v4_with_status(elf, &authority, LoaderV4Status::Retracted),
+    );
+    account_store.insert(authority, system_account_with_lamports(10_000_000_000));
+
+    (mollusk().with_context(account_store), program_id, authority)
+}
+
+#[test]
+fn test_add_program_with_loader_v4() {
+    let memo_elf = read_file(MEMO_ELF_PATH);
+    let program_id = Pubkey::new_unique();
+
+    let mut mollusk = mollusk();
+    mollusk.add_program_with_loader_and_elf(&program_id, &loader_keys::LOADER_V4, &memo_elf);
+
+    let instruction = memo_instruction(&program_id);
+    let account = (instruction.accounts[0].pubkey, Account::default());
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        std::slice::from_ref(&account),
+        &[Check::err(ProgramError::MissingRequiredSignature)],
+    );
+
+    // Program accounts can also be provided explicitly.
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &[
+            (program_id, create_program_account_loader_v4(&memo_elf)),
+            account,
+        ],
+        &[Check::err(ProgramError::MissingRequiredSignature)],
+    );
+}
+
+#[test]
+fn test_deploy_and_retract() {
+    let memo_elf = read_file(MEMO_ELF_PATH);
+    let (mut context, program_id, authority) = setup_retracted(&memo_elf);
+
+    context.mollusk.warp_to_slot(1);
+    context.process_and_validate_instruction(
+        &loader_v4_instruction::deploy(&program_id, &authority),
+        &[Check::success()],
+    );
+
+    // The deployed program is executed.
+    context.process_and_validate_instruction(
+        &memo_instruction(&program_id),
+        &[Check::err(ProgramError::MissingRequiredSignature)],
+    );
+    assert_eq!(
+        context
+            .mollusk
+            .program_cache
+            .get_program_elf_bytes(&program_id)
+            .unwrap(),
+        memo_elf,
+    );
+
+    // Programs can't be retracted in the slot they were deployed.
+    let result =
+        context.process_instruction(&loader_v4_instruction::retract(&program_id, &authority));
+    assert!(result.program_result.is_err());
+
+    context.mollusk.warp_to_slot(2);
+    context.process_and_validate_instruction(
+        &loader_v4_instruction::retract(&program_id, &authority),
+        &[Check::success()],
+    );
+
+    // The retracted program is evicted.
+    assert!(context
+        .mollusk
+        .program_cache
+        .get_program_elf_bytes(&program_id)
+        .is_none());
+    let result = context.process_instruction(&memo_instruction(&program_id));
+    assert!(result.program_result.is_err());
+}
+
+#[test]
+fn test_deploy_with_mismatched_clock() {
+    let memo_elf = read_file(MEMO_ELF_PATH);
+    let program_id = Pubkey::new_unique();
+    let authority = Pubkey::new_unique();
+
+    // A provided clock sysvar account disagrees with the program cache's slot.
+    let mollusk = mollusk();
+    let (clock_key, mut clock_account) = mollusk.sysvars.keyed_account_for_clock_sysvar();
+    let mut clock = mollusk.sysvars.clock.clone();
+    clock.slot = 5;
+    clock_account.data = bincode::serialize(&clock).unwrap();
+
+    mollusk.process_and_validate_instruction(
+        &loader_v4_instruction::deploy(&program_id, &authority),
+        &[
+            (
+                program_id,
+                create_program_account_loader_v4_with_status(
+                    &memo_elf,
+                    &authority,
+                    LoaderV4Status::Retracted,
+                ),
+            ),
+            (authority, system_account_with_lamports(10_000_000_000)),
+            (clock_key, clock_account),
+        ],
+        &[Check::err(ProgramError::InvalidArgument)],
+    );
+}
+
+#[test]
+fn test_transfer_authority() {
+    let memo_elf = read_file(MEMO_ELF_PATH);
+    let (context, program_id, authority) = setup_retracted(&memo_elf);
+
+    let new_authority = Pubkey::new_unique();
+    context
+        .account_store
+        .borrow_mut()
+        .insert(new_authority, system_account_with_lamports(10_000_000_000));
+
+    context.process_and_validate_instruction(
+        &loader_v4_instruction::transfer_authority(&program_id, &authority, &new_authority),
+        &[Check::success()],
+    );
+
+    // Only the new authority can deploy.
+    context.process_and_validate_instruction(
+        &loader_v4_instruction::deploy(&program_id, &authority),
+        &[Check::err(ProgramError::IncorrectAuthority)],
+    );
+    context.process_and_validate_instruction(
+        &loader_v4_instruction::deploy(&program_id, &new_authority),
+        &[Check::success()],
+    );
+    context.process_and_validate_instruction(
+        &memo_instruction(&program_id),
+        &[Check::err(ProgramError::MissingRequiredSignature)],
+    );
+}
+
+#[test]
+fn test_finalized_program_is_immutable() {
+    let memo_elf = read_file(MEMO_ELF_PATH);
+    let program_id = Pubkey::new_unique();
+    let authority = Pubkey::new_unique();
+
+    let mut mollusk = mollusk();
+    mollusk.warp_to_slot(1);
+    mollusk.process_and_validate_instruction(
+        &loader_v4_instruction::retract(&program_id, &authority),
+        &[
+            (
+                program_id,
+                create_program_account_loader_v4_with_status(
+                    &memo_elf,
+                    &program_id,
+                    LoaderV4Status::Finalized,
+                ),
+            ),
+            (authority, system_account_with_lamports(10_000_000_000)),
+        ],
+        &[Check::err(ProgramError::IncorrectAuthority)],
+    );
+}
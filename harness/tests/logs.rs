@@ -0,0 +1,184 @@
+mod common;
+
+use {
+    common::{memo_mollusk, signed_memo},
+    mollusk_svm::{
+        result::{Check, Compare, Config},
+        Mollusk,
+    },
+    solana_account::Account,
+    solana_instruction::Instruction,
+    solana_pubkey::Pubkey,
+    solana_svm_log_collector::LogCollector,
+};
+
+fn transfer() -> (Instruction, Vec<(Pubkey, Account)>) {
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    (
+        solana_system_interface::instruction::transfer(&sender, &recipient, 42_000),
+        vec![
+            (
+                sender,
+                Account::new(100_000_000, 0, &solana_sdk_ids::system_program::id()),
+            ),
+            (
+                recipient,
+                Account::new(100_000_000, 0, &solana_sdk_ids::system_program::id()),
+            ),
+        ],
+    )
+}
+
+#[test]
+fn test_logs() {
+    let (mollusk, program_id) = memo_mollusk();
+    let (instruction, accounts) = signed_memo(&program_id, b"hello");
+    let signer = accounts[0].0;
+
+    let result = mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[Check::success(), Check::logs_contain("Memo (len 5)")],
+    );
+
+    assert_eq!(
+        result.logs,
+        vec![
+            format!("Program {program_id} invoke [1]"),
+            format!("Program log: Signed by {signer}"),
+            "Program log: Memo (len 5): \"hello\"".to_string(),
+            format!(
+                "Program {program_id} consumed {} of 1400000 compute units",
+                result.compute_units_consumed,
+            ),
+            format!("Program {program_id} success"),
+        ],
+    );
+}
+
+#[test]
+fn test_no_logs() {
+    let mollusk = Mollusk::default();
+    let (instruction, accounts) = transfer();
+
+    // The runtime's own messages don't count as logs.
+    let result = mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[Check::success(), Check::no_logs()],
+    );
+    assert!(!result.logs.is_empty());
+}
+
+#[test]
+fn test_log_checks_fail() {
+    let (mollusk, program_id) = memo_mollusk();
+    let (instruction, accounts) = signed_memo(&program_id, b"hello");
+    let result = mollusk.process_instruction(&instruction, &accounts);
+
+    let config = Config {
+        panic: false,
+        verbose: false,
+    };
+    assert!(!result.run_checks(&[Check::logs_contain("goodbye")], &config, &mollusk));
+    assert!(!result.run_checks(&[Check::no_logs()], &config, &mollusk));
+}
+
+#[test]
+#[cfg(feature = "regex")]
+fn test_log_matches() {
+    let (mollusk, program_id) = memo_mollusk();
+    let (instruction, accounts) = signed_memo(&program_id, b"hello");
+    let result = mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[
+            Check::success(),
+            Check::log_matches(r"^Program log: Signed by \w+$"),
+        ],
+    );
+
+    let config = Config {
+        panic: false,
+        verbose: false,
+    };
+    assert!(!result.run_checks(&[Check::log_matches(r"^Memo")], &config, &mollusk));
+    assert!(!result.run_checks(&[Check::log_matches(r"(")], &config, &mollusk));
+}
+
+#[test]
+fn test_logs_chain_and_transaction() {
+    let (mollusk, program_id) = memo_mollusk();
+    let (instruction, accounts) = signed_memo(&program_id, b"hello");
+    let single = mollusk.process_instruction(&instruction, &accounts);
+
+    // Chains and transactions collect the logs of every instruction.
+    let chain = mollusk.process_instruction_chain(&[instruction.clone(), instruction], &accounts);
+    assert_eq!(chain.logs.len(), 2 * single.logs.len());
+
+    let (instruction, accounts) = signed_memo(&program_id, b"hello");
+    let transaction = mollusk.process_transaction_instructions(
+        &[instruction.clone(), instruction],
+        &accounts,
+        None,
+    );
+    assert_eq!(transaction.logs.len(), 2 * single.logs.len());
+    assert_eq!(
+        transaction
+            .logs
+            .iter()
+            .filter(|message| message.starts_with("Program log: Memo"))
+            .count(),
+        2,
+    );
+}
+
+#[test]
+fn test_logs_forwarded_to_logger() {
+    let (mut mollusk, program_id) = memo_mollusk();
+    let logger = LogCollector::new_ref();
+    mollusk.logger = Some(logger.clone());
+
+    let (instruction, accounts) = signed_memo(&program_id, b"hello");
+    let first = mollusk.process_instruction(&instruction, &accounts);
+    let second = mollusk.process_instruction(&instruction, &accounts);
+
+    // Each result only holds its own logs, while the logger accumulates them.
+    assert_eq!(first.logs.len(), second.logs.len());
+    assert_eq!(
+        logger.borrow().get_recorded_content(),
+        [first.logs, second.logs].concat(),
+    );
+}
+
+#[test]
+fn test_logger_bytes_limit() {
+    let (mut mollusk, program_id) = memo_mollusk();
+    let logger = LogCollector::new_ref_with_limit(Some(1));
+    mollusk.logger = Some(logger.clone());
+
+    // The logger's limit applies to the captured logs too.
+    let (instruction, accounts) = signed_memo(&program_id, b"hello");
+    let result = mollusk.process_instruction(&instruction, &accounts);
+    assert_eq!(result.logs, vec!["Log truncated".to_string()]);
+    assert_eq!(logger.borrow().get_recorded_content(), result.logs);
+}
+
+#[test]
+fn test_compare_logs() {
+    let (mollusk, program_id) = memo_mollusk();
+    let (instruction, accounts) = signed_memo(&program_id, b"hello");
+    let result_a = mollusk.process_instruction(&instruction, &accounts);
+    let result_b = mollusk.process_instruction(&instruction, &accounts);
+
+    let config = Config {
+        panic: false,
+        verbose: false,
+    };
+    assert!(result_a.compare_with_config(&result_b, &[Compare::Logs], &config));
+
+    let (instruction, accounts) = signed_memo(&program_id, b"goodbye");
+    let result_c = mollusk.process_instruction(&instruction, &accounts);
+    assert!(!result_a.compare_with_config(&result_c, &[Compare::Logs], &config));
+}
@@ -0,0 +1,171 @@
+use {
+    mollusk_svm::{
+        file::read_file, program::create_keyed_account_for_builtin_program, result::Check, Mollusk,
+    },
+    solana_account::Account,
+    solana_instruction::{error::InstructionError, AccountMeta, Instruction},
+    solana_program_error::ProgramError,
+    solana_pubkey::Pubkey,
+    std::{cell::Cell, rc::Rc},
+};
+
+const ASSOCIATED_TOKEN_ELF_PATH: &str = "../programs/token/src/elf/associated_token.so";
+const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    solana_pubkey::pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+const TOKEN_PROGRAM_ID: Pubkey =
+    solana_pubkey::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+// SPL Token instruction discriminators and sizes used by the ATA program.
+const INITIALIZE_ACCOUNT_3: u8 = 18;
+const GET_ACCOUNT_DATA_SIZE: u8 = 21;
+const INITIALIZE_IMMUTABLE_OWNER: u8 = 22;
+const TOKEN_ACCOUNT_LEN: u64 = 165;
+
+#[test]
+fn test_mock_program() {
+    let program_id = Pubkey::new_unique();
+    let key = Pubkey::new_unique();
+
+    let mut mollusk = Mollusk::default();
+    mollusk.add_mock_program(&program_id, |mut ctx| {
+        let data = ctx.instruction_data.to_vec();
+        ctx.accounts[0].account.data = data;
+        ctx.set_return_data(b"mocked");
+        ctx.log("Hello from a mock!");
+        Ok(())
+    });
+
+    mollusk.process_and_validate_instruction(
+        &Instruction::new_with_bytes(program_id, &[1, 2, 3], vec![AccountMeta::new(key, false)]),
+        &[(key, Account::new(1_000_000_000, 3, &program_id))],
+        &[
+            Check::success(),
+            Check::return_data(b"mocked"),
+            Check::account(&key).data(&[1, 2, 3]).build(),
+        ],
+    );
+}
+
+#[test]
+fn test_mock_program_error() {
+    let program_id = Pubkey::new_unique();
+    let key = Pubkey::new_unique();
+
+    let mut mollusk = Mollusk::default();
+    mollusk.add_mock_program(&program_id, |ctx| {
+        ctx.accounts[0].account.data = vec![9; 3];
+        Err(ProgramError::Custom(42))
+    });
+
+    // Changes made by failing mocks aren't applied.
+    mollusk.process_and_validate_instruction(
+        &Instruction::new_with_bytes(program_id, &[], vec![AccountMeta::new(key, false)]),
+        &[(key, Account::new(1_000_000_000, 3, &program_id))],
+        &[
+            Check::err(ProgramError::Custom(42)),
+            Check::account(&key).data(&[0; 3]).build(),
+        ],
+    );
+}
+
+#[test]
+fn test_mock_program_readonly_account() {
+    let program_id = Pubkey::new_unique();
+    let key = Pubkey::new_unique();
+
+    let mut mollusk = Mollusk::default();
+    mollusk.add_mock_program(&program_id, |ctx| {
+        ctx.accounts[0].account.data = vec![9; 3];
+        Ok(())
+    });
+
+    // Mocks are subject to the runtime's account modification rules.
+    mollusk.process_and_validate_instruction(
+        &Instruction::new_with_bytes(program_id, &[], vec![AccountMeta::new_readonly(key, false)]),
+        &[(key, Account::new(1_000_000_000, 3, &program_id))],
+        &[Check::instruction_err(
+            InstructionError::ReadonlyDataModified,
+        )],
+    );
+}
+
+#[test]
+fn test_cpi_to_mock_program() {
+    let token_program_id = TOKEN_PROGRAM_ID;
+    let payer = Pubkey::new_unique();
+    let wallet = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let associated_token_address = Pubkey::find_program_address(
+        &[wallet.as_ref(), token_program_id.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0;
+
+    let mut mollusk = Mollusk::default();
+    mollusk.add_program_with_loader_and_elf(
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+        &mollusk_svm::program::loader_keys::LOADER_V3,
+        &read_file(ASSOCIATED_TOKEN_ELF_PATH),
+    );
+
+    // Stand in for the token program, which the ATA program CPIs into.
+    let invocations = Rc::new(Cell::new(0));
+    mollusk.add_mock_program(&token_program_id, {
+        let invocations = Rc::clone(&invocations);
+        move |mut ctx| {
+            invocations.set(invocations.get() + 1);
+            match ctx.instruction_data.first() {
+                Some(&GET_ACCOUNT_DATA_SIZE) => {
+                    ctx.set_return_data(&TOKEN_ACCOUNT_LEN.to_le_bytes());
+                }
+                Some(&INITIALIZE_IMMUTABLE_OWNER) => (),
+                Some(&INITIALIZE_ACCOUNT_3) => {
+                    ctx.accounts[0].account.data[..32].copy_from_slice(mint.as_ref());
+                }
+                _ => return Err(ProgramError::InvalidInstructionData),
+            }
+            Ok(())
+        }
+    });
+
+    let instruction = Instruction::new_with_bytes(
+        ASSOCIATED_TOKEN_PROGRAM_ID,
+        &[],
+        vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(associated_token_address, false),
+            AccountMeta::new_readonly(wallet, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(solana_sdk_ids::system_program::id(), false),
+            AccountMeta::new_readonly(token_program_id, false),
+        ],
+    );
+
+    let result = mollusk.process_and_validate_instruction(
+        &instruction,
+        &[
+            (
+                payer,
+                Account::new(10_000_000_000, 0, &solana_sdk_ids::system_program::id()),
+            ),
+            (associated_token_address, Account::default()),
+            (wallet, Account::default()),
+            (mint, Account::new(1_000_000_000, 82, &token_program_id)),
+            mollusk_svm::program::keyed_account_for_system_program(),
+            create_keyed_account_for_builtin_program(&token_program_id, "mock_program"),
+        ],
+        &[
+            Check::success(),
+            Check::account(&associated_token_address)
+                .owner(&token_program_id)
+                .space(TOKEN_ACCOUNT_LEN as usize)
+                .build(),
+        ],
+    );
+
+    assert_eq!(invocations.get(), 3);
+    assert_eq!(
+        &result.get_account(&associated_token_address).unwrap().data[..32],
+        mint.as_ref(),
+    );
+}
@@ -0,0 +1,206 @@
+use {
+    mollusk_svm::{
+        program::{create_keyed_account_for_builtin_program, keyed_account_for_system_program},
+        result::Check,
+        Mollusk,
+    },
+    solana_account::Account,
+    solana_account_info::AccountInfo,
+    solana_instruction::{error::InstructionError, AccountMeta, Instruction},
+    solana_program_error::{ProgramError, ProgramResult},
+    solana_pubkey::Pubkey,
+    solana_sysvar::program_stubs,
+};
+
+const CALLEE_PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+const VAULT_SEED: &[u8] = b"vault";
+
+// Resizes the first account to fit the instruction data and writes it there.
+fn process_write(_program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let account = &accounts[0];
+    if !account.is_writable {
+        return Err(ProgramError::InvalidArgument);
+    }
+    account.resize(data.len())?;
+    account.try_borrow_mut_data()?.copy_from_slice(data);
+    program_stubs::sol_log("Wrote data");
+    program_stubs::sol_set_return_data(b"written");
+    Ok(())
+}
+
+fn process_fail(_program_id: &Pubkey, accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    accounts[0].try_borrow_mut_data()?.fill(9);
+    Err(ProgramError::Custom(42))
+}
+
+// Transfers lamports to the recipient from both the payer and the program's
+// vault PDA via CPI to the System program.
+fn process_transfer(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [payer, vault, recipient, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    let lamports = u64::from_le_bytes(data.try_into().unwrap());
+    let (_, bump) = Pubkey::find_program_address(&[VAULT_SEED], program_id);
+
+    program_stubs::sol_invoke_signed(
+        &solana_system_interface::instruction::transfer(payer.key, recipient.key, lamports),
+        accounts,
+        &[],
+    )?;
+    program_stubs::sol_invoke_signed(
+        &solana_system_interface::instruction::transfer(vault.key, recipient.key, lamports),
+        accounts,
+        &[&[VAULT_SEED, &[bump]]],
+    )?;
+
+    // The caller observes the results of the CPIs.
+    if recipient.lamports() != 2 * lamports {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+// Returns the stack height it was invoked at.
+fn process_callee(_program_id: &Pubkey, _accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    program_stubs::sol_set_return_data(&program_stubs::sol_get_stack_height().to_le_bytes());
+    Ok(())
+}
+
+// Invokes the callee and writes its return data into the first account.
+fn process_caller(_program_id: &Pubkey, accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    program_stubs::sol_invoke_signed(
+        &Instruction::new_with_bytes(CALLEE_PROGRAM_ID, &[], vec![]),
+        accounts,
+        &[],
+    )?;
+    let (program_id, return_data) = program_stubs::sol_get_return_data().unwrap();
+    if program_id != CALLEE_PROGRAM_ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    accounts[0]
+        .try_borrow_mut_data()?
+        .copy_from_slice(&return_data);
+    Ok(())
+}
+
+#[test]
+fn test_native_program() {
+    let program_id = Pubkey::new_unique();
+    let key = Pubkey::new_unique();
+
+    let mut mollusk = Mollusk::default();
+    mollusk.add_native_program(&program_id, process_write);
+
+    let instruction = Instruction::new_with_bytes(
+        program_id,
+        &[1, 2, 3, 4, 5],
+        vec![AccountMeta::new(key, false)],
+    );
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &[(key, Account::new(1_000_000_000, 3, &program_id))],
+        &[
+            Check::success(),
+            Check::return_data(b"written"),
+            Check::account(&key).data(&[1, 2, 3, 4, 5]).build(),
+        ],
+    );
+
+    // Native programs are subject to the runtime's account modification
+    // rules.
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &[(key, Account::new(1_000_000_000, 3, &Pubkey::new_unique()))],
+        &[Check::instruction_err(
+            InstructionError::ExternalAccountDataModified,
+        )],
+    );
+}
+
+#[test]
+fn test_native_program_error() {
+    let program_id = Pubkey::new_unique();
+    let key = Pubkey::new_unique();
+
+    let mut mollusk = Mollusk::default();
+    mollusk.add_native_program(&program_id, process_fail);
+
+    // Changes made by failing programs aren't applied.
+    mollusk.process_and_validate_instruction(
+        &Instruction::new_with_bytes(program_id, &[], vec![AccountMeta::new(key, false)]),
+        &[(key, Account::new(1_000_000_000, 3, &program_id))],
+        &[
+            Check::err(ProgramError::Custom(42)),
+            Check::account(&key).data(&[0; 3]).build(),
+        ],
+    );
+}
+
+#[test]
+fn test_native_program_cpi() {
+    let program_id = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let vault = Pubkey::find_program_address(&[VAULT_SEED], &program_id).0;
+    let recipient = Pubkey::new_unique();
+    let lamports = 1_000_000u64;
+
+    let mut mollusk = Mollusk::default();
+    mollusk.add_native_program(&program_id, process_transfer);
+
+    let system_account =
+        |lamports| Account::new(lamports, 0, &solana_sdk_ids::system_program::id());
+
+    mollusk.process_and_validate_instruction(
+        &Instruction::new_with_bytes(
+            program_id,
+            &lamports.to_le_bytes(),
+            vec![
+                AccountMeta::new(payer, true),
+                AccountMeta::new(vault, false),
+                AccountMeta::new(recipient, false),
+                AccountMeta::new_readonly(solana_sdk_ids::system_program::id(), false),
+            ],
+        ),
+        &[
+            (payer, system_account(10_000_000)),
+            (vault, system_account(10_000_000)),
+            (recipient, Account::default()),
+            keyed_account_for_system_program(),
+        ],
+        &[
+            Check::success(),
+            Check::account(&payer).lamports(9_000_000).build(),
+            Check::account(&vault).lamports(9_000_000).build(),
+            Check::account(&recipient).lamports(2 * lamports).build(),
+        ],
+    );
+}
+
+#[test]
+fn test_native_program_cpi_to_native_program() {
+    let program_id = Pubkey::new_unique();
+    let key = Pubkey::new_unique();
+
+    let mut mollusk = Mollusk::default();
+    mollusk.add_native_program(&program_id, process_caller);
+    mollusk.add_native_program(&CALLEE_PROGRAM_ID, process_callee);
+
+    mollusk.process_and_validate_instruction(
+        &Instruction::new_with_bytes(
+            program_id,
+            &[],
+            vec![
+                AccountMeta::new(key, false),
+                AccountMeta::new_readonly(CALLEE_PROGRAM_ID, false),
+            ],
+        ),
+        &[
+            (key, Account::new(1_000_000_000, 8, &program_id)),
+            create_keyed_account_for_builtin_program(&CALLEE_PROGRAM_ID, "native_program"),
+        ],
+        &[
+            Check::success(),
+            Check::account(&key).data(&2u64.to_le_bytes()).build(),
+        ],
+    );
+}
@@ -0,0 +1,195 @@
+#![cfg(feature = "profiler")]
+
+mod common;
+
+use {
+    common::{mint_data, signed_memo},
+    mollusk_svm::{profiler::ProfilingCallback, result::Check, Mollusk},
+    solana_account::Account,
+    solana_instruction::{AccountMeta, Instruction},
+    solana_pubkey::Pubkey,
+    std::{cell::RefCell, rc::Rc},
+};
+
+// Program names are resolved relative to the working directory.
+const MEMO_PROGRAM_NAME: &str = "../programs/memo/src/elf/memo";
+const TOKEN_PROGRAM_NAME: &str = "../programs/token/src/elf/token";
+const ASSOCIATED_TOKEN_PROGRAM_NAME: &str = "../programs/token/src/elf/associated_token";
+const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    solana_pubkey::pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+const TOKEN_PROGRAM_ID: Pubkey =
+    solana_pubkey::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+fn profiled_mollusk(
+    program_id: &Pubkey,
+    program_name: &str,
+    output_dir: Option<String>,
+) -> (Mollusk, Rc<RefCell<Vec<mollusk_svm::profiler::Profile>>>) {
+    let mut mollusk = Mollusk::new_debuggable(program_id, program_name, true);
+    let callback = ProfilingCallback::new(output_dir);
+    let profiles = Rc::clone(&callback.profiles);
+    mollusk.invocation_inspect_callback = Box::new(callback);
+    (mollusk, profiles)
+}
+
+#[test]
+fn test_profile_instruction() {
+    let program_id = Pubkey::new_unique();
+    let (mollusk, profiles) = profiled_mollusk(&program_id, MEMO_PROGRAM_NAME, None);
+
+    let (instruction, accounts) = signed_memo(&program_id, b"hello");
+    let result =
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    let profiles = profiles.borrow();
+    assert_eq!(profiles.len(), 1);
+    let profile = &profiles[0];
+    assert_eq!(profile.program_id, program_id);
+
+    // Syscall charges aren't attributed, so the profile accounts for less
+    // than the total.
+    let total = profile.total_compute_units();
+    assert!(total > 0);
+    assert!(total < result.compute_units_consumed);
+
+    // Every stack starts at the program's entrypoint.
+    for stack in profile.stacks.keys() {
+        assert_eq!(stack[0], program_id.to_string());
+        assert_eq!(stack[1], "entrypoint");
+    }
+    assert!(profile
+        .stacks
+        .keys()
+        .any(|stack| stack.last().unwrap() == "sol_log_"));
+
+    // The entrypoint is included in every stack, and calls other functions.
+    let functions = profile.functions();
+    let entrypoint = functions
+        .iter()
+        .find(|function| function.name == "entrypoint")
+        .unwrap();
+    assert_eq!(entrypoint.total_compute_units, total);
+    assert!(entrypoint.self_compute_units < total);
+    assert!(functions
+        .iter()
+        .any(|function| function.name.starts_with("function_")));
+}
+
+#[test]
+fn test_profile_each_instruction() {
+    let program_id = Pubkey::new_unique();
+    let (mollusk, profiles) = profiled_mollusk(&program_id, MEMO_PROGRAM_NAME, None);
+
+    // Each top-level instruction is profiled separately.
+    let (instruction, accounts) = signed_memo(&program_id, b"hello");
+    mollusk.process_transaction_instructions(&[instruction.clone(), instruction], &accounts, None);
+
+    let profiles = profiles.borrow();
+    assert_eq!(profiles.len(), 2);
+    assert_eq!(profiles[0], profiles[1]);
+}
+
+#[test]
+fn test_profile_cpi() {
+    let payer = Pubkey::new_unique();
+    let wallet = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let associated_token_address = Pubkey::find_program_address(
+        &[wallet.as_ref(), TOKEN_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0;
+
+    let (mut mollusk, profiles) = profiled_mollusk(
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+        ASSOCIATED_TOKEN_PROGRAM_NAME,
+        None,
+    );
+    mollusk.add_program(&TOKEN_PROGRAM_ID, TOKEN_PROGRAM_NAME);
+
+    let instruction = Instruction::new_with_bytes(
+        ASSOCIATED_TOKEN_PROGRAM_ID,
+        &[],
+        vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(associated_token_address, false),
+            AccountMeta::new_readonly(wallet, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(solana_sdk_ids::system_program::id(), false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ],
+    );
+    let accounts = [
+        (
+            payer,
+            Account::new(10_000_000_000, 0, &solana_sdk_ids::system_program::id()),
+        ),
+        (associated_token_address, Account::default()),
+        (wallet, Account::default()),
+        (mint, {
+            let mut account = Account::new(1_000_000_000, 0, &TOKEN_PROGRAM_ID);
+            account.data = mint_data();
+            account
+        }),
+        mollusk_svm::program::keyed_account_for_system_program(),
+        (
+            TOKEN_PROGRAM_ID,
+            mollusk_svm::program::create_program_account_loader_v3(&TOKEN_PROGRAM_ID),
+        ),
+    ];
+
+    mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    let profiles = profiles.borrow();
+    assert_eq!(profiles.len(), 1);
+
+    // The token program's stacks are nested under the invoke syscall that
+    // invoked it.
+    let token_stacks = profiles[0]
+        .stacks
+        .keys()
+        .filter(|stack| stack.contains(&TOKEN_PROGRAM_ID.to_string()))
+        .collect::<Vec<_>>();
+    assert!(!token_stacks.is_empty());
+    for stack in token_stacks {
+        assert_eq!(stack[0], ASSOCIATED_TOKEN_PROGRAM_ID.to_string());
+        let position = stack
+            .iter()
+            .position(|frame| *frame == TOKEN_PROGRAM_ID.to_string())
+            .unwrap();
+        assert_eq!(stack[position - 1], "sol_invoke_signed_rust");
+        assert_eq!(stack[position + 1], "entrypoint");
+    }
+}
+
+#[test]
+fn test_profile_output() {
+    let output_dir = std::env::temp_dir().join(format!("mollusk-profile-{}", std::process::id()));
+    let program_id = Pubkey::new_unique();
+    let (mollusk, profiles) = profiled_mollusk(
+        &program_id,
+        MEMO_PROGRAM_NAME,
+        Some(output_dir.to_string_lossy().into_owned()),
+    );
+
+    let (instruction, accounts) = signed_memo(&program_id, b"hello");
+    mollusk.process_instruction(&instruction, &accounts);
+
+    let base_fname = output_dir.join(format!("0000-{program_id}"));
+    let folded = std::fs::read_to_string(base_fname.with_extension("folded")).unwrap();
+    let total = folded
+        .lines()
+        .map(|line| {
+            let (stack, count) = line.rsplit_once(' ').unwrap();
+            assert!(stack.starts_with(&format!("{program_id};entrypoint")));
+            count.parse::<u64>().unwrap()
+        })
+        .sum::<u64>();
+    assert_eq!(total, profiles.borrow()[0].total_compute_units());
+
+    let svg = std::fs::read_to_string(base_fname.with_extension("svg")).unwrap();
+    assert!(svg.starts_with("<?xml"));
+    assert!(svg.contains("entrypoint"));
+
+    std::fs::remove_dir_all(output_dir).unwrap();
+}
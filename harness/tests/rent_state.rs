@@ -0,0 +1,221 @@
+use {
+    mollusk_svm::{result::Check, Mollusk},
+    solana_account::Account,
+    solana_pubkey::Pubkey,
+    solana_transaction_error::TransactionError,
+    std::collections::HashMap,
+};
+
+fn system_account_with_lamports(lamports: u64) -> Account {
+    Account::new(lamports, 0, &solana_sdk_ids::system_program::id())
+}
+
+// Struct update syntax on `Mollusk` doesn't compile with the `register-tracing`
+// feature, which adds a private field.
+#[allow(clippy::field_reassign_with_default)]
+fn enforcing_mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::default();
+    mollusk.enforce_rent_state_transitions = true;
+    mollusk
+}
+
+#[test]
+fn test_rent_paying_recipient() {
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+
+    let base_lamports = 100_000_000u64;
+    let transfer_amount = 42u64;
+
+    let instructions = [solana_system_interface::instruction::transfer(
+        &sender,
+        &recipient,
+        transfer_amount,
+    )];
+    let accounts = [
+        (sender, system_account_with_lamports(base_lamports)),
+        (recipient, Account::default()),
+    ];
+
+    // Without enforcement, the recipient is left rent-paying.
+    Mollusk::default().process_and_validate_transaction_instructions(
+        &instructions,
+        &accounts,
+        &[
+            Check::success(),
+            Check::account(&recipient).lamports(transfer_amount).build(),
+        ],
+        None,
+    );
+
+    // With enforcement, the transaction fails and no changes are applied.
+    enforcing_mollusk().process_and_validate_transaction_instructions(
+        &instructions,
+        &accounts,
+        &[
+            Check::transaction_err(TransactionError::InsufficientFundsForRent { account_index: 1 }),
+            Check::account(&sender).lamports(base_lamports).build(),
+            Check::account(&recipient).lamports(0).build(),
+        ],
+        None,
+    );
+}
+
+#[test]
+#[should_panic(
+    expected = "Rent state transitions are only enforced by the transaction APIs: \
+                process_instruction"
+)]
+fn test_instruction_apis_reject_enforced_checks() {
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+
+    enforcing_mollusk().process_instruction(
+        &solana_system_interface::instruction::transfer(&sender, &recipient, 42),
+        &[
+            (sender, system_account_with_lamports(100_000_000)),
+            (recipient, Account::default()),
+        ],
+    );
+}
+
+#[test]
+fn test_allowed_transitions() {
+    let mollusk = enforcing_mollusk();
+
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+
+    let base_lamports = 100_000_000u64;
+    let rent_exempt_minimum = mollusk.sysvars.rent.minimum_balance(0);
+
+    let check_transfer = |sender_lamports: u64, recipient_lamports: u64, amount: u64| {
+        mollusk.process_and_validate_transaction_instructions(
+            &[solana_system_interface::instruction::transfer(
+                &sender, &recipient, amount,
+            )],
+            &[
+                (sender, system_account_with_lamports(sender_lamports)),
+                (recipient, system_account_with_lamports(recipient_lamports)),
+            ],
+            &[Check::success()],
+            None,
+        );
+    };
+
+    // Recipient becomes rent-exempt.
+    check_transfer(base_lamports, 0, rent_exempt_minimum);
+
+    // Sender is closed out entirely.
+    check_transfer(base_lamports, base_lamports, base_lamports);
+
+    // Rent-paying recipient is credited up to the minimum.
+    check_transfer(base_lamports, 1, rent_exempt_minimum - 1);
+}
+
+#[test]
+fn test_rent_exempt_sender_debited_below_minimum() {
+    let mollusk = enforcing_mollusk();
+
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+
+    let rent_exempt_minimum = mollusk.sysvars.rent.minimum_balance(0);
+
+    mollusk.process_and_validate_transaction_instructions(
+        &[solana_system_interface::instruction::transfer(
+            &sender, &recipient, 1,
+        )],
+        &[
+            (sender, system_account_with_lamports(rent_exempt_minimum)),
+            (recipient, system_account_with_lamports(rent_exempt_minimum)),
+        ],
+        &[Check::transaction_err(
+            TransactionError::InsufficientFundsForRent { account_index: 0 },
+        )],
+        None,
+    );
+}
+
+#[test]
+fn test_incinerator_exempt() {
+    let mollusk = enforcing_mollusk();
+
+    let sender = Pubkey::new_unique();
+    let incinerator = solana_sdk_ids::incinerator::id();
+
+    mollusk.process_and_validate_transaction_instructions(
+        &[solana_system_interface::instruction::transfer(
+            &sender,
+            &incinerator,
+            42,
+        )],
+        &[
+            (sender, system_account_with_lamports(100_000_000)),
+            (incinerator, Account::default()),
+        ],
+        &[
+            Check::success(),
+            Check::account(&incinerator).lamports(42).build(),
+        ],
+        None,
+    );
+}
+
+#[test]
+fn test_fee_paying_transaction_keeps_fee() {
+    let mollusk = enforcing_mollusk();
+
+    let payer = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+
+    let base_lamports = 100_000_000u64;
+
+    mollusk.process_and_validate_transaction(
+        &[solana_system_interface::instruction::transfer(
+            &payer, &recipient, 42,
+        )],
+        &[
+            (payer, system_account_with_lamports(base_lamports)),
+            (recipient, Account::default()),
+        ],
+        &payer,
+        &[],
+        &[
+            Check::transaction_err(TransactionError::InsufficientFundsForRent { account_index: 1 }),
+            Check::account(&payer)
+                .lamports(base_lamports - mollusk.lamports_per_signature)
+                .build(),
+            Check::account(&recipient).lamports(0).build(),
+        ],
+    );
+}
+
+#[test]
+fn test_context_does_not_persist_violation() {
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+
+    let base_lamports = 100_000_000u64;
+
+    let mut account_store = HashMap::new();
+    account_store.insert(sender, system_account_with_lamports(base_lamports));
+
+    let context = enforcing_mollusk().with_context(account_store);
+
+    context.process_and_validate_transaction_instructions(
+        &[solana_system_interface::instruction::transfer(
+            &sender, &recipient, 42,
+        )],
+        &[Check::transaction_err(
+            TransactionError::InsufficientFundsForRent { account_index: 1 },
+        )],
+        None,
+    );
+
+    let store = context.account_store.borrow();
+    assert_eq!(store.get(&sender).unwrap().lamports, base_lamports);
+    assert!(store
+        .get(&recipient)
+        .is_none_or(|account| account.lamports == 0));
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use {
+    common::{memo_mollusk, signed_memo},
+    mollusk_svm::{
+        file::read_file,
+        program::loader_keys,
+        result::Check,
+        syscall_intercept::{SyscallAction, SyscallCall, SyscallInterceptError},
+        Mollusk,
+    },
+    solana_account::Account,
+    solana_instruction::error::InstructionError,
+    solana_program_error::ProgramError,
+    solana_program_pack::Pack,
+    solana_pubkey::Pubkey,
+    spl_token_interface::{instruction::initialize_mint2, state::Mint},
+    std::{cell::RefCell, rc::Rc},
+};
+
+const TOKEN_ELF_PATH: &str = "../programs/token/src/elf/token.so";
+
+#[test]
+fn test_intercept_syscall_pass_through() {
+    let (mut mollusk, program_id) = memo_mollusk();
+
+    let calls = Rc::new(RefCell::new(Vec::new()));
+    mollusk
+        .intercept_syscall("sol_log_", {
+            let calls = Rc::clone(&calls);
+            move |call| {
+                calls.borrow_mut().push((call.count, call.args[1]));
+                SyscallAction::PassThrough
+            }
+        })
+        .unwrap();
+
+    let (instruction, accounts) = signed_memo(&program_id, b"hello");
+    mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    // Each call is observed, with its arguments.
+    let calls = calls.borrow();
+    assert!(!calls.is_empty());
+    for (index, (count, len)) in calls.iter().enumerate() {
+        assert_eq!(*count, index as u64);
+        assert!(*len > 0);
+    }
+}
+
+#[test]
+fn test_intercept_syscall_error() {
+    let (mut mollusk, program_id) = memo_mollusk();
+    let (instruction, accounts) = signed_memo(&program_id, b"hello");
+
+    // Programs added before the interception are intercepted too.
+    mollusk
+        .intercept_syscall("sol_log_", |call: &SyscallCall| match call.count {
+            1 => SyscallAction::Error(InstructionError::Custom(7)),
+            _ => SyscallAction::PassThrough,
+        })
+        .unwrap();
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[Check::err(ProgramError::Custom(7))],
+    );
+
+    // Call counts start over for each instruction.
+    let result = mollusk.process_instruction(&instruction, &accounts);
+    assert_eq!(result.raw_result, Err(InstructionError::Custom(7)));
+
+    // Intercepting again replaces the interceptor.
+    mollusk
+        .intercept_syscall("sol_log_", |_| SyscallAction::PassThrough)
+        .unwrap();
+    mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+}
+
+#[test]
+fn test_intercept_syscall_return() {
+    // The value `sol_get_sysvar` returns when a sysvar isn't found.
+    const SYSVAR_NOT_FOUND: u64 = 2;
+
+    let program_id = spl_token_interface::id();
+    let mint = Pubkey::new_unique();
+
+    let mut mollusk = Mollusk::default();
+    mollusk.add_program_with_loader_and_elf(
+        &program_id,
+        &loader_keys::LOADER_V2,
+        &read_file(TOKEN_ELF_PATH),
+    );
+
+    let instruction = initialize_mint2(&program_id, &mint, &Pubkey::new_unique(), None, 9).unwrap();
+    let accounts = [(mint, Account::new(1_000_000_000, Mint::LEN, &program_id))];
+
+    mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    // `InitializeMint2` reads the Rent sysvar.
+    mollusk
+        .intercept_syscall("sol_get_sysvar", |_| {
+            SyscallAction::Return(SYSVAR_NOT_FOUND)
+        })
+        .unwrap();
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[Check::err(ProgramError::UnsupportedSysvar)],
+    );
+}
+
+#[test]
+fn test_intercept_unregistered_syscall() {
+    let mut mollusk = Mollusk::default();
+    assert_eq!(
+        mollusk.intercept_syscall("sol_does_not_exist", |_| SyscallAction::PassThrough),
+        Err(SyscallInterceptError::NotRegistered(
+            "sol_does_not_exist".to_string()
+        )),
+    );
+}
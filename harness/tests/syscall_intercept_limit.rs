@@ -0,0 +1,57 @@
+// Wrapper slots are shared by the whole process, so running out of them is
+// tested in its own test binary.
+
+use {
+    mollusk_svm::{
+        syscall_intercept::{SyscallAction, SyscallInterceptError, MAX_WRAPPED_SYSCALLS},
+        Mollusk,
+    },
+    solana_program_runtime::{
+        invoke_context::InvokeContext,
+        solana_sbpf::{declare_builtin_function, program::BuiltinFunctionDefinition},
+    },
+};
+
+declare_builtin_function!(
+    /// A custom syscall that does nothing.
+    SyscallNoop,
+    fn rust(
+        _invoke_context: &mut InvokeContext<'_, '_>,
+        _arg1: u64,
+        _arg2: u64,
+        _arg3: u64,
+        _arg4: u64,
+        _arg5: u64,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        Ok(0)
+    }
+);
+
+#[test]
+fn test_intercept_syscall_slots_exhausted() {
+    let mut mollusk = Mollusk::default();
+    let names = (0..=MAX_WRAPPED_SYSCALLS)
+        .map(|index| format!("sol_noop_{index}"))
+        .collect::<Vec<_>>();
+    for name in &names {
+        mollusk
+            .program_cache
+            .register_builtin_function(name, SyscallNoop::register)
+            .unwrap();
+    }
+
+    let results = names
+        .iter()
+        .map(|name| mollusk.intercept_syscall(name, |_| SyscallAction::PassThrough))
+        .collect::<Vec<_>>();
+    assert_eq!(results.first(), Some(&Ok(())));
+    assert_eq!(
+        results.last(),
+        Some(&Err(SyscallInterceptError::SlotsExhausted))
+    );
+
+    // Syscalls that already have a slot can still be intercepted again.
+    mollusk
+        .intercept_syscall(&names[0], |_| SyscallAction::Return(0))
+        .unwrap();
+}
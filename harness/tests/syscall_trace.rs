@@ -0,0 +1,206 @@
+#![cfg(feature = "syscall-trace")]
+
+mod common;
+
+use {
+    common::{memo_mollusk, mint_data, signed_memo},
+    mollusk_svm::{
+        file::read_file,
+        program::{
+            create_program_account_loader_v2, keyed_account_for_system_program, loader_keys,
+        },
+        result::{types::SyscallArg, Check},
+        syscall_intercept::SyscallAction,
+        Mollusk,
+    },
+    solana_account::Account,
+    solana_instruction::{AccountMeta, Instruction},
+    solana_pubkey::Pubkey,
+};
+
+const TOKEN_ELF_PATH: &str = "../programs/token/src/elf/token.so";
+const ASSOCIATED_TOKEN_ELF_PATH: &str = "../programs/token/src/elf/associated_token.so";
+const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    solana_pubkey::pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+const TOKEN_PROGRAM_ID: Pubkey =
+    solana_pubkey::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+#[test]
+fn test_syscall_trace() {
+    let (mollusk, program_id) = memo_mollusk();
+    let (instruction, accounts) = signed_memo(&program_id, b"hello");
+
+    let result =
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    assert!(!result.syscall_trace.is_empty());
+    for entry in &result.syscall_trace {
+        assert_eq!(entry.name, "sol_log_");
+        assert_eq!(entry.program_id, program_id);
+        assert_eq!(entry.stack_height, 1);
+        assert!(entry.args[1] > 0);
+        assert!(entry.compute_units_consumed > 0);
+    }
+    let traced_compute_units = result
+        .syscall_trace
+        .iter()
+        .map(|entry| entry.compute_units_consumed)
+        .sum::<u64>();
+    assert!(traced_compute_units < result.compute_units_consumed);
+
+    // The arguments are decoded by the syscall's signature.
+    let entry = &result.syscall_trace[0];
+    assert_eq!(
+        entry.decoded_args(),
+        Some(vec![
+            SyscallArg::Address("message", entry.args[0]),
+            SyscallArg::Value("len", entry.args[1]),
+        ]),
+    );
+    assert_eq!(
+        entry.to_string(),
+        format!(
+            "sol_log_(message: {:#x}, len: {}): {} CU",
+            entry.args[0], entry.args[1], entry.compute_units_consumed,
+        ),
+    );
+
+    let summary = result.syscall_summary();
+    let stats = summary.get("sol_log_").unwrap();
+    assert_eq!(stats.calls, result.syscall_trace.len() as u64);
+    assert_eq!(stats.compute_units_consumed, traced_compute_units);
+    assert_eq!(
+        summary.to_string(),
+        format!(
+            "sol_log_: {} calls, {} CU\n",
+            stats.calls, stats.compute_units_consumed,
+        ),
+    );
+}
+
+#[test]
+fn test_syscall_trace_chain() {
+    let (mollusk, program_id) = memo_mollusk();
+    let (instruction, accounts) = signed_memo(&program_id, b"hello");
+
+    let single = mollusk.process_instruction(&instruction, &accounts);
+
+    // Chains and transactions trace the syscalls of every instruction.
+    let chain = mollusk.process_instruction_chain(&[instruction.clone(), instruction], &accounts);
+    assert_eq!(chain.syscall_trace.len(), 2 * single.syscall_trace.len());
+    assert_eq!(
+        chain
+            .syscall_summary()
+            .get("sol_log_")
+            .unwrap()
+            .compute_units_consumed,
+        2 * single
+            .syscall_summary()
+            .get("sol_log_")
+            .unwrap()
+            .compute_units_consumed,
+    );
+
+    let (instruction, accounts) = signed_memo(&program_id, b"hello");
+    let transaction = mollusk.process_transaction_instructions(
+        &[instruction.clone(), instruction],
+        &accounts,
+        None,
+    );
+    assert_eq!(
+        transaction.syscall_trace.len(),
+        2 * single.syscall_trace.len()
+    );
+}
+
+#[test]
+fn test_syscall_trace_cpi() {
+    let payer = Pubkey::new_unique();
+    let wallet = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let associated_token_address = Pubkey::find_program_address(
+        &[wallet.as_ref(), TOKEN_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0;
+
+    let token_elf = read_file(TOKEN_ELF_PATH);
+
+    let mut mollusk = Mollusk::default();
+    mollusk.add_program_with_loader_and_elf(
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+        &loader_keys::LOADER_V2,
+        &read_file(ASSOCIATED_TOKEN_ELF_PATH),
+    );
+    mollusk.add_program_with_loader_and_elf(&TOKEN_PROGRAM_ID, &loader_keys::LOADER_V2, &token_elf);
+
+    let instruction = Instruction::new_with_bytes(
+        ASSOCIATED_TOKEN_PROGRAM_ID,
+        &[],
+        vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(associated_token_address, false),
+            AccountMeta::new_readonly(wallet, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(solana_sdk_ids::system_program::id(), false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ],
+    );
+    let accounts = [
+        (
+            payer,
+            Account::new(10_000_000_000, 0, &solana_sdk_ids::system_program::id()),
+        ),
+        (associated_token_address, Account::default()),
+        (wallet, Account::default()),
+        (mint, {
+            let mut account = Account::new(1_000_000_000, 0, &TOKEN_PROGRAM_ID);
+            account.data = mint_data();
+            account
+        }),
+        keyed_account_for_system_program(),
+        (
+            TOKEN_PROGRAM_ID,
+            create_program_account_loader_v2(&token_elf),
+        ),
+    ];
+
+    let result =
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    // Syscalls are attributed to the program that invoked them, at the
+    // depth it was invoked at.
+    let trace = &result.syscall_trace;
+    let invoke = trace
+        .iter()
+        .find(|entry| entry.name == "sol_invoke_signed_rust")
+        .unwrap();
+    assert_eq!(invoke.program_id, ASSOCIATED_TOKEN_PROGRAM_ID);
+    assert_eq!(invoke.stack_height, 1);
+    assert!(invoke.compute_units_consumed > 0);
+    assert!(trace
+        .iter()
+        .any(|entry| entry.program_id == TOKEN_PROGRAM_ID && entry.stack_height == 2));
+    assert!(trace
+        .iter()
+        .all(|entry| (entry.program_id == TOKEN_PROGRAM_ID) == (entry.stack_height == 2)));
+}
+
+#[test]
+fn test_syscall_trace_intercepted() {
+    let (mut mollusk, program_id) = memo_mollusk();
+    let (instruction, accounts) = signed_memo(&program_id, b"hello");
+
+    // Intercepted calls are traced too, charging nothing when skipped.
+    mollusk
+        .intercept_syscall("sol_log_", |_| SyscallAction::Return(0))
+        .unwrap();
+    let result =
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+
+    assert!(!result.syscall_trace.is_empty());
+    assert!(result
+        .syscall_trace
+        .iter()
+        .all(|entry| entry.name == "sol_log_" && entry.compute_units_consumed == 0));
+}
@@ -0,0 +1,327 @@
+use {
+    mollusk_svm::{
+        result::{types::TransactionProgramResult, Check},
+        Mollusk,
+    },
+    solana_account::Account,
+    solana_program_error::ProgramError,
+    solana_pubkey::Pubkey,
+    solana_system_interface::error::SystemError,
+    solana_transaction_error::TransactionError,
+    std::collections::HashMap,
+};
+
+fn system_account_with_lamports(lamports: u64) -> Account {
+    Account::new(lamports, 0, &solana_sdk_ids::system_program::id())
+}
+
+#[test]
+fn test_fee_charged_on_success() {
+    let mollusk = Mollusk::default();
+
+    let payer = Pubkey::new_unique();
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+
+    let base_lamports = 100_000_000u64;
+    let transfer_amount = 42_000u64;
+
+    // Two signatures: the payer and the sender.
+    let expected_fee = 2 * mollusk.lamports_per_signature;
+
+    let result = mollusk.process_and_validate_transaction(
+        &[solana_system_interface::instruction::transfer(
+            &sender,
+            &recipient,
+            transfer_amount,
+        )],
+        &[
+            (payer, system_account_with_lamports(base_lamports)),
+            (sender, system_account_with_lamports(base_lamports)),
+            (recipient, system_account_with_lamports(base_lamports)),
+        ],
+        &payer,
+        &[sender],
+        &[
+            Check::success(),
+            Check::account(&payer)
+                .lamports(base_lamports - expected_fee)
+                .build(),
+            Check::account(&sender)
+                .lamports(base_lamports - transfer_amount)
+                .build(),
+            Check::account(&recipient)
+                .lamports(base_lamports + transfer_amount)
+                .build(),
+        ],
+    );
+
+    assert_eq!(result.fee.signature_fee, expected_fee);
+    assert_eq!(result.fee.prioritization_fee, 0);
+}
+
+#[test]
+fn test_fee_kept_on_failure() {
+    let mollusk = Mollusk::default();
+
+    let payer = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+
+    let base_lamports = 100_000_000u64;
+
+    let result = mollusk.process_and_validate_transaction(
+        &[solana_system_interface::instruction::transfer(
+            &payer,
+            &recipient,
+            base_lamports * 2,
+        )],
+        &[
+            (payer, system_account_with_lamports(base_lamports)),
+            (recipient, system_account_with_lamports(base_lamports)),
+        ],
+        &payer,
+        &[],
+        &[
+            Check::err(ProgramError::Custom(
+                SystemError::ResultWithNegativeLamports as u32,
+            )),
+            Check::account(&payer)
+                .lamports(base_lamports - mollusk.lamports_per_signature)
+                .build(),
+            Check::account(&recipient).lamports(base_lamports).build(),
+        ],
+    );
+
+    assert_eq!(result.fee.total(), mollusk.lamports_per_signature);
+}
+
+#[test]
+// Struct update syntax on `Mollusk` doesn't compile with the `register-tracing`
+// feature, which adds a private field.
+#[allow(clippy::field_reassign_with_default)]
+fn test_custom_lamports_per_signature() {
+    let mut mollusk = Mollusk::default();
+    mollusk.lamports_per_signature = 12_345;
+
+    let payer = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+
+    let base_lamports = 100_000_000u64;
+    let transfer_amount = 42_000u64;
+
+    mollusk.process_and_validate_transaction(
+        &[solana_system_interface::instruction::transfer(
+            &payer,
+            &recipient,
+            transfer_amount,
+        )],
+        &[
+            (payer, system_account_with_lamports(base_lamports)),
+            (recipient, system_account_with_lamports(0)),
+        ],
+        &payer,
+        &[],
+        &[
+            Check::success(),
+            Check::account(&payer)
+                .lamports(base_lamports - transfer_amount - 12_345)
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_rejected_transactions_are_not_charged() {
+    let mollusk = Mollusk::default();
+
+    let payer = Pubkey::new_unique();
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+
+    let base_lamports = 100_000_000u64;
+    let instructions = [solana_system_interface::instruction::transfer(
+        &sender, &recipient, 42_000,
+    )];
+
+    let check_rejected = |payer_account: Account, signers: &[Pubkey], error: TransactionError| {
+        let result = mollusk.process_and_validate_transaction(
+            &instructions,
+            &[
+                (payer, payer_account.clone()),
+                (sender, system_account_with_lamports(base_lamports)),
+                (recipient, system_account_with_lamports(base_lamports)),
+            ],
+            &payer,
+            signers,
+            &[
+                Check::transaction_err(error.clone()),
+                Check::account(&payer)
+                    .lamports(payer_account.lamports)
+                    .build(),
+                Check::account(&sender).lamports(base_lamports).build(),
+            ],
+        );
+        assert_eq!(
+            result.program_result,
+            TransactionProgramResult::TransactionError(error),
+        );
+        // A rejected transaction never reports a program result.
+        assert!(!result.check_report(&[Check::success()], &mollusk).is_pass());
+        assert_eq!(result.fee.total(), 0);
+        assert_eq!(result.compute_units_consumed, 0);
+    };
+
+    // Missing signer.
+    check_rejected(
+        system_account_with_lamports(base_lamports),
+        &[],
+        TransactionError::SignatureFailure,
+    );
+
+    // Unfunded fee payer.
+    check_rejected(
+        system_account_with_lamports(0),
+        &[sender],
+        TransactionError::AccountNotFound,
+    );
+
+    // Fee payer not owned by the system program.
+    check_rejected(
+        Account::new(base_lamports, 0, &Pubkey::new_unique()),
+        &[sender],
+        TransactionError::InvalidAccountForFee,
+    );
+
+    // Fee payer with data.
+    check_rejected(
+        Account::new(base_lamports, 8, &solana_sdk_ids::system_program::id()),
+        &[sender],
+        TransactionError::InvalidAccountForFee,
+    );
+
+    // Fee payer can't cover the fee.
+    check_rejected(
+        system_account_with_lamports(mollusk.lamports_per_signature),
+        &[sender],
+        TransactionError::InsufficientFundsForFee,
+    );
+
+    // Fee payer would drop below the rent-exempt minimum.
+    check_rejected(
+        system_account_with_lamports(mollusk.sysvars.rent.minimum_balance(0)),
+        &[sender],
+        TransactionError::InsufficientFundsForRent { account_index: 0 },
+    );
+}
+
+#[cfg(feature = "all-builtins")]
+#[test]
+fn test_prioritization_fee() {
+    use solana_instruction::Instruction;
+
+    let mollusk = Mollusk::default();
+
+    let payer = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+
+    let base_lamports = 100_000_000u64;
+    let transfer_amount = 42_000u64;
+
+    let compute_unit_limit = 200_000u32;
+    let compute_unit_price = 1_000_000u64; // 1 lamport per compute unit.
+    let expected_prioritization_fee = compute_unit_limit as u64;
+
+    let set_compute_unit_limit = {
+        let mut data = vec![2];
+        data.extend_from_slice(&compute_unit_limit.to_le_bytes());
+        Instruction::new_with_bytes(solana_sdk_ids::compute_budget::id(), &data, vec![])
+    };
+    let set_compute_unit_price = {
+        let mut data = vec![3];
+        data.extend_from_slice(&compute_unit_price.to_le_bytes());
+        Instruction::new_with_bytes(solana_sdk_ids::compute_budget::id(), &data, vec![])
+    };
+
+    let result = mollusk.process_and_validate_transaction(
+        &[
+            set_compute_unit_limit,
+            set_compute_unit_price,
+            solana_system_interface::instruction::transfer(&payer, &recipient, transfer_amount),
+        ],
+        &[
+            (payer, system_account_with_lamports(base_lamports)),
+            (recipient, system_account_with_lamports(0)),
+        ],
+        &payer,
+        &[],
+        &[
+            Check::success(),
+            Check::account(&payer)
+                .lamports(
+                    base_lamports
+                        - transfer_amount
+                        - mollusk.lamports_per_signature
+                        - expected_prioritization_fee,
+                )
+                .build(),
+        ],
+    );
+
+    assert_eq!(result.fee.prioritization_fee, expected_prioritization_fee);
+}
+
+#[test]
+fn test_context_persists_fee_on_failure() {
+    let payer = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+
+    let base_lamports = 100_000_000u64;
+
+    let mut account_store = HashMap::new();
+    account_store.insert(payer, system_account_with_lamports(base_lamports));
+    account_store.insert(recipient, system_account_with_lamports(0));
+
+    let context = Mollusk::default().with_context(account_store);
+    let fee = context.mollusk.lamports_per_signature;
+
+    // Successful transfer: the fee and the transfer are persisted.
+    context.process_and_validate_transaction(
+        &[solana_system_interface::instruction::transfer(
+            &payer, &recipient, 1_000,
+        )],
+        &payer,
+        &[],
+        &[Check::success()],
+    );
+
+    // Failed transfer: only the fee is persisted.
+    context.process_and_validate_transaction(
+        &[solana_system_interface::instruction::transfer(
+            &payer,
+            &recipient,
+            base_lamports * 2,
+        )],
+        &payer,
+        &[],
+        &[Check::err(ProgramError::Custom(
+            SystemError::ResultWithNegativeLamports as u32,
+        ))],
+    );
+
+    // Rejected transaction: nothing is persisted.
+    context.process_and_validate_transaction(
+        &[solana_system_interface::instruction::transfer(
+            &recipient, &payer, 1_000,
+        )],
+        &payer,
+        &[],
+        &[Check::transaction_err(TransactionError::SignatureFailure)],
+    );
+
+    let store = context.account_store.borrow();
+    assert_eq!(
+        store.get(&payer).unwrap().lamports,
+        base_lamports - 1_000 - 2 * fee,
+    );
+    assert_eq!(store.get(&recipient).unwrap().lamports, 1_000);
+}
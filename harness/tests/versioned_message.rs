@@ -0,0 +1,260 @@
+use {
+    mollusk_svm::{
+        address_lookup_table::create_address_lookup_table_account, result::Check, Mollusk,
+    },
+    solana_account::Account,
+    solana_address_lookup_table_interface::state::{AddressLookupTable, LookupTableMeta},
+    solana_hash::Hash,
+    solana_instruction::error::InstructionError,
+    solana_message::{
+        v0::{self, MessageAddressTableLookup},
+        AddressLookupTableAccount, MessageHeader, VersionedMessage,
+    },
+    solana_pubkey::Pubkey,
+    solana_transaction_error::TransactionError,
+    std::{borrow::Cow, collections::HashMap},
+};
+
+fn system_account_with_lamports(lamports: u64) -> Account {
+    Account::new(lamports, 0, &solana_sdk_ids::system_program::id())
+}
+
+#[test]
+fn test_v0_transfer_with_lookup_table() {
+    let mollusk = Mollusk::default();
+
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let lookup_table = Pubkey::new_unique();
+
+    let base_lamports = 100_000_000u64;
+    let transfer_amount = 42_000u64;
+
+    let message = v0::Message::try_compile(
+        &sender,
+        &[solana_system_interface::instruction::transfer(
+            &sender,
+            &recipient,
+            transfer_amount,
+        )],
+        &[AddressLookupTableAccount {
+            key: lookup_table,
+            addresses: vec![recipient],
+        }],
+        Hash::default(),
+    )
+    .unwrap();
+
+    // The recipient should be loaded from the lookup table.
+    assert!(!message.account_keys.contains(&recipient));
+    assert_eq!(message.address_table_lookups.len(), 1);
+
+    mollusk.process_and_validate_versioned_message(
+        &VersionedMessage::V0(message),
+        &[
+            (sender, system_account_with_lamports(base_lamports)),
+            (recipient, system_account_with_lamports(base_lamports)),
+            (
+                lookup_table,
+                create_address_lookup_table_account(&[recipient]),
+            ),
+        ],
+        &[
+            Check::success(),
+            Check::account(&sender)
+                .lamports(base_lamports - transfer_amount)
+                .build(),
+            Check::account(&recipient)
+                .lamports(base_lamports + transfer_amount)
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_v0_readonly_lookup_table_address() {
+    let mollusk = Mollusk::default();
+
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let lookup_table = Pubkey::new_unique();
+
+    let base_lamports = 100_000_000u64;
+
+    // Load the transfer recipient as readonly.
+    let message = v0::Message {
+        header: MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 1,
+        },
+        account_keys: vec![sender, solana_sdk_ids::system_program::id()],
+        recent_blockhash: Hash::default(),
+        instructions: vec![solana_message::compiled_instruction::CompiledInstruction {
+            program_id_index: 1,
+            accounts: vec![0, 2],
+            data: solana_system_interface::instruction::transfer(&sender, &recipient, 42_000).data,
+        }],
+        address_table_lookups: vec![MessageAddressTableLookup {
+            account_key: lookup_table,
+            writable_indexes: vec![],
+            readonly_indexes: vec![0],
+        }],
+    };
+
+    mollusk.process_and_validate_versioned_message(
+        &VersionedMessage::V0(message),
+        &[
+            (sender, system_account_with_lamports(base_lamports)),
+            (recipient, system_account_with_lamports(base_lamports)),
+            (
+                lookup_table,
+                create_address_lookup_table_account(&[recipient]),
+            ),
+        ],
+        &[
+            Check::instruction_err(InstructionError::ReadonlyLamportChange),
+            Check::account(&sender).lamports(base_lamports).build(),
+            Check::account(&recipient).lamports(base_lamports).build(),
+        ],
+    );
+}
+
+#[test]
+fn test_v0_lookup_table_errors() {
+    let mollusk = Mollusk::default();
+
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let lookup_table = Pubkey::new_unique();
+
+    let base_lamports = 100_000_000u64;
+
+    let message = VersionedMessage::V0(
+        v0::Message::try_compile(
+            &sender,
+            &[solana_system_interface::instruction::transfer(
+                &sender, &recipient, 42_000,
+            )],
+            &[AddressLookupTableAccount {
+                key: lookup_table,
+                addresses: vec![recipient],
+            }],
+            Hash::default(),
+        )
+        .unwrap(),
+    );
+
+    let check_rejected = |lookup_table_account: Option<Account>, error: TransactionError| {
+        let mut accounts = vec![
+            (sender, system_account_with_lamports(base_lamports)),
+            (recipient, system_account_with_lamports(base_lamports)),
+        ];
+        if let Some(account) = lookup_table_account {
+            accounts.push((lookup_table, account));
+        }
+        mollusk.process_and_validate_versioned_message(
+            &message,
+            &accounts,
+            &[
+                Check::transaction_err(error),
+                Check::account(&sender).lamports(base_lamports).build(),
+            ],
+        );
+    };
+
+    // Missing lookup table.
+    check_rejected(None, TransactionError::AddressLookupTableNotFound);
+
+    // Lookup table not owned by the address lookup table program.
+    check_rejected(
+        Some(Account {
+            owner: Pubkey::new_unique(),
+            ..create_address_lookup_table_account(&[recipient])
+        }),
+        TransactionError::InvalidAddressLookupTableOwner,
+    );
+
+    // Lookup table with invalid data.
+    check_rejected(
+        Some(Account {
+            data: vec![1; 8],
+            ..create_address_lookup_table_account(&[recipient])
+        }),
+        TransactionError::InvalidAddressLookupTableData,
+    );
+
+    // Lookup table index out of bounds.
+    check_rejected(
+        Some(create_address_lookup_table_account(&[])),
+        TransactionError::InvalidAddressLookupTableIndex,
+    );
+
+    // Deactivated lookup table.
+    let deactivated_data = AddressLookupTable {
+        meta: LookupTableMeta {
+            deactivation_slot: 1_000,
+            ..LookupTableMeta::default()
+        },
+        addresses: Cow::Owned(vec![recipient]),
+    }
+    .serialize_for_tests()
+    .unwrap();
+    check_rejected(
+        Some(Account {
+            data: deactivated_data,
+            ..create_address_lookup_table_account(&[recipient])
+        }),
+        TransactionError::AddressLookupTableNotFound,
+    );
+}
+
+#[test]
+fn test_v0_context_loads_lookup_tables_from_store() {
+    let sender = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let lookup_table = Pubkey::new_unique();
+
+    let base_lamports = 100_000_000u64;
+    let transfer_amount = 42_000u64;
+
+    let mut account_store = HashMap::new();
+    account_store.insert(sender, system_account_with_lamports(base_lamports));
+    account_store.insert(recipient, system_account_with_lamports(base_lamports));
+    account_store.insert(
+        lookup_table,
+        create_address_lookup_table_account(&[recipient]),
+    );
+
+    let context = Mollusk::default().with_context(account_store);
+
+    let message = VersionedMessage::V0(
+        v0::Message::try_compile(
+            &sender,
+            &[solana_system_interface::instruction::transfer(
+                &sender,
+                &recipient,
+                transfer_amount,
+            )],
+            &[AddressLookupTableAccount {
+                key: lookup_table,
+                addresses: vec![recipient],
+            }],
+            Hash::default(),
+        )
+        .unwrap(),
+    );
+
+    context.process_and_validate_versioned_message(&message, &[Check::success()]);
+    context.process_and_validate_versioned_message(&message, &[Check::success()]);
+
+    let store = context.account_store.borrow();
+    assert_eq!(
+        store.get(&sender).unwrap().lamports,
+        base_lamports - 2 * transfer_amount,
+    );
+    assert_eq!(
+        store.get(&recipient).unwrap().lamports,
+        base_lamports + 2 * transfer_amount,
+    );
+}
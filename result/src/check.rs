@@ -1,34 +1,67 @@
 //! Check system for validating individual instruction results.
 
 #[cfg(feature = "inner-instructions")]
-use solana_transaction_status_client_types::InnerInstruction;
+use {
+    crate::types::ResolvedInnerInstruction, solana_instruction::AccountMeta,
+    solana_transaction_status_client_types::InnerInstruction,
+};
 use {
     crate::{
-        config::{compare, throw, CheckContext, Config},
+        config::{compare, CheckContext, Config},
+        decode::AccountDecoder,
+        diff::{DataDiff, DataLayout, DebugDiff},
+        report::CheckReport,
         types::{InstructionResult, ProgramResult, TransactionProgramResult, TransactionResult},
     },
     solana_account::{Account, ReadableAccount},
     solana_instruction::error::InstructionError,
     solana_program_error::ProgramError,
     solana_pubkey::Pubkey,
+    solana_transaction_error::TransactionError,
+    std::{any::type_name, fmt::Debug},
 };
 
 enum CheckType<'a> {
     /// Check the number of compute units consumed by the instruction.
     ComputeUnitsConsumed(u64),
+    /// Check that the compute units consumed do not exceed a maximum.
+    ComputeUnitsAtMost(u64),
+    /// Check that the compute units consumed are within a percentage of the
+    /// expected amount.
+    ComputeUnitsWithin { units: u64, percent: f64 },
     /// Check the time taken to execute the instruction.
     ExecutionTime(u64),
     /// Check the result code of the program's execution.
     ProgramResult(ProgramResult),
+    /// Check that the runtime rejected the transaction outside of program
+    /// execution.
+    TransactionError(TransactionError),
     /// Check the return data produced by executing the instruction.
     ReturnData(&'a [u8]),
-    /// Check a resulting account after executing the instruction.
-    ResultingAccount(AccountCheck<'a>),
+    /// Check a resulting account after executing the instruction, along with
+    /// any checks against its decoded data.
+    ResultingAccount(AccountCheck<'a>, Vec<DecodedCheck<'a>>),
     /// Check that all accounts are rent exempt
     AllRentExempt,
+    /// Check that a log message contains the provided text.
+    LogsContain(&'a str),
+    /// Check that a log message matches the provided regular expression.
+    #[cfg(feature = "regex")]
+    LogMatches(&'a str),
+    /// Check that the program didn't log any messages.
+    NoLogs,
     /// Check the number of inner instructions (CPIs) invoked.
     #[cfg(feature = "inner-instructions")]
     InnerInstructionCount(usize),
+    /// Check that a matching inner instruction (CPI) was invoked.
+    #[cfg(feature = "inner-instructions")]
+    Cpi(CpiCheck<'a>),
+    /// Check the inner instructions (CPIs) invoked, in order.
+    #[cfg(feature = "inner-instructions")]
+    Cpis {
+        cpis: &'a [CpiCheckBuilder<'a>],
+        exact: bool,
+    },
 }
 
 pub struct Check<'a> {
@@ -45,6 +78,18 @@ impl<'a> Check<'a> {
         Check::new(CheckType::ComputeUnitsConsumed(units))
     }
 
+    /// Check that the compute units consumed by the instruction do not
+    /// exceed the provided maximum.
+    pub const fn compute_units_at_most(units: u64) -> Self {
+        Check::new(CheckType::ComputeUnitsAtMost(units))
+    }
+
+    /// Check that the compute units consumed by the instruction are within
+    /// `percent` percent of the provided amount, in either direction.
+    pub const fn compute_units_within(units: u64, percent: f64) -> Self {
+        Check::new(CheckType::ComputeUnitsWithin { units, percent })
+    }
+
     /// Check the time taken to execute the instruction.
     pub const fn time(time: u64) -> Self {
         Check::new(CheckType::ExecutionTime(time))
@@ -65,6 +110,12 @@ impl<'a> Check<'a> {
         Check::new(CheckType::ProgramResult(ProgramResult::UnknownError(error)))
     }
 
+    /// Assert that the runtime rejected the transaction with the provided
+    /// error, outside of program execution.
+    pub const fn transaction_err(error: TransactionError) -> Self {
+        Check::new(CheckType::TransactionError(error))
+    }
+
     /// Assert that the instruction returned the provided result.
     pub const fn program_result(result: ProgramResult) -> Self {
         Check::new(CheckType::ProgramResult(result))
@@ -85,11 +136,56 @@ impl<'a> Check<'a> {
         Check::new(CheckType::AllRentExempt)
     }
 
+    /// Check that at least one log message contains the provided text.
+    pub const fn logs_contain(text: &'a str) -> Self {
+        Check::new(CheckType::LogsContain(text))
+    }
+
+    /// Check that at least one log message matches the provided regular
+    /// expression.
+    #[cfg(feature = "regex")]
+    pub const fn log_matches(regex: &'a str) -> Self {
+        Check::new(CheckType::LogMatches(regex))
+    }
+
+    /// Check that the program didn't log any messages, such as with `msg!`,
+    /// `sol_log_data` or `sol_log_compute_units`.
+    ///
+    /// The messages logged by the runtime for each invocation, such as
+    /// `Program <id> invoke [1]`, are ignored.
+    pub const fn no_logs() -> Self {
+        Check::new(CheckType::NoLogs)
+    }
+
     /// Check the number of inner instructions (CPIs) invoked during execution.
     #[cfg(feature = "inner-instructions")]
     pub const fn inner_instruction_count(count: usize) -> Self {
         Check::new(CheckType::InnerInstructionCount(count))
     }
+
+    /// Check an inner instruction (CPI) invoked during execution.
+    ///
+    /// By default, the check passes if at least one CPI to the program
+    /// matches. Use `CpiCheckBuilder::times` to require an exact number of
+    /// matching CPIs.
+    #[cfg(feature = "inner-instructions")]
+    pub const fn cpi(program_id: &Pubkey) -> CpiCheckBuilder<'_> {
+        CpiCheckBuilder::new(program_id)
+    }
+
+    /// Check that the inner instructions (CPIs) invoked during execution are
+    /// exactly the provided CPIs, in order.
+    #[cfg(feature = "inner-instructions")]
+    pub const fn cpis(cpis: &'a [CpiCheckBuilder<'a>]) -> Self {
+        Check::new(CheckType::Cpis { cpis, exact: true })
+    }
+
+    /// Check that the provided CPIs were invoked in order during execution,
+    /// allowing other CPIs before, between and after them.
+    #[cfg(feature = "inner-instructions")]
+    pub const fn cpis_in_order(cpis: &'a [CpiCheckBuilder<'a>]) -> Self {
+        Check::new(CheckType::Cpis { cpis, exact: false })
+    }
 }
 
 enum AccountStateCheck {
@@ -106,8 +202,13 @@ struct AccountCheck<'a> {
     check_space: Option<usize>,
     check_state: Option<AccountStateCheck>,
     check_data_slice: Option<(usize, &'a [u8])>,
+    data_layout: Option<&'a DataLayout>,
 }
 
+/// A check against decoded account data, producing the expected value, the
+/// actual value and a message on failure.
+type DecodedCheck<'a> = Box<dyn Fn(&[u8]) -> Result<(), (String, String, String)> + 'a>;
+
 impl AccountCheck<'_> {
     const fn new(pubkey: &Pubkey) -> Self {
         Self {
@@ -119,6 +220,17 @@ impl AccountCheck<'_> {
             check_space: None,
             check_state: None,
             check_data_slice: None,
+            data_layout: None,
+        }
+    }
+}
+
+impl<'a> AccountCheck<'a> {
+    fn data_diff(&self, expected: &'a [u8], actual: &'a [u8], offset: usize) -> DataDiff<'a> {
+        let diff = DataDiff::new(expected, actual).offset(offset);
+        match self.data_layout {
+            Some(layout) => diff.layout(layout),
+            None => diff,
         }
     }
 }
@@ -174,90 +286,390 @@ impl<'a> AccountCheckBuilder<'a> {
         self
     }
 
+    /// Name the fields of the account data, so that data mismatches are
+    /// reported by field.
+    pub const fn data_layout(mut self, layout: &'a DataLayout) -> Self {
+        self.check.data_layout = Some(layout);
+        self
+    }
+
+    /// Decode the account data with the provided decoder, and check that the
+    /// decoded value satisfies the predicate.
+    ///
+    /// On failure, the decoded value is rendered with `Debug`. Further decoded
+    /// checks can be chained on the returned builder.
+    pub fn decoded<T, D>(
+        self,
+        decoder: D,
+        predicate: impl Fn(&T) -> bool + 'a,
+    ) -> DecodedAccountCheckBuilder<'a>
+    where
+        T: Debug + 'a,
+        D: AccountDecoder<T> + 'a,
+    {
+        DecodedAccountCheckBuilder::new(self.check).decoded(decoder, predicate)
+    }
+
+    /// Decode the account data with the provided decoder, and check that the
+    /// decoded value equals the expected value.
+    ///
+    /// On failure, the changed lines of the decoded value are rendered as a
+    /// diff. Further decoded checks can be chained on the returned builder.
+    pub fn decoded_eq<T, D>(self, decoder: D, expected: T) -> DecodedAccountCheckBuilder<'a>
+    where
+        T: Debug + PartialEq + 'a,
+        D: AccountDecoder<T> + 'a,
+    {
+        DecodedAccountCheckBuilder::new(self.check).decoded_eq(decoder, expected)
+    }
+
     pub const fn build(self) -> Check<'a> {
-        Check::new(CheckType::ResultingAccount(self.check))
+        Check::new(CheckType::ResultingAccount(self.check, Vec::new()))
+    }
+}
+
+/// A builder for account checks with checks against the decoded account data,
+/// returned by `AccountCheckBuilder::decoded` and
+/// `AccountCheckBuilder::decoded_eq`.
+pub struct DecodedAccountCheckBuilder<'a> {
+    check: AccountCheck<'a>,
+    decoded: Vec<DecodedCheck<'a>>,
+}
+
+impl<'a> DecodedAccountCheckBuilder<'a> {
+    fn new(check: AccountCheck<'a>) -> Self {
+        Self {
+            check,
+            decoded: Vec::new(),
+        }
+    }
+
+    /// Decode the account data with the provided decoder, and check that the
+    /// decoded value satisfies the predicate.
+    ///
+    /// On failure, the decoded value is rendered with `Debug`.
+    pub fn decoded<T, D>(mut self, decoder: D, predicate: impl Fn(&T) -> bool + 'a) -> Self
+    where
+        T: Debug + 'a,
+        D: AccountDecoder<T> + 'a,
+    {
+        self.decoded.push(Box::new(move |data| {
+            let value = decode::<T>(&decoder, data)?;
+            if predicate(&value) {
+                return Ok(());
+            }
+            let actual = format!("{value:#?}");
+            let message = format!(
+                "Decoded `{}` does not satisfy the predicate:\n{actual}",
+                type_name::<T>(),
+            );
+            Err(("predicate satisfied".to_string(), actual, message))
+        }));
+        self
+    }
+
+    /// Decode the account data with the provided decoder, and check that the
+    /// decoded value equals the expected value.
+    ///
+    /// On failure, the changed lines of the decoded value are rendered as a
+    /// diff.
+    pub fn decoded_eq<T, D>(mut self, decoder: D, expected: T) -> Self
+    where
+        T: Debug + PartialEq + 'a,
+        D: AccountDecoder<T> + 'a,
+    {
+        self.decoded.push(Box::new(move |data| {
+            let value = decode::<T>(&decoder, data)?;
+            if value == expected {
+                return Ok(());
+            }
+            let diff = DebugDiff::new(&expected, &value);
+            let message = format!("Decoded `{}` mismatch:\n{diff}", type_name::<T>());
+            Err((
+                format!("{expected:?}"),
+                format!("{value:?}"),
+                message.trim_end().to_string(),
+            ))
+        }));
+        self
+    }
+
+    pub fn build(self) -> Check<'a> {
+        Check::new(CheckType::ResultingAccount(self.check, self.decoded))
+    }
+}
+
+/// The number of compute units by which a result may differ from `units`:
+/// the greater of `absolute` and `percent` percent of `units`, rounded down.
+pub(crate) fn compute_units_tolerance(units: u64, absolute: u64, percent: f64) -> u64 {
+    let relative = (units as f64 * percent.max(0.0) / 100.0) as u64;
+    absolute.max(relative)
+}
+
+fn decode<T>(decoder: &impl AccountDecoder<T>, data: &[u8]) -> Result<T, (String, String, String)> {
+    decoder.decode(data).map_err(|err| {
+        let message = format!(
+            "Failed to decode account data as `{}`: {err}",
+            type_name::<T>()
+        );
+        ("decodable".to_string(), err, message)
+    })
+}
+
+#[cfg(feature = "inner-instructions")]
+#[derive(Debug)]
+struct CpiCheck<'a> {
+    program_id: Pubkey,
+    check_data: Option<&'a [u8]>,
+    check_data_prefix: Option<&'a [u8]>,
+    check_accounts: Option<&'a [AccountMeta]>,
+    check_account_keys: Option<&'a [Pubkey]>,
+    check_stack_height: Option<u32>,
+    check_times: Option<usize>,
+}
+
+#[cfg(feature = "inner-instructions")]
+impl CpiCheck<'_> {
+    const fn new(program_id: &Pubkey) -> Self {
+        Self {
+            program_id: *program_id,
+            check_data: None,
+            check_data_prefix: None,
+            check_accounts: None,
+            check_account_keys: None,
+            check_stack_height: None,
+            check_times: None,
+        }
+    }
+
+    fn matches(&self, cpi: &ResolvedInnerInstruction) -> bool {
+        let instruction = &cpi.instruction;
+        instruction.program_id == self.program_id
+            && self.check_data.is_none_or(|data| instruction.data == data)
+            && self
+                .check_data_prefix
+                .is_none_or(|prefix| instruction.data.starts_with(prefix))
+            && self
+                .check_accounts
+                .is_none_or(|accounts| instruction.accounts == accounts)
+            && self.check_account_keys.is_none_or(|keys| {
+                instruction.accounts.len() == keys.len()
+                    && instruction
+                        .accounts
+                        .iter()
+                        .zip(keys)
+                        .all(|(meta, key)| meta.pubkey == *key)
+            })
+            && self
+                .check_stack_height
+                .is_none_or(|stack_height| cpi.stack_height == stack_height)
+    }
+}
+
+/// A check on an inner instruction (CPI), matching on the invoked program and
+/// any of the provided properties.
+#[cfg(feature = "inner-instructions")]
+#[derive(Debug)]
+pub struct CpiCheckBuilder<'a> {
+    check: CpiCheck<'a>,
+}
+
+#[cfg(feature = "inner-instructions")]
+impl<'a> CpiCheckBuilder<'a> {
+    const fn new(program_id: &Pubkey) -> Self {
+        Self {
+            check: CpiCheck::new(program_id),
+        }
+    }
+
+    /// Match CPIs with exactly this instruction data.
+    pub const fn data(mut self, data: &'a [u8]) -> Self {
+        self.check.check_data = Some(data);
+        self
+    }
+
+    /// Match CPIs whose instruction data starts with this prefix, such as an
+    /// instruction discriminator.
+    pub const fn data_prefix(mut self, prefix: &'a [u8]) -> Self {
+        self.check.check_data_prefix = Some(prefix);
+        self
+    }
+
+    /// Match CPIs with exactly these accounts, including their signer and
+    /// writable flags.
+    pub const fn accounts(mut self, accounts: &'a [AccountMeta]) -> Self {
+        self.check.check_accounts = Some(accounts);
+        self
+    }
+
+    /// Match CPIs with exactly these account keys, regardless of their
+    /// signer and writable flags.
+    pub const fn account_keys(mut self, keys: &'a [Pubkey]) -> Self {
+        self.check.check_account_keys = Some(keys);
+        self
+    }
+
+    /// Match CPIs invoked at this stack height, where `2` is a CPI made
+    /// directly by a top-level instruction.
+    pub const fn stack_height(mut self, stack_height: u32) -> Self {
+        self.check.check_stack_height = Some(stack_height);
+        self
+    }
+
+    /// Require exactly this many matching CPIs.
+    ///
+    /// This is ignored when the check is part of `Check::cpis` or
+    /// `Check::cpis_in_order`.
+    pub const fn times(mut self, times: usize) -> Self {
+        self.check.check_times = Some(times);
+        self
+    }
+
+    pub const fn build(self) -> Check<'a> {
+        Check::new(CheckType::Cpi(self.check))
     }
 }
 
 #[allow(clippy::too_many_arguments)]
 fn run_checks<C: CheckContext>(
     checks: &[Check],
-    config: &Config,
     context: &C,
     compute_units_consumed: u64,
     execution_time: u64,
-    program_result: &ProgramResult,
+    program_result: Result<&ProgramResult, &TransactionError>,
     return_data: &[u8],
     resulting_accounts: &[(Pubkey, Account)],
+    logs: &[String],
     #[cfg(feature = "inner-instructions")] inner_instructions: &[InnerInstruction],
-) -> bool {
-    let c = config;
-    let mut pass = true;
+    #[cfg(feature = "inner-instructions")]
+    resolved_inner_instructions: &[ResolvedInnerInstruction],
+    stop_at_first_mismatch: bool,
+) -> CheckReport {
+    let mut r = CheckReport::default();
     for check in checks {
+        if stop_at_first_mismatch && r.has_mismatch() {
+            break;
+        }
         match &check.check {
             CheckType::ComputeUnitsConsumed(units) => {
                 let check_units = *units;
                 let actual_units = compute_units_consumed;
-                pass &= compare!(c, "compute_units", check_units, actual_units);
+                compare!(r, "compute_units", check_units, actual_units);
+            }
+            CheckType::ComputeUnitsAtMost(units) => {
+                if compute_units_consumed > *units {
+                    r.mismatch(
+                        "compute_units_at_most",
+                        None,
+                        format!("at most {units}"),
+                        compute_units_consumed.to_string(),
+                    );
+                }
+            }
+            CheckType::ComputeUnitsWithin { units, percent } => {
+                let tolerance = compute_units_tolerance(*units, 0, *percent);
+                if units.abs_diff(compute_units_consumed) > tolerance {
+                    r.mismatch(
+                        "compute_units_within",
+                        None,
+                        format!("{units} ± {tolerance} ({percent}%)"),
+                        compute_units_consumed.to_string(),
+                    );
+                }
             }
             CheckType::ExecutionTime(time) => {
                 let check_time = *time;
                 let actual_time = execution_time;
-                pass &= compare!(c, "execution_time", check_time, actual_time);
+                compare!(r, "execution_time", check_time, actual_time);
             }
-            CheckType::ProgramResult(check_program_result) => {
-                let check_result = check_program_result;
-                let actual_result = program_result;
-                pass &= compare!(c, "program_result", check_result, actual_result);
+            CheckType::ProgramResult(check_program_result) => match program_result {
+                Ok(actual_result) => {
+                    let check_result = check_program_result;
+                    compare!(r, "program_result", check_result, actual_result);
+                }
+                Err(actual_err) => r.mismatch(
+                    "program_result",
+                    None,
+                    format!("{:?}", check_program_result),
+                    format!("{:?}", actual_err),
+                ),
+            },
+            CheckType::TransactionError(check_err) => {
+                let check_err = Some(check_err);
+                let actual_err = program_result.err();
+                compare!(r, "transaction_error", check_err, actual_err);
             }
             CheckType::ReturnData(check_return_data) => {
                 let actual_return_data = return_data;
-                pass &= compare!(c, "return_data", *check_return_data, actual_return_data);
+                compare!(r, "return_data", *check_return_data, actual_return_data);
             }
-            CheckType::ResultingAccount(account) => {
+            CheckType::ResultingAccount(account, decoded_checks) => {
                 let pubkey = account.pubkey;
                 let Some(resulting_account) = resulting_accounts
                     .iter()
                     .find(|(k, _)| k == &pubkey)
                     .map(|(_, a)| a)
                 else {
-                    pass &= throw!(c, "Account not found in resulting accounts: {}", pubkey);
+                    r.throw(
+                        "account",
+                        Some(pubkey),
+                        "present".to_string(),
+                        "not found".to_string(),
+                        format!("Account not found in resulting accounts: {}", pubkey),
+                    );
                     continue;
                 };
                 if let Some(check_data) = account.check_data {
                     let actual_data = resulting_account.data();
-                    pass &= compare!(c, "account_data", check_data, actual_data);
+                    if check_data != actual_data {
+                        let diff = account.data_diff(check_data, actual_data, 0);
+                        r.data_mismatch(
+                            "account_data",
+                            Some(pubkey),
+                            diff,
+                            check_data,
+                            actual_data,
+                        );
+                    }
                 }
                 if let Some(check_executable) = account.check_executable {
                     let actual_executable = resulting_account.executable();
-                    pass &= compare!(c, "account_executable", check_executable, actual_executable);
+                    compare!(
+                        r,
+                        "account_executable",
+                        pubkey: pubkey,
+                        check_executable,
+                        actual_executable,
+                    );
                 }
                 if let Some(check_lamports) = account.check_lamports {
                     let actual_lamports = resulting_account.lamports();
-                    pass &= compare!(c, "account_lamports", check_lamports, actual_lamports);
+                    compare!(r, "account_lamports", pubkey: pubkey, check_lamports, actual_lamports);
                 }
                 if let Some(check_owner) = account.check_owner {
                     let actual_owner = resulting_account.owner();
-                    pass &= compare!(c, "account_owner", check_owner, actual_owner);
+                    compare!(r, "account_owner", pubkey: pubkey, check_owner, actual_owner);
                 }
                 if let Some(check_space) = account.check_space {
                     let actual_space = resulting_account.data().len();
-                    pass &= compare!(c, "account_space", check_space, actual_space);
+                    compare!(r, "account_space", pubkey: pubkey, check_space, actual_space);
                 }
                 if let Some(check_state) = &account.check_state {
                     match check_state {
                         AccountStateCheck::Closed => {
-                            pass &= compare!(
-                                c,
+                            compare!(
+                                r,
                                 "account_closed",
+                                pubkey: pubkey,
                                 true,
                                 resulting_account == &Default::default(),
                             );
                         }
                         AccountStateCheck::RentExempt => {
-                            pass &= compare!(
-                                c,
+                            compare!(
+                                r,
                                 "account_rent_exempt",
+                                pubkey: pubkey,
                                 true,
                                 context.is_rent_exempt(
                                     resulting_account.lamports,
@@ -268,21 +680,46 @@ fn run_checks<C: CheckContext>(
                         }
                     }
                 }
+                for check in decoded_checks {
+                    if let Err((expected, actual, message)) = check(resulting_account.data()) {
+                        r.fail(
+                            "account_decoded",
+                            Some(pubkey),
+                            expected,
+                            actual,
+                            format!("CHECK FAILED: account_decoded ({pubkey})\n{message}"),
+                        );
+                    }
+                }
                 if let Some((offset, check_data_slice)) = account.check_data_slice {
                     let actual_data = resulting_account.data();
                     if offset + check_data_slice.len() > actual_data.len() {
-                        pass &= throw!(
-                            c,
-                            "Account data slice: offset {} + slice length {} exceeds account data \
-                             length {}",
-                            offset,
-                            check_data_slice.len(),
-                            actual_data.len(),
+                        r.throw(
+                            "account_data_slice",
+                            Some(pubkey),
+                            format!("{:?}", check_data_slice),
+                            format!("data length {}", actual_data.len()),
+                            format!(
+                                "Account data slice: offset {} + slice length {} exceeds account \
+                                 data length {}",
+                                offset,
+                                check_data_slice.len(),
+                                actual_data.len(),
+                            ),
                         );
                         continue;
                     }
                     let actual_data_slice = &actual_data[offset..offset + check_data_slice.len()];
-                    pass &= compare!(c, "account_data_slice", check_data_slice, actual_data_slice,);
+                    if check_data_slice != actual_data_slice {
+                        let diff = account.data_diff(check_data_slice, actual_data_slice, offset);
+                        r.data_mismatch(
+                            "account_data_slice",
+                            Some(pubkey),
+                            diff,
+                            check_data_slice,
+                            actual_data_slice,
+                        );
+                    }
                 }
             }
             CheckType::AllRentExempt => {
@@ -293,26 +730,136 @@ fn run_checks<C: CheckContext>(
                         account.owner,
                     );
                     if !is_rent_exempt {
-                        pass &= throw!(
-                            c,
-                            "Account {} is not rent exempt after execution (lamports: {}, \
-                             data_len: {})",
-                            pubkey,
-                            account.lamports(),
-                            account.data().len()
+                        r.throw(
+                            "account_rent_exempt",
+                            Some(*pubkey),
+                            format!("{:?}", true),
+                            format!("{:?}", false),
+                            format!(
+                                "Account {} is not rent exempt after execution (lamports: {}, \
+                                 data_len: {})",
+                                pubkey,
+                                account.lamports(),
+                                account.data().len()
+                            ),
                         );
                     }
                 }
             }
+            CheckType::LogsContain(text) => {
+                if !logs.iter().any(|message| message.contains(text)) {
+                    r.fail(
+                        "logs_contain",
+                        None,
+                        format!("{:?}", text),
+                        format!("{:?}", logs),
+                        format!("No log message contains `{}`. Logs: {:#?}", text, logs),
+                    );
+                }
+            }
+            #[cfg(feature = "regex")]
+            CheckType::LogMatches(pattern) => {
+                let regex = match regex::Regex::new(pattern) {
+                    Ok(regex) => regex,
+                    Err(e) => {
+                        r.fail(
+                            "log_matches",
+                            None,
+                            format!("{:?}", pattern),
+                            e.to_string(),
+                            format!("Invalid log regex `{}`: {}", pattern, e),
+                        );
+                        continue;
+                    }
+                };
+                if !logs.iter().any(|message| regex.is_match(message)) {
+                    r.fail(
+                        "log_matches",
+                        None,
+                        format!("{:?}", pattern),
+                        format!("{:?}", logs),
+                        format!("No log message matches `{}`. Logs: {:#?}", pattern, logs),
+                    );
+                }
+            }
+            CheckType::NoLogs => {
+                let program_logs = logs
+                    .iter()
+                    .filter(|message| is_program_log(message))
+                    .collect::<Vec<_>>();
+                compare!(r, "no_logs", Vec::<&String>::new(), program_logs);
+            }
             #[cfg(feature = "inner-instructions")]
             CheckType::InnerInstructionCount(count) => {
                 let check_count = *count;
                 let actual_count = inner_instructions.len();
-                pass &= compare!(c, "inner_instruction_count", check_count, actual_count);
+                compare!(r, "inner_instruction_count", check_count, actual_count);
+            }
+            #[cfg(feature = "inner-instructions")]
+            CheckType::Cpi(cpi) => {
+                let matches = resolved_inner_instructions
+                    .iter()
+                    .filter(|inner| cpi.matches(inner))
+                    .count();
+                let pass_times = match cpi.check_times {
+                    Some(times) => matches == times,
+                    None => matches > 0,
+                };
+                if !pass_times {
+                    let expected = format!("{:?}", cpi);
+                    let actual = format!("{:#?}", resolved_inner_instructions);
+                    r.fail(
+                        "cpi",
+                        None,
+                        expected.clone(),
+                        actual.clone(),
+                        format!(
+                            "CHECK FAILED: cpi\n  Expected: `{}`,\n Got {} matching CPIs: `{}`",
+                            expected, matches, actual,
+                        ),
+                    );
+                }
+            }
+            #[cfg(feature = "inner-instructions")]
+            CheckType::Cpis { cpis, exact } => {
+                let pass_cpis = if *exact {
+                    cpis.len() == resolved_inner_instructions.len()
+                        && cpis
+                            .iter()
+                            .zip(resolved_inner_instructions)
+                            .all(|(cpi, inner)| cpi.check.matches(inner))
+                } else {
+                    let mut remaining = resolved_inner_instructions.iter();
+                    cpis.iter()
+                        .all(|cpi| remaining.any(|inner| cpi.check.matches(inner)))
+                };
+                if !pass_cpis {
+                    let check = if *exact { "cpis" } else { "cpis_in_order" };
+                    r.mismatch(
+                        check,
+                        None,
+                        format!("{:#?}", cpis),
+                        format!("{:#?}", resolved_inner_instructions),
+                    );
+                }
             }
         }
     }
-    pass
+    r
+}
+
+// Whether a log message was logged by a program, rather than by the runtime.
+fn is_program_log(message: &str) -> bool {
+    const PROGRAM_LOG_PREFIXES: &[&str] = &[
+        "Program log: ",
+        "Program data: ",
+        "Program consumption: ",
+        // Logged when programs exceed the log limit.
+        "Log truncated",
+    ];
+    PROGRAM_LOG_PREFIXES
+        .iter()
+        .any(|prefix| message.starts_with(prefix))
 }
 
 impl InstructionResult {
@@ -327,17 +874,37 @@ impl InstructionResult {
         config: &Config,
         context: &C,
     ) -> bool {
+        self.check_until(checks, context, /* stop_at_first_mismatch */ true)
+            .resolve(config)
+    }
+
+    /// Run every check on the instruction result with a custom context,
+    /// returning a report of all failed checks rather than stopping at the
+    /// first.
+    pub fn check_report<C: CheckContext>(&self, checks: &[Check], context: &C) -> CheckReport {
+        self.check_until(checks, context, /* stop_at_first_mismatch */ false)
+    }
+
+    fn check_until<C: CheckContext>(
+        &self,
+        checks: &[Check],
+        context: &C,
+        stop_at_first_mismatch: bool,
+    ) -> CheckReport {
         run_checks(
             checks,
-            config,
             context,
             self.compute_units_consumed,
             self.execution_time,
-            &self.program_result,
+            Ok(&self.program_result),
             &self.return_data,
             &self.resulting_accounts,
+            &self.logs,
             #[cfg(feature = "inner-instructions")]
             &self.inner_instructions,
+            #[cfg(feature = "inner-instructions")]
+            &self.resolved_inner_instructions,
+            stop_at_first_mismatch,
         )
     }
 }
@@ -354,27 +921,51 @@ impl TransactionResult {
         config: &Config,
         context: &C,
     ) -> bool {
+        self.check_until(checks, context, /* stop_at_first_mismatch */ true)
+            .resolve(config)
+    }
+
+    /// Run every check on the transaction result with a custom context,
+    /// returning a report of all failed checks rather than stopping at the
+    /// first.
+    pub fn check_report<C: CheckContext>(&self, checks: &[Check], context: &C) -> CheckReport {
+        self.check_until(checks, context, /* stop_at_first_mismatch */ false)
+    }
+
+    fn check_until<C: CheckContext>(
+        &self,
+        checks: &[Check],
+        context: &C,
+        stop_at_first_mismatch: bool,
+    ) -> CheckReport {
         let program_result = match &self.program_result {
-            TransactionProgramResult::Success => ProgramResult::Success,
-            TransactionProgramResult::Failure(_idx, err) => ProgramResult::Failure(err.clone()),
+            TransactionProgramResult::Success => Ok(ProgramResult::Success),
+            TransactionProgramResult::Failure(_idx, err) => Ok(ProgramResult::Failure(err.clone())),
             TransactionProgramResult::UnknownError(_idx, err) => {
-                ProgramResult::UnknownError(err.clone())
+                Ok(ProgramResult::UnknownError(err.clone()))
             }
+            TransactionProgramResult::TransactionError(err) => Err(err),
         };
         run_checks(
             checks,
-            config,
             context,
             self.compute_units_consumed,
             self.execution_time,
-            &program_result,
+            program_result.as_ref().map_err(|err| *err),
             &self.return_data,
             &self.resulting_accounts,
+            &self.logs,
             #[cfg(feature = "inner-instructions")]
             self.inner_instructions
                 .first()
                 .map(Vec::as_slice)
                 .unwrap_or(&[]),
+            #[cfg(feature = "inner-instructions")]
+            self.resolved_inner_instructions
+                .first()
+                .map(Vec::as_slice)
+                .unwrap_or(&[]),
+            stop_at_first_mismatch,
         )
     }
 }
@@ -2,7 +2,10 @@
 
 use {
     crate::{
+        check::compute_units_tolerance,
         config::{compare, Config},
+        diff::DataDiff,
+        report::CheckReport,
         types::InstructionResult,
     },
     solana_account::ReadableAccount,
@@ -23,12 +26,26 @@ use {
 pub enum Compare {
     /// Validate compute units consumed.
     ComputeUnits,
+    /// Validate compute units consumed, allowing them to differ by up to the
+    /// greater of an absolute number of units and a percentage of the
+    /// expected units, in either direction.
+    ComputeUnitsWithin {
+        /// The number of compute units by which the results may differ.
+        #[cfg_attr(feature = "serde", serde(default))]
+        absolute: u64,
+        /// The percentage of the expected compute units by which the results
+        /// may differ.
+        #[cfg_attr(feature = "serde", serde(default))]
+        percent: f64,
+    },
     /// Validate execution time.
     ExecutionTime,
     /// Validate the program result.
     ProgramResult,
     /// Validate the return data.
     ReturnData,
+    /// Validate the log messages.
+    Logs,
     /// Validate all resulting accounts.
     AllResultingAccounts {
         /// Whether or not to validate each account's data.
@@ -126,6 +143,11 @@ impl Compare {
         }
     }
 
+    /// Returns `true` if this compares compute unit consumption.
+    pub const fn is_compute_units(&self) -> bool {
+        matches!(self, Self::ComputeUnits | Self::ComputeUnitsWithin { .. })
+    }
+
     /// Validate everything but compute unit consumption.
     pub fn everything_but_cus() -> Vec<Self> {
         vec![
@@ -163,73 +185,121 @@ impl InstructionResult {
         addresses: &[Pubkey],
         ignore_addresses: &[Pubkey],
         fields: CompareAccountFields,
-        config: &Config,
-    ) -> bool {
-        let c = config;
-        let mut pass = true;
+        r: &mut CheckReport,
+    ) {
         for (a, b) in self
             .resulting_accounts
             .iter()
             .zip(b.resulting_accounts.iter())
         {
             if addresses.contains(&a.0) && !ignore_addresses.contains(&a.0) {
-                if fields.data {
-                    pass &= compare!(c, "resulting_account_data", a.1.data(), b.1.data());
+                let pubkey = a.0;
+                if fields.data && a.1.data() != b.1.data() {
+                    r.data_mismatch(
+                        "resulting_account_data",
+                        Some(pubkey),
+                        DataDiff::new(a.1.data(), b.1.data()),
+                        a.1.data(),
+                        b.1.data(),
+                    );
                 }
                 if fields.executable {
-                    pass &= compare!(
-                        c,
+                    compare!(
+                        r,
                         "resulting_account_executable",
+                        pubkey: pubkey,
                         a.1.executable(),
                         b.1.executable()
                     );
                 }
                 if fields.lamports {
-                    pass &= compare!(
-                        c,
+                    compare!(
+                        r,
                         "resulting_account_lamports",
+                        pubkey: pubkey,
                         a.1.lamports(),
                         b.1.lamports()
                     );
                 }
                 if fields.owner {
-                    pass &= compare!(c, "resulting_account_owner", a.1.owner(), b.1.owner());
+                    compare!(
+                        r,
+                        "resulting_account_owner",
+                        pubkey: pubkey,
+                        a.1.owner(),
+                        b.1.owner()
+                    );
                 }
                 if fields.space {
-                    pass &= compare!(
-                        c,
+                    compare!(
+                        r,
                         "resulting_account_space",
+                        pubkey: pubkey,
                         a.1.data().len(),
                         b.1.data().len()
                     );
                 }
             }
         }
-        pass
     }
 
     /// Compare an `InstructionResult` against another `InstructionResult`.
     pub fn compare_with_config(&self, b: &Self, checks: &[Compare], config: &Config) -> bool {
-        let c = config;
-        let mut pass = true;
+        self.compare_until(b, checks, /* stop_at_first_mismatch */ true)
+            .resolve(config)
+    }
+
+    /// Compare an `InstructionResult` against another `InstructionResult`,
+    /// returning a report of all mismatches rather than stopping at the
+    /// first.
+    pub fn compare_report(&self, b: &Self, checks: &[Compare]) -> CheckReport {
+        self.compare_until(b, checks, /* stop_at_first_mismatch */ false)
+    }
+
+    fn compare_until(
+        &self,
+        b: &Self,
+        checks: &[Compare],
+        stop_at_first_mismatch: bool,
+    ) -> CheckReport {
+        let mut r = CheckReport::default();
         for check in checks {
+            if stop_at_first_mismatch && r.has_mismatch() {
+                break;
+            }
             match check {
                 Compare::ComputeUnits => {
-                    pass &= compare!(
-                        c,
+                    compare!(
+                        r,
                         "compute_units_consumed",
                         self.compute_units_consumed,
                         b.compute_units_consumed
                     );
                 }
+                Compare::ComputeUnitsWithin { absolute, percent } => {
+                    let expected = self.compute_units_consumed;
+                    let actual = b.compute_units_consumed;
+                    let tolerance = compute_units_tolerance(expected, *absolute, *percent);
+                    if expected.abs_diff(actual) > tolerance {
+                        r.mismatch(
+                            "compute_units_consumed",
+                            None,
+                            format!("{expected} ± {tolerance}"),
+                            actual.to_string(),
+                        );
+                    }
+                }
                 Compare::ExecutionTime => {
-                    pass &= compare!(c, "execution_time", self.execution_time, b.execution_time);
+                    compare!(r, "execution_time", self.execution_time, b.execution_time);
                 }
                 Compare::ProgramResult => {
-                    pass &= compare!(c, "program_result", self.program_result, b.program_result);
+                    compare!(r, "program_result", self.program_result, b.program_result);
                 }
                 Compare::ReturnData => {
-                    pass &= compare!(c, "return_data", self.return_data, b.return_data);
+                    compare!(r, "return_data", self.return_data, b.return_data);
+                }
+                Compare::Logs => {
+                    compare!(r, "logs", self.logs, b.logs);
                 }
                 Compare::AllResultingAccounts {
                     data,
@@ -238,8 +308,8 @@ impl InstructionResult {
                     owner,
                     space,
                 } => {
-                    pass &= compare!(
-                        c,
+                    compare!(
+                        r,
                         "resulting_accounts_length",
                         self.resulting_accounts.len(),
                         b.resulting_accounts.len()
@@ -249,7 +319,7 @@ impl InstructionResult {
                         .iter()
                         .map(|(k, _)| *k)
                         .collect::<Vec<_>>();
-                    pass &= self.compare_resulting_accounts(
+                    self.compare_resulting_accounts(
                         b,
                         &addresses,
                         &[],
@@ -260,7 +330,7 @@ impl InstructionResult {
                             owner: *owner,
                             space: *space,
                         },
-                        c,
+                        &mut r,
                     );
                 }
                 Compare::OnlyResultingAccounts {
@@ -271,7 +341,7 @@ impl InstructionResult {
                     owner,
                     space,
                 } => {
-                    pass &= self.compare_resulting_accounts(
+                    self.compare_resulting_accounts(
                         b,
                         addresses,
                         &[],
@@ -282,7 +352,7 @@ impl InstructionResult {
                             owner: *owner,
                             space: *space,
                         },
-                        c,
+                        &mut r,
                     );
                 }
                 Compare::AllResultingAccountsExcept {
@@ -298,7 +368,7 @@ impl InstructionResult {
                         .iter()
                         .map(|(k, _)| *k)
                         .collect::<Vec<_>>();
-                    pass &= self.compare_resulting_accounts(
+                    self.compare_resulting_accounts(
                         b,
                         &addresses,
                         ignore_addresses,
@@ -309,12 +379,12 @@ impl InstructionResult {
                             owner: *owner,
                             space: *space,
                         },
-                        c,
+                        &mut r,
                     );
                 }
             }
         }
-        pass
+        r
     }
 
     /// Compare an `InstructionResult` against another `InstructionResult`,
@@ -29,38 +29,28 @@ pub trait CheckContext {
     }
 }
 
+// Record a failure in the report if the expected and actual values differ.
 macro_rules! compare {
-    ($c:expr, $check:expr, $left:expr, $right:expr $(,)?) => {{
+    ($r:expr, $check:expr, pubkey: $pubkey:expr, $left:expr, $right:expr $(,)?) => {{
         if $left != $right {
-            let msg = format!(
-                "CHECK FAILED: {}\n  Expected: `{:?}`,\n Got: `{:?}`",
-                $check, $left, $right
+            $r.mismatch(
+                $check,
+                Some($pubkey),
+                format!("{:?}", $left),
+                format!("{:?}", $right),
             );
-            if $c.panic {
-                panic!("{}", msg);
-            } else {
-                if $c.verbose {
-                    println!("{}", msg);
-                }
-                return false;
-            }
         }
-        true
     }};
-}
-
-macro_rules! throw {
-    ($c:expr, $($arg:tt)+) => {{
-        let msg = format!($($arg)+);
-        if $c.panic {
-            panic!("{}", msg);
-        } else {
-            if $c.verbose {
-                eprintln!("{}", msg);
-            }
+    ($r:expr, $check:expr, $left:expr, $right:expr $(,)?) => {{
+        if $left != $right {
+            $r.mismatch(
+                $check,
+                None,
+                format!("{:?}", $left),
+                format!("{:?}", $right),
+            );
         }
-        false
     }};
 }
 
-pub(crate) use {compare, throw};
+pub(crate) use compare;
@@ -0,0 +1,72 @@
+//! Decoders for typed account data checks.
+//!
+//! A decoder turns raw account data into a typed value, so that checks can
+//! assert on fields by name rather than by byte offset. Any closure of the
+//! form `Fn(&[u8]) -> Result<T, String>` is a decoder. Built-in decoders are
+//! available behind features:
+//!
+//! * [`Pod`] (`bytemuck`) - Types implementing `bytemuck::Pod`.
+//! * [`Borsh`] (`borsh`) - Types implementing `borsh::BorshDeserialize`.
+//! * [`Pack`] (`program-pack`) - Types implementing
+//!   `solana_program_pack::Pack`.
+
+/// Decodes account data into a value of type `T`.
+pub trait AccountDecoder<T> {
+    /// Decode the account data, returning a description of the error if the
+    /// data is invalid.
+    fn decode(&self, data: &[u8]) -> Result<T, String>;
+}
+
+impl<T, F> AccountDecoder<T> for F
+where
+    F: Fn(&[u8]) -> Result<T, String>,
+{
+    fn decode(&self, data: &[u8]) -> Result<T, String> {
+        self(data)
+    }
+}
+
+/// Decodes account data as a `bytemuck::Pod` type.
+///
+/// The account data must be exactly `size_of::<T>()` bytes. It does not need
+/// to be aligned.
+#[cfg(feature = "bytemuck")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Pod;
+
+#[cfg(feature = "bytemuck")]
+impl<T: bytemuck::Pod> AccountDecoder<T> for Pod {
+    fn decode(&self, data: &[u8]) -> Result<T, String> {
+        bytemuck::try_pod_read_unaligned(data).map_err(|err| err.to_string())
+    }
+}
+
+/// Decodes account data as a Borsh-serialized type.
+///
+/// Trailing bytes after the serialized value are ignored, since accounts are
+/// often allocated with more space than their current state requires.
+#[cfg(feature = "borsh")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Borsh;
+
+#[cfg(feature = "borsh")]
+impl<T: borsh::BorshDeserialize> AccountDecoder<T> for Borsh {
+    fn decode(&self, data: &[u8]) -> Result<T, String> {
+        T::deserialize(&mut &data[..]).map_err(|err| err.to_string())
+    }
+}
+
+/// Decodes account data as a `solana_program_pack::Pack` type.
+///
+/// The account data must be exactly `T::LEN` bytes. The initialization state
+/// is not checked, so uninitialized accounts can be asserted on too.
+#[cfg(feature = "program-pack")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Pack;
+
+#[cfg(feature = "program-pack")]
+impl<T: solana_program_pack::Pack> AccountDecoder<T> for Pack {
+    fn decode(&self, data: &[u8]) -> Result<T, String> {
+        T::unpack_unchecked(data).map_err(|err| err.to_string())
+    }
+}
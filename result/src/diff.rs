@@ -0,0 +1,428 @@
+//! Human-readable diffs of account data.
+
+use {
+    solana_pubkey::Pubkey,
+    std::{
+        fmt::{self, Display},
+        ops::Range,
+    },
+};
+
+/// The number of bytes rendered per hex row.
+const ROW_LEN: usize = 16;
+/// The number of unchanged rows rendered around each changed row.
+const CONTEXT_ROWS: usize = 1;
+/// The maximum number of rows rendered before the diff is truncated.
+const MAX_ROWS: usize = 64;
+
+/// How to decode a field of account data when rendering a diff.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldKind {
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I64,
+    Bool,
+    Pubkey,
+    /// Raw bytes of the given length, rendered as hex.
+    Bytes(usize),
+}
+
+impl FieldKind {
+    const fn len(&self) -> usize {
+        match self {
+            Self::U8 | Self::Bool => 1,
+            Self::U16 => 2,
+            Self::U32 => 4,
+            Self::U64 | Self::I64 => 8,
+            Self::U128 => 16,
+            Self::Pubkey => 32,
+            Self::Bytes(len) => *len,
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> String {
+        macro_rules! le {
+            ($ty:ty) => {
+                <$ty>::from_le_bytes(bytes.try_into().unwrap()).to_string()
+            };
+        }
+        match self {
+            Self::U8 => bytes[0].to_string(),
+            Self::U16 => le!(u16),
+            Self::U32 => le!(u32),
+            Self::U64 => le!(u64),
+            Self::U128 => le!(u128),
+            Self::I64 => le!(i64),
+            Self::Bool => match bytes[0] {
+                0 => "false".to_string(),
+                1 => "true".to_string(),
+                other => format!("invalid bool ({other})"),
+            },
+            Self::Pubkey => Pubkey::new_from_array(bytes.try_into().unwrap()).to_string(),
+            Self::Bytes(_) => hex(bytes),
+        }
+    }
+}
+
+/// A named field of account data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub offset: usize,
+    pub kind: FieldKind,
+}
+
+impl Field {
+    /// The byte range of the field within the account data.
+    pub const fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.kind.len()
+    }
+}
+
+/// A layout hint for account data, naming the fields at certain offsets so
+/// that diffs can report changed fields by name and decoded value.
+///
+/// ```rust,ignore
+/// let layout = DataLayout::new()
+///     .pubkey("mint", 0)
+///     .pubkey("owner", 32)
+///     .u64("amount", 64);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DataLayout {
+    pub fields: Vec<Field>,
+}
+
+impl DataLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a field of any kind at the given offset.
+    pub fn field(mut self, name: &str, offset: usize, kind: FieldKind) -> Self {
+        self.fields.push(Field {
+            name: name.to_string(),
+            offset,
+            kind,
+        });
+        self
+    }
+
+    pub fn u8(self, name: &str, offset: usize) -> Self {
+        self.field(name, offset, FieldKind::U8)
+    }
+
+    pub fn u16(self, name: &str, offset: usize) -> Self {
+        self.field(name, offset, FieldKind::U16)
+    }
+
+    pub fn u32(self, name: &str, offset: usize) -> Self {
+        self.field(name, offset, FieldKind::U32)
+    }
+
+    pub fn u64(self, name: &str, offset: usize) -> Self {
+        self.field(name, offset, FieldKind::U64)
+    }
+
+    pub fn u128(self, name: &str, offset: usize) -> Self {
+        self.field(name, offset, FieldKind::U128)
+    }
+
+    pub fn i64(self, name: &str, offset: usize) -> Self {
+        self.field(name, offset, FieldKind::I64)
+    }
+
+    pub fn bool(self, name: &str, offset: usize) -> Self {
+        self.field(name, offset, FieldKind::Bool)
+    }
+
+    pub fn pubkey(self, name: &str, offset: usize) -> Self {
+        self.field(name, offset, FieldKind::Pubkey)
+    }
+
+    pub fn bytes(self, name: &str, offset: usize, len: usize) -> Self {
+        self.field(name, offset, FieldKind::Bytes(len))
+    }
+}
+
+/// A diff between expected and actual account data.
+///
+/// Renders, with `Display`:
+///
+/// * The change in length, if any.
+/// * Each changed field of the layout, if provided, with its decoded values.
+/// * Hex rows with offsets around each changed range, marking the changed
+///   bytes.
+///
+/// ```text
+/// length: 82 -> 80 (-2)
+/// field 'amount' @ 64..72: 100 -> 90
+/// - 00000040  64 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+/// + 00000040  5a 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00
+///             ^^
+/// ```
+pub struct DataDiff<'a> {
+    expected: &'a [u8],
+    actual: &'a [u8],
+    offset: usize,
+    layout: Option<&'a DataLayout>,
+}
+
+impl<'a> DataDiff<'a> {
+    pub const fn new(expected: &'a [u8], actual: &'a [u8]) -> Self {
+        Self {
+            expected,
+            actual,
+            offset: 0,
+            layout: None,
+        }
+    }
+
+    /// The offset of the compared data within the account data, such as
+    /// when comparing a slice. Offsets are rendered relative to the account
+    /// data, and layout fields are matched against them.
+    pub const fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Name the changed fields using the provided layout.
+    pub const fn layout(mut self, layout: &'a DataLayout) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    /// Returns `true` if the expected and actual data are identical.
+    pub fn is_empty(&self) -> bool {
+        self.expected == self.actual
+    }
+
+    fn write_fields(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(layout) = self.layout else {
+            return Ok(());
+        };
+        for field in &layout.fields {
+            let range = field.range();
+            let Some(relative) = range
+                .start
+                .checked_sub(self.offset)
+                .map(|start| start..start + field.kind.len())
+            else {
+                continue;
+            };
+            let expected = self.expected.get(relative.clone());
+            let actual = self.actual.get(relative);
+            if expected.is_none() && actual.is_none() || expected == actual {
+                continue;
+            }
+            let render = |bytes: Option<&[u8]>| {
+                bytes.map_or("<missing>".to_string(), |bytes| field.kind.decode(bytes))
+            };
+            writeln!(
+                f,
+                "field '{}' @ {}..{}: {} -> {}",
+                field.name,
+                range.start,
+                range.end,
+                render(expected),
+                render(actual),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_rows(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let len = self.expected.len().max(self.actual.len());
+        let row_count = len.div_ceil(ROW_LEN);
+        let changed_rows = (0..row_count)
+            .filter(|row| {
+                let range = row * ROW_LEN..((row + 1) * ROW_LEN).min(len);
+                range
+                    .into_iter()
+                    .any(|i| self.expected.get(i) != self.actual.get(i))
+            })
+            .collect::<Vec<_>>();
+
+        // Expand each changed row by its context, merging overlapping rows.
+        let mut rows: Vec<usize> = vec![];
+        for row in changed_rows {
+            let start = row.saturating_sub(CONTEXT_ROWS);
+            let end = (row + CONTEXT_ROWS).min(row_count - 1);
+            for row in start..=end {
+                if rows.last().is_none_or(|last| *last < row) {
+                    rows.push(row);
+                }
+            }
+        }
+
+        let mut previous = None;
+        for (rendered, row) in rows.iter().enumerate() {
+            if rendered == MAX_ROWS {
+                writeln!(f, "... ({} more rows)", rows.len() - MAX_ROWS)?;
+                break;
+            }
+            if previous.is_some_and(|previous| previous + 1 != *row) {
+                writeln!(f, "...")?;
+            }
+            previous = Some(*row);
+            self.write_row(f, row * ROW_LEN..((row + 1) * ROW_LEN).min(len))?;
+        }
+        Ok(())
+    }
+
+    fn write_row(&self, f: &mut fmt::Formatter<'_>, range: Range<usize>) -> fmt::Result {
+        let address = self.offset + range.start;
+        let cells = |data: &[u8]| {
+            range
+                .clone()
+                .map(|i| data.get(i).map_or("--".to_string(), |b| format!("{b:02x}")))
+                .collect::<Vec<_>>()
+        };
+        let markers = range
+            .clone()
+            .map(|i| {
+                if self.expected.get(i) != self.actual.get(i) {
+                    "^^"
+                } else {
+                    "  "
+                }
+                .to_string()
+            })
+            .collect::<Vec<_>>();
+        writeln!(f, "- {address:08x}  {}", join_cells(&cells(self.expected)))?;
+        writeln!(f, "+ {address:08x}  {}", join_cells(&cells(self.actual)))?;
+        if markers.iter().any(|marker| marker != "  ") {
+            writeln!(f, "            {}", join_cells(&markers).trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for DataDiff<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no differences");
+        }
+        let (expected_len, actual_len) = (self.expected.len(), self.actual.len());
+        if expected_len != actual_len {
+            writeln!(
+                f,
+                "length: {} -> {} ({:+})",
+                expected_len,
+                actual_len,
+                actual_len as i128 - expected_len as i128,
+            )?;
+        }
+        self.write_fields(f)?;
+        self.write_rows(f)
+    }
+}
+
+/// A line diff between the pretty `Debug` renderings of an expected and
+/// actual value, such as decoded account state.
+///
+/// Renders, with `Display`, the changed lines prefixed by `-` and `+`, with
+/// one line of unchanged context around each change.
+///
+/// ```text
+///   Account {
+///       mint: 11111111111111111111111111111111,
+/// -     amount: 100,
+/// +     amount: 90,
+///       delegate: None,
+/// ...
+/// ```
+pub struct DebugDiff {
+    expected: String,
+    actual: String,
+}
+
+impl DebugDiff {
+    pub fn new<T: fmt::Debug>(expected: &T, actual: &T) -> Self {
+        Self {
+            expected: format!("{expected:#?}"),
+            actual: format!("{actual:#?}"),
+        }
+    }
+
+    /// Returns `true` if the expected and actual renderings are identical.
+    pub fn is_empty(&self) -> bool {
+        self.expected == self.actual
+    }
+
+    // The lines of both renderings, aligned by their longest common
+    // subsequence.
+    fn lines(&self) -> Vec<(char, &str)> {
+        let expected = self.expected.lines().collect::<Vec<_>>();
+        let actual = self.actual.lines().collect::<Vec<_>>();
+        let (n, m) = (expected.len(), actual.len());
+
+        // lcs[i][j] is the length of the LCS of expected[i..] and actual[j..].
+        let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i][j] = if expected[i] == actual[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+
+        let mut lines = vec![];
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && expected[i] == actual[j] {
+                lines.push((' ', expected[i]));
+                i += 1;
+                j += 1;
+            } else if j == m || (i < n && lcs[i + 1][j] >= lcs[i][j + 1]) {
+                lines.push(('-', expected[i]));
+                i += 1;
+            } else {
+                lines.push(('+', actual[j]));
+                j += 1;
+            }
+        }
+        lines
+    }
+}
+
+impl Display for DebugDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no differences");
+        }
+        let lines = self.lines();
+        let changed = |i: usize| lines.get(i).is_some_and(|(tag, _)| *tag != ' ');
+        let mut previous = None;
+        for (i, (tag, line)) in lines.iter().enumerate() {
+            let start = i.saturating_sub(CONTEXT_ROWS);
+            if !(start..=i + CONTEXT_ROWS).any(changed) {
+                continue;
+            }
+            if previous.is_some_and(|previous| previous + 1 != i) {
+                writeln!(f, "...")?;
+            }
+            previous = Some(i);
+            writeln!(f, "{tag} {line}")?;
+        }
+        Ok(())
+    }
+}
+
+// Join hex cells with a space, and an extra space between each half row.
+fn join_cells(cells: &[String]) -> String {
+    cells
+        .chunks(ROW_LEN / 2)
+        .map(|half| half.join(" "))
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
@@ -1,6 +1,8 @@
 //! Fuzz fixture conversions for instruction results.
 
 use crate::types::{InstructionResult, ProgramResult};
+#[cfg(feature = "inner-instructions")]
+use {crate::types::ResolvedInnerInstruction, mollusk_svm_fuzz_fixture::effects::InnerInstruction};
 
 impl From<&InstructionResult> for mollusk_svm_fuzz_fixture::effects::Effects {
     fn from(input: &InstructionResult) -> Self {
@@ -16,12 +18,25 @@ impl From<&InstructionResult> for mollusk_svm_fuzz_fixture::effects::Effects {
 
         let resulting_accounts = input.resulting_accounts.clone();
 
+        #[cfg(feature = "inner-instructions")]
+        let inner_instructions = input
+            .resolved_inner_instructions
+            .iter()
+            .map(|inner_instruction| InnerInstruction {
+                instruction: inner_instruction.instruction.clone(),
+                stack_height: inner_instruction.stack_height,
+            })
+            .collect();
+        #[cfg(not(feature = "inner-instructions"))]
+        let inner_instructions = vec![];
+
         Self {
             compute_units_consumed,
             execution_time,
             program_result,
             return_data,
             resulting_accounts,
+            inner_instructions,
         }
     }
 }
@@ -51,10 +66,24 @@ impl From<&mollusk_svm_fuzz_fixture::effects::Effects> for InstructionResult {
             raw_result,
             return_data,
             resulting_accounts,
+            logs: vec![],
             #[cfg(feature = "inner-instructions")]
             inner_instructions: vec![],
             #[cfg(feature = "inner-instructions")]
+            resolved_inner_instructions: input
+                .inner_instructions
+                .iter()
+                .map(|inner_instruction| ResolvedInnerInstruction {
+                    instruction: inner_instruction.instruction.clone(),
+                    stack_height: inner_instruction.stack_height,
+                })
+                .collect(),
+            #[cfg(feature = "inner-instructions")]
             message: None,
+            #[cfg(feature = "syscall-trace")]
+            syscall_trace: vec![],
+            #[cfg(feature = "invocation-tree")]
+            invocations: vec![],
         }
     }
 }
@@ -15,6 +15,8 @@
 //! * [`Compare`] - Compare two instruction results
 //! * [`Config`] - Configuration for validation behavior
 //! * [`CheckContext`] - Context trait for custom validation logic
+//! * [`CheckReport`] - Every failed check from a single run
+//! * [`AccountDecoder`] - Decode account data for typed checks
 //!
 //! # Example
 //!
@@ -31,15 +33,22 @@
 pub mod check;
 pub mod compare;
 pub mod config;
+pub mod decode;
+pub mod diff;
 #[cfg(feature = "fuzz")]
 pub mod fuzz;
+pub mod report;
 pub mod types;
 
 // Re-export the main types and traits for convenience, and for backwards
 // compatibility.
+#[cfg(feature = "inner-instructions")]
+pub use check::CpiCheckBuilder;
 pub use {
-    check::{AccountCheckBuilder, Check},
+    check::{AccountCheckBuilder, Check, DecodedAccountCheckBuilder},
     compare::Compare,
     config::{CheckContext, Config},
+    decode::AccountDecoder,
+    report::CheckReport,
     types::{InstructionResult, ProgramResult},
 };
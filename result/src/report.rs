@@ -0,0 +1,188 @@
+//! Structured reports of failed checks and comparisons.
+
+use {
+    crate::{config::Config, diff::DataDiff},
+    solana_pubkey::Pubkey,
+    std::fmt::{self, Display},
+};
+
+/// A single failed check or comparison.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CheckFailure {
+    /// The name of the failed check, such as `account_lamports`.
+    pub check: String,
+    /// The account the check was run against, if any.
+    pub pubkey: Option<Pubkey>,
+    /// The expected value, formatted with `Debug`.
+    pub expected: String,
+    /// The actual value, formatted with `Debug`.
+    pub actual: String,
+    /// A human-readable description of the failure.
+    pub message: String,
+}
+
+impl CheckFailure {
+    fn mismatch(check: &str, pubkey: Option<Pubkey>, expected: String, actual: String) -> Self {
+        let message = match pubkey {
+            Some(pubkey) => format!(
+                "CHECK FAILED: {check} ({pubkey})\n  Expected: `{expected}`,\n Got: `{actual}`"
+            ),
+            None => format!("CHECK FAILED: {check}\n  Expected: `{expected}`,\n Got: `{actual}`"),
+        };
+        Self {
+            check: check.to_string(),
+            pubkey,
+            expected,
+            actual,
+            message,
+        }
+    }
+}
+
+impl Display for CheckFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Every failed check or comparison from a single run, in the order the
+/// checks were provided.
+///
+/// Unlike `run_checks` and `compare_with_config`, which stop at the first
+/// mismatch, producing a report runs every check. The report can be rendered
+/// with `Display`, or converted into an error with `into_result`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CheckReport {
+    /// The failed checks.
+    pub failures: Vec<CheckFailure>,
+    // The index of the first failure recorded as a mismatch, which stops
+    // `run_checks`. Failures recorded with `throw` don't.
+    first_mismatch: Option<usize>,
+}
+
+impl CheckReport {
+    /// Returns `true` if every check passed.
+    pub fn is_pass(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Convert the report into a `Result`, which is an error if any check
+    /// failed.
+    pub fn into_result(self) -> Result<(), Self> {
+        if self.is_pass() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Record a mismatch between an expected and actual value.
+    pub(crate) fn mismatch(
+        &mut self,
+        check: &str,
+        pubkey: Option<Pubkey>,
+        expected: String,
+        actual: String,
+    ) {
+        self.first_mismatch.get_or_insert(self.failures.len());
+        self.failures
+            .push(CheckFailure::mismatch(check, pubkey, expected, actual));
+    }
+
+    /// Record a mismatch between expected and actual account data, rendered
+    /// as a diff.
+    pub(crate) fn data_mismatch(
+        &mut self,
+        check: &str,
+        pubkey: Option<Pubkey>,
+        diff: DataDiff,
+        expected: &[u8],
+        actual: &[u8],
+    ) {
+        let message = match pubkey {
+            Some(pubkey) => format!("CHECK FAILED: {check} ({pubkey})\n{diff}"),
+            None => format!("CHECK FAILED: {check}\n{diff}"),
+        };
+        self.fail(
+            check,
+            pubkey,
+            format!("{:?}", expected),
+            format!("{:?}", actual),
+            message.trim_end().to_string(),
+        );
+    }
+
+    /// Record a failure with a custom message.
+    pub(crate) fn fail(
+        &mut self,
+        check: &str,
+        pubkey: Option<Pubkey>,
+        expected: String,
+        actual: String,
+        message: String,
+    ) {
+        self.first_mismatch.get_or_insert(self.failures.len());
+        self.throw(check, pubkey, expected, actual, message);
+    }
+
+    /// Record a failure that doesn't stop `run_checks`, such as an account
+    /// missing from the result. It's reported to stderr rather than stdout.
+    pub(crate) fn throw(
+        &mut self,
+        check: &str,
+        pubkey: Option<Pubkey>,
+        expected: String,
+        actual: String,
+        message: String,
+    ) {
+        self.failures.push(CheckFailure {
+            check: check.to_string(),
+            pubkey,
+            expected,
+            actual,
+            message,
+        });
+    }
+
+    /// Returns `true` once a mismatch has been recorded, after which
+    /// `run_checks` stops.
+    pub(crate) fn has_mismatch(&self) -> bool {
+        self.first_mismatch.is_some()
+    }
+
+    /// Resolve a report that stopped at the first mismatch according to the
+    /// config, panicking on the first failure if configured to do so.
+    pub(crate) fn resolve(self, config: &Config) -> bool {
+        let resolved = self
+            .first_mismatch
+            .map_or(self.failures.len(), |index| index + 1);
+        for (index, failure) in self.failures[..resolved].iter().enumerate() {
+            if config.panic {
+                panic!("{}", failure);
+            }
+            if config.verbose {
+                if Some(index) == self.first_mismatch {
+                    println!("{}", failure);
+                } else {
+                    eprintln!("{}", failure);
+                }
+            }
+        }
+        self.is_pass()
+    }
+}
+
+impl Display for CheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_pass() {
+            return write!(f, "All checks passed");
+        }
+        write!(f, "{} check(s) failed", self.failures.len())?;
+        for failure in &self.failures {
+            write!(f, "\n\n{}", failure)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CheckReport {}
@@ -1,5 +1,14 @@
 //! Core result types for SVM program execution.
 
+#[cfg(any(feature = "inner-instructions", feature = "invocation-tree"))]
+use solana_instruction::Instruction;
+#[cfg(any(feature = "invocation-tree", feature = "syscall-trace"))]
+use std::fmt;
+#[cfg(feature = "syscall-trace")]
+use {
+    num_format::{Locale, ToFormattedString},
+    std::collections::HashMap,
+};
 use {
     solana_account::Account, solana_instruction::error::InstructionError,
     solana_program_error::ProgramError, solana_pubkey::Pubkey,
@@ -65,6 +74,9 @@ pub struct InstructionResult {
     /// they were provided. Any accounts that were modified will maintain
     /// their original position in this list, but with updated state.
     pub resulting_accounts: Vec<(Pubkey, Account)>,
+    /// The log messages emitted during the instruction execution, including
+    /// those written by the runtime, such as `Program <id> invoke [1]`.
+    pub logs: Vec<String>,
     /// Inner instructions (CPIs) invoked during the instruction execution.
     ///
     /// Each entry represents a cross-program invocation made by the program,
@@ -72,6 +84,16 @@ pub struct InstructionResult {
     /// was called.
     #[cfg(feature = "inner-instructions")]
     pub inner_instructions: Vec<InnerInstruction>,
+    /// Inner instructions (CPIs) invoked during the instruction execution,
+    /// resolved to the pubkeys of their program and accounts.
+    ///
+    /// Unlike `inner_instructions`, these don't need the compiled message to
+    /// be interpreted, so they're also available for results loaded from a
+    /// fuzz fixture. Each account's signer and writable flags are those it
+    /// was invoked with, so accounts signed for by a program-derived address
+    /// are marked as signers.
+    #[cfg(feature = "inner-instructions")]
+    pub resolved_inner_instructions: Vec<ResolvedInnerInstruction>,
     /// The compiled message used to execute the instruction.
     ///
     /// This can be used to map account indices in inner instructions back to
@@ -81,6 +103,14 @@ pub struct InstructionResult {
     /// fixtures don't contain the compiled message.
     #[cfg(feature = "inner-instructions")]
     pub message: Option<SanitizedMessage>,
+    /// Every syscall invoked during the instruction execution, in the order
+    /// they were invoked.
+    #[cfg(feature = "syscall-trace")]
+    pub syscall_trace: Vec<SyscallTraceEntry>,
+    /// The tree of program invocations made during the instruction
+    /// execution, with one root per processed instruction.
+    #[cfg(feature = "invocation-tree")]
+    pub invocations: Vec<Invocation>,
 }
 
 impl Default for InstructionResult {
@@ -92,10 +122,17 @@ impl Default for InstructionResult {
             raw_result: Ok(()),
             return_data: vec![],
             resulting_accounts: vec![],
+            logs: vec![],
             #[cfg(feature = "inner-instructions")]
             inner_instructions: vec![],
             #[cfg(feature = "inner-instructions")]
+            resolved_inner_instructions: vec![],
+            #[cfg(feature = "inner-instructions")]
             message: None,
+            #[cfg(feature = "syscall-trace")]
+            syscall_trace: vec![],
+            #[cfg(feature = "invocation-tree")]
+            invocations: vec![],
         }
     }
 }
@@ -116,11 +153,23 @@ impl InstructionResult {
         self.raw_result = other.raw_result;
         self.return_data = other.return_data;
         self.resulting_accounts = other.resulting_accounts;
+        self.logs.extend(other.logs);
         #[cfg(feature = "inner-instructions")]
         {
             self.inner_instructions = other.inner_instructions;
+            self.resolved_inner_instructions = other.resolved_inner_instructions;
             self.message = other.message;
         }
+        #[cfg(feature = "syscall-trace")]
+        self.syscall_trace.extend(other.syscall_trace);
+        #[cfg(feature = "invocation-tree")]
+        self.invocations.extend(other.invocations);
+    }
+
+    /// Aggregate the syscall trace by syscall name.
+    #[cfg(feature = "syscall-trace")]
+    pub fn syscall_summary(&self) -> SyscallSummary {
+        SyscallSummary::from_trace(&self.syscall_trace)
     }
 }
 
@@ -133,6 +182,9 @@ pub enum TransactionProgramResult {
     Failure(usize, ProgramError),
     /// Mollusk encountered an error while executing the program.
     UnknownError(usize, InstructionError),
+    /// The runtime rejected the transaction outside of program execution,
+    /// such as while validating the fee payer.
+    TransactionError(TransactionError),
 }
 
 impl TransactionProgramResult {
@@ -147,17 +199,44 @@ impl TransactionProgramResult {
     }
 }
 
+/// The fees charged to a transaction's fee payer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TransactionFee {
+    /// The base fee charged for the transaction's signatures.
+    pub signature_fee: u64,
+    /// The additional fee charged for the requested compute unit price.
+    pub prioritization_fee: u64,
+}
+
+impl TransactionFee {
+    /// The total fee charged to the fee payer.
+    pub const fn total(&self) -> u64 {
+        self.signature_fee.saturating_add(self.prioritization_fee)
+    }
+}
+
 /// The overall result of the transaction.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct TransactionResult {
     /// The number of compute units consumed by the transaction.
     pub compute_units_consumed: u64,
+    /// The number of compute units consumed by each processed top-level
+    /// instruction, in order.
+    ///
+    /// If an instruction fails, it's the last entry, and the remaining
+    /// instructions have no entries.
+    pub instruction_compute_units: Vec<u64>,
     /// The time taken to execute the transaction.
     pub execution_time: u64,
     /// The result code of the last program's execution and its index.
     pub program_result: TransactionProgramResult,
     /// The raw result of the program's execution.
     pub raw_result: Result<(), TransactionError>,
+    /// The fees charged to the fee payer.
+    ///
+    /// Fees are only charged when processing a transaction with
+    /// `Mollusk::process_transaction`. Otherwise, this is zero.
+    pub fee: TransactionFee,
     /// The return data produced by the transaction, if any.
     pub return_data: Vec<u8>,
     /// The resulting accounts after executing the transaction.
@@ -166,6 +245,9 @@ pub struct TransactionResult {
     /// they were provided. Any accounts that were modified will maintain
     /// their original position in this list, but with updated state.
     pub resulting_accounts: Vec<(Pubkey, Account)>,
+    /// The log messages emitted during the transaction execution, including
+    /// those written by the runtime, such as `Program <id> invoke [1]`.
+    pub logs: Vec<String>,
     /// Inner instructions (CPIs) invoked during the transaction execution.
     ///
     /// Each entry represents a cross-program invocation made by the program,
@@ -173,6 +255,13 @@ pub struct TransactionResult {
     /// was called.
     #[cfg(feature = "inner-instructions")]
     pub inner_instructions: Vec<Vec<InnerInstruction>>,
+    /// Inner instructions (CPIs) invoked during the transaction execution,
+    /// resolved to the pubkeys of their program and accounts.
+    ///
+    /// Like `inner_instructions`, these are grouped by the top-level
+    /// instruction which invoked them.
+    #[cfg(feature = "inner-instructions")]
+    pub resolved_inner_instructions: Vec<Vec<ResolvedInnerInstruction>>,
     /// The compiled message used to execute the transaction.
     ///
     /// This can be used to map account indices in inner instructions back to
@@ -182,6 +271,14 @@ pub struct TransactionResult {
     /// fixtures don't contain the compiled message.
     #[cfg(feature = "inner-instructions")]
     pub message: Option<SanitizedMessage>,
+    /// Every syscall invoked during the transaction execution, in the order
+    /// they were invoked.
+    #[cfg(feature = "syscall-trace")]
+    pub syscall_trace: Vec<SyscallTraceEntry>,
+    /// The tree of program invocations made during the transaction
+    /// execution, with one root per processed top-level instruction.
+    #[cfg(feature = "invocation-tree")]
+    pub invocations: Vec<Invocation>,
 }
 
 impl TransactionResult {
@@ -192,4 +289,320 @@ impl TransactionResult {
             .find(|(k, _)| k == pubkey)
             .map(|(_, a)| a)
     }
+
+    /// Aggregate the syscall trace by syscall name.
+    #[cfg(feature = "syscall-trace")]
+    pub fn syscall_summary(&self) -> SyscallSummary {
+        SyscallSummary::from_trace(&self.syscall_trace)
+    }
+}
+
+/// An inner instruction (CPI), resolved to the pubkeys of its program and
+/// accounts.
+#[cfg(feature = "inner-instructions")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResolvedInnerInstruction {
+    /// The invoked instruction.
+    pub instruction: Instruction,
+    /// The stack height of the invocation, where `2` is a CPI made directly
+    /// by a top-level instruction.
+    pub stack_height: u32,
+}
+
+/// A program invocation, either a top-level instruction or a CPI, along with
+/// the invocations it made.
+#[cfg(feature = "invocation-tree")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Invocation {
+    /// The invoked program.
+    pub program_id: Pubkey,
+    /// The stack height of the invocation, where `1` is a top-level
+    /// instruction and each CPI adds one.
+    pub stack_height: u32,
+    /// The invoked instruction.
+    pub instruction: Instruction,
+    /// The number of compute units consumed by the invocation, including its
+    /// inner invocations.
+    ///
+    /// This is read from the runtime's logs for CPIs, so it's `None` for
+    /// programs which don't report it, such as builtins, or if the logs were
+    /// truncated.
+    pub compute_units_consumed: Option<u64>,
+    /// The result of the invocation.
+    pub program_result: ProgramResult,
+    /// The return data set by the invocation, if any.
+    pub return_data: Vec<u8>,
+    /// The log messages emitted by the invocation itself, excluding those of
+    /// its inner invocations and the runtime's `invoke`, `consumed`,
+    /// `success` and `failed` messages.
+    pub logs: Vec<String>,
+    /// The invocations (CPIs) made by this invocation, in the order they were
+    /// made.
+    pub inner_invocations: Vec<Invocation>,
+}
+
+#[cfg(feature = "invocation-tree")]
+impl Invocation {
+    /// Iterate over this invocation and all of its inner invocations, depth
+    /// first, in the order they were invoked.
+    pub fn iter(&self) -> impl Iterator<Item = &Invocation> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let invocation = stack.pop()?;
+            stack.extend(invocation.inner_invocations.iter().rev());
+            Some(invocation)
+        })
+    }
+}
+
+#[cfg(feature = "invocation-tree")]
+impl fmt::Display for Invocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for invocation in self.iter() {
+            let indent = "  ".repeat(invocation.stack_height.saturating_sub(1) as usize);
+            write!(
+                f,
+                "{indent}{} [{}]",
+                invocation.program_id, invocation.stack_height
+            )?;
+            if let Some(compute_units_consumed) = invocation.compute_units_consumed {
+                write!(f, " {compute_units_consumed} CU")?;
+            }
+            match &invocation.program_result {
+                ProgramResult::Success => writeln!(f, " success")?,
+                ProgramResult::Failure(err) => writeln!(f, " failed: {err}")?,
+                ProgramResult::UnknownError(err) => writeln!(f, " failed: {err}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A syscall invoked during execution.
+#[cfg(feature = "syscall-trace")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyscallTraceEntry {
+    /// The name of the syscall, such as `sol_log_`.
+    pub name: String,
+    /// The program that invoked the syscall.
+    pub program_id: Pubkey,
+    /// The stack height the syscall was invoked at, where `1` is a top-level
+    /// instruction and each CPI adds one.
+    pub stack_height: u32,
+    /// The raw arguments passed to the syscall. Pointers are VM addresses.
+    pub args: [u64; 5],
+    /// The number of compute units charged by the syscall.
+    ///
+    /// For syscalls that invoke other programs, such as
+    /// `sol_invoke_signed_rust`, this includes the compute units consumed by
+    /// the invoked program.
+    pub compute_units_consumed: u64,
+}
+
+/// A syscall argument, named after the syscall's parameter.
+#[cfg(feature = "syscall-trace")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyscallArg {
+    /// A VM address, such as a pointer to a buffer.
+    Address(&'static str, u64),
+    /// A plain value, such as a length.
+    Value(&'static str, u64),
+}
+
+#[cfg(feature = "syscall-trace")]
+impl fmt::Display for SyscallArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Address(name, address) => write!(f, "{name}: {address:#x}"),
+            Self::Value(name, value) => write!(f, "{name}: {value}"),
+        }
+    }
+}
+
+#[cfg(feature = "syscall-trace")]
+impl SyscallTraceEntry {
+    /// Decode the raw arguments by the syscall's signature, omitting unused
+    /// arguments.
+    ///
+    /// Returns `None` for syscalls with an unknown signature, such as custom
+    /// syscalls.
+    pub fn decoded_args(&self) -> Option<Vec<SyscallArg>> {
+        use SyscallArg::{Address as A, Value as V};
+        #[allow(clippy::type_complexity)]
+        let signature: &[(fn(&'static str, u64) -> SyscallArg, &'static str)] =
+            match self.name.as_str() {
+                "abort"
+                | "sol_log_compute_units_"
+                | "sol_get_stack_height"
+                | "sol_remaining_compute_units" => &[],
+                "sol_panic_" => &[(A, "file"), (V, "len"), (V, "line"), (V, "column")],
+                "sol_log_" => &[(A, "message"), (V, "len")],
+                "sol_log_64_" => &[
+                    (V, "arg1"),
+                    (V, "arg2"),
+                    (V, "arg3"),
+                    (V, "arg4"),
+                    (V, "arg5"),
+                ],
+                "sol_log_pubkey" => &[(A, "pubkey")],
+                "sol_log_data" => &[(A, "fields"), (V, "len")],
+                "sol_create_program_address" => &[
+                    (A, "seeds"),
+                    (V, "seeds_len"),
+                    (A, "program_id"),
+                    (A, "address"),
+                ],
+                "sol_try_find_program_address" => &[
+                    (A, "seeds"),
+                    (V, "seeds_len"),
+                    (A, "program_id"),
+                    (A, "address"),
+                    (A, "bump_seed"),
+                ],
+                "sol_sha256" | "sol_keccak256" | "sol_blake3" => {
+                    &[(A, "vals"), (V, "vals_len"), (A, "result")]
+                }
+                "sol_secp256k1_recover" => &[
+                    (A, "hash"),
+                    (V, "recovery_id"),
+                    (A, "signature"),
+                    (A, "result"),
+                ],
+                "sol_get_clock_sysvar"
+                | "sol_get_epoch_schedule_sysvar"
+                | "sol_get_fees_sysvar"
+                | "sol_get_rent_sysvar"
+                | "sol_get_epoch_rewards_sysvar"
+                | "sol_get_last_restart_slot" => &[(A, "var")],
+                "sol_get_sysvar" => &[(A, "sysvar_id"), (A, "var"), (V, "offset"), (V, "len")],
+                "sol_memcpy_" | "sol_memmove_" => &[(A, "dst"), (A, "src"), (V, "n")],
+                "sol_memcmp_" => &[(A, "s1"), (A, "s2"), (V, "n"), (A, "result")],
+                "sol_memset_" => &[(A, "s"), (V, "c"), (V, "n")],
+                "sol_invoke_signed_c" | "sol_invoke_signed_rust" => &[
+                    (A, "instruction"),
+                    (A, "account_infos"),
+                    (V, "account_infos_len"),
+                    (A, "signers_seeds"),
+                    (V, "signers_seeds_len"),
+                ],
+                "sol_set_return_data" => &[(A, "data"), (V, "len")],
+                "sol_get_return_data" => &[(A, "data"), (V, "len"), (A, "program_id")],
+                "sol_get_processed_sibling_instruction" => &[
+                    (V, "index"),
+                    (A, "meta"),
+                    (A, "program_id"),
+                    (A, "data"),
+                    (A, "accounts"),
+                ],
+                "sol_get_epoch_stake" => &[(A, "vote_address")],
+                "sol_alloc_free_" => &[(V, "size"), (A, "free_addr")],
+                _ => return None,
+            };
+        Some(
+            signature
+                .iter()
+                .zip(self.args)
+                .map(|((decode, name), value)| decode(name, value))
+                .collect(),
+        )
+    }
+}
+
+/// Displays the call with its decoded arguments, or the raw arguments for
+/// syscalls with an unknown signature, and the compute units it charged:
+///
+/// ```text
+/// sol_log_(message: 0x100000f20, len: 5): 105 CU
+/// ```
+#[cfg(feature = "syscall-trace")]
+impl fmt::Display for SyscallTraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let args = match self.decoded_args() {
+            Some(args) => args.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            None => self.args.iter().map(ToString::to_string).collect(),
+        };
+        write!(
+            f,
+            "{}({}): {} CU",
+            self.name,
+            args.join(", "),
+            self.compute_units_consumed.to_formatted_string(&Locale::en),
+        )
+    }
+}
+
+/// The calls to a single syscall, aggregated from a syscall trace.
+#[cfg(feature = "syscall-trace")]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SyscallStats {
+    /// The name of the syscall, such as `sol_log_`.
+    pub name: String,
+    /// The number of calls to the syscall.
+    pub calls: u64,
+    /// The total number of compute units charged by the calls.
+    pub compute_units_consumed: u64,
+}
+
+/// A syscall trace aggregated by syscall name, sorted by compute units
+/// consumed in descending order.
+///
+/// Displays as a table, one syscall per line:
+///
+/// ```text
+/// sol_sha256: 12 calls, 1,020 CU
+/// sol_log_: 3 calls, 300 CU
+/// ```
+#[cfg(feature = "syscall-trace")]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SyscallSummary {
+    /// The aggregated calls to each syscall.
+    pub syscalls: Vec<SyscallStats>,
+}
+
+#[cfg(feature = "syscall-trace")]
+impl SyscallSummary {
+    /// Aggregate a syscall trace by syscall name.
+    pub fn from_trace(trace: &[SyscallTraceEntry]) -> Self {
+        let mut stats: HashMap<&str, SyscallStats> = HashMap::new();
+        for entry in trace {
+            let stats = stats.entry(&entry.name).or_insert_with(|| SyscallStats {
+                name: entry.name.clone(),
+                ..Default::default()
+            });
+            stats.calls += 1;
+            stats.compute_units_consumed += entry.compute_units_consumed;
+        }
+        let mut syscalls = stats.into_values().collect::<Vec<_>>();
+        syscalls.sort_by(|a, b| {
+            b.compute_units_consumed
+                .cmp(&a.compute_units_consumed)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        Self { syscalls }
+    }
+
+    /// Get the aggregated calls to a syscall by its name.
+    pub fn get(&self, name: &str) -> Option<&SyscallStats> {
+        self.syscalls.iter().find(|stats| stats.name == name)
+    }
+}
+
+#[cfg(feature = "syscall-trace")]
+impl fmt::Display for SyscallSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for stats in &self.syscalls {
+            let calls = if stats.calls == 1 { "call" } else { "calls" };
+            writeln!(
+                f,
+                "{}: {} {}, {} CU",
+                stats.name,
+                stats.calls.to_formatted_string(&Locale::en),
+                calls,
+                stats
+                    .compute_units_consumed
+                    .to_formatted_string(&Locale::en),
+            )?;
+        }
+        Ok(())
+    }
 }